@@ -1,30 +1,86 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
+use std::ops::ControlFlow;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
 use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Month, Months, NaiveDate, Weekday};
 use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use rrulex_core::{
-    CoreError, DateValueType, ExpandQuery, ExplainResult, Findings, RecurrenceSpec, canonical_json,
-    expand_result, explain, is_potentially_unbounded, lint, parse_ics_spec, parse_iso_datetime,
-    parse_timezone,
+    BusinessCalendar, CoreError, CountOptions, CountResult, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE,
+    DateValueType, DstPolicy, ErrorReport, EventDuration, ExpandQuery, ExpandResult, ExpansionSpan,
+    ExplainOptions, ExplainResult, Findings, FlatExplainResult, HeatmapKind, IcsOptions,
+    Occurrence, OccurrenceStyle, RecurrenceSpec, SampleMode, SimulateResult, SpecDiagnostics, Tz,
+    Window, apply_abbreviations, apply_business_calendar, apply_calendar_fields, apply_floating,
+    apply_heatmap, apply_periods, apply_rule_timezones, apply_sample, apply_source_filter,
+    apply_week_spans, canonical_json, canonicalize_rule, capabilities, combine_datetimes,
+    count_occurrences, days_over_limit, dedupe_dates, describe, expand_for_each_within,
+    expand_meta, expand_result, expand_result_within, explain_local_with, format_occurrence,
+    fullcalendar_events, ics_calendar, ics_calendar_compressed, invalid_rule_span,
+    is_potentially_unbounded, lint, merge_rrules, next_occurrence_within, occurrence_map,
+    one_period_end, parse_ics_event, parse_iso_datetime_with, parse_json_spec,
+    parse_json_spec_line, parse_rrule_shorthand, parse_timezone, previous_occurrence_within,
+    rdate_property, selftest, simulate, spec_to_json, summarize_series, timezone_fallback,
+    validate_spec,
 };
 
 #[derive(Parser, Debug)]
 #[command(version, about = "RFC5545 RRULE Expander + Linter + Explain")]
 struct Cli {
+    /// Show invalid RRULEs with the offending part underlined
+    #[arg(long, global = true)]
+    pretty_errors: bool,
+
+    /// How failures are written to stderr: text, or one `{"error": {code, message, ...}}` JSON line
+    #[arg(
+        long,
+        global = true,
+        default_value = "text",
+        conflicts_with = "pretty_errors"
+    )]
+    error_format: ErrorFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Expand occurrences for a recurrence specification.
-    Expand(ExpandArgs),
+    Expand(Box<ExpandArgs>),
     /// Lint recurrence rules for common footguns.
     Lint(LintArgs),
     /// Explain why a concrete datetime is included/excluded.
     Explain(ExplainArgs),
+    /// Count occurrences in a window without listing them.
+    Count(CountArgs),
+    /// List the next occurrences with the gap between consecutive fires.
+    Simulate(SimulateArgs),
+    /// Expand several ICS/JSON spec files with the same query.
+    Batch(BatchArgs),
+    /// Print the supported RRULE/ICS features as JSON.
+    Capabilities,
+    /// Describe each RRULE as an English sentence.
+    Describe(DescribeArgs),
+    /// Rewrite every RRULE/EXRULE with its parts in canonical order.
+    Normalize(NormalizeArgs),
+    /// Print the first occurrence strictly after --at.
+    Next(PivotArgs),
+    /// Print the last occurrence strictly before --at.
+    Prev(PivotArgs),
+    /// Check that a spec parses and its rules validate, without expanding.
+    Validate(ValidateArgs),
+    /// Run the built-in recurrence expectations; exits nonzero on any failure.
+    #[command(hide = true)]
+    Selftest(SelftestArgs),
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -33,12 +89,118 @@ enum OutputFormat {
     Text,
 }
 
-#[derive(Args, Debug, Clone)]
+/// `expand` renders occurrences in more shapes than the other subcommands.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExpandFormat {
+    Json,
+    Text,
+    /// A single folded RDATE property line
+    Rdate,
+    /// An iCalendar VCALENDAR with one VEVENT per occurrence
+    Ics,
+    /// Month grids of the window marking days with occurrences
+    Calendar,
+    /// One compact JSON object per line: the meta, then each occurrence
+    Jsonl,
+    /// A JSON array of FullCalendar `{title, start, end, allDay}` events
+    Fullcalendar,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+#[value(rename_all = "UPPER")]
+enum WeekStart {
+    Mo,
+    Tu,
+    We,
+    Th,
+    Fr,
+    Sa,
+    Su,
+}
+
+impl From<WeekStart> for Weekday {
+    fn from(value: WeekStart) -> Self {
+        match value {
+            WeekStart::Mo => Weekday::Mon,
+            WeekStart::Tu => Weekday::Tue,
+            WeekStart::We => Weekday::Wed,
+            WeekStart::Th => Weekday::Thu,
+            WeekStart::Fr => Weekday::Fri,
+            WeekStart::Sa => Weekday::Sat,
+            WeekStart::Su => Weekday::Sun,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SourceSelection {
+    Rrule,
+    Rdate,
+    All,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TextStyle {
+    Default,
+    Table,
+    Human,
+    Rfc3339,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum DstResolution {
+    #[default]
+    Reject,
+    Earliest,
+    Latest,
+    ShiftForward,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum EmptyPolicy {
+    Ok,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CountBreakdown {
+    Source,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum HeatmapAxes {
+    WeekdayHour,
+    MonthDay,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SampleStrategy {
+    Even,
+    Head,
+    Tail,
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OverflowPolicy {
+    Warn,
+    Error,
+}
+
+#[derive(Args, Debug, Clone, Default)]
 struct InputArgs {
     /// iCalendar input file (minimal parser for DTSTART/RRULE/RDATE/EXDATE/EXRULE)
     #[arg(long)]
     ics: Option<PathBuf>,
 
+    /// JSON spec file ({"dtstart", "tz", "rrule": [...], "rdate", "exrule", "exdate"})
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// ICS or JSON spec file, detected from the extension or contents
+    #[arg(long)]
+    input: Option<PathBuf>,
+
     /// DTSTART as ISO datetime/date
     #[arg(long)]
     dtstart: Option<String>,
@@ -47,223 +209,1353 @@ struct InputArgs {
     #[arg(long)]
     tz: Option<String>,
 
-    /// RRULE strings (repeatable)
-    #[arg(long, action = ArgAction::Append)]
-    rrule: Vec<String>,
+    /// RRULE strings (repeatable); shorthand like `daily` or `"weekly mon,wed"` is expanded
+    #[arg(long, action = ArgAction::Append)]
+    rrule: Vec<String>,
+
+    /// RDATE values (repeatable)
+    #[arg(long, action = ArgAction::Append)]
+    rdate: Vec<String>,
+
+    /// EXRULE strings (repeatable)
+    #[arg(long, action = ArgAction::Append)]
+    exrule: Vec<String>,
+
+    /// EXDATE values (repeatable)
+    #[arg(long, action = ArgAction::Append)]
+    exdate: Vec<String>,
+
+    /// Reject ICS input that RFC 5545 does not allow (e.g. a VEVENT without UID)
+    #[arg(long)]
+    rfc_strict: bool,
+
+    /// Select the VEVENT with this UID from a multi-event ICS file
+    #[arg(long)]
+    uid: Option<String>,
+
+    /// Occurrence length as ISO 8601 (e.g. PT1H30M, P1D); overrides DTEND/DURATION
+    #[arg(long)]
+    duration: Option<String>,
+
+    /// Treat a zoneless DTSTART as floating local time (`tz: null`) instead of rejecting it
+    #[arg(long, conflicts_with = "tz")]
+    floating: bool,
+
+    /// Resolve local times repeated (earliest/latest) or skipped (shift-forward) by a DST change
+    #[arg(long, default_value = "reject")]
+    dst_policy: DstResolution,
+}
+
+#[derive(Args, Debug)]
+struct ExpandArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Window [start end], inclusive unless --exclusive-end
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    between: Option<Vec<String>>,
+
+    /// Window start (with --end; alternative to --between)
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Window end (with --start; alternative to --between)
+    #[arg(long)]
+    end: Option<String>,
+
+    /// Leave an occurrence exactly at the window end out, making it [start, end)
+    #[arg(long)]
+    exclusive_end: bool,
+
+    /// Start datetime for after/count query
+    #[arg(long)]
+    after: Option<String>,
+
+    /// Number of occurrences to return with --after, or the first N of a --between window
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// Zone for naive --between/--start/--end/--after values (default: the spec's tz)
+    #[arg(long, value_name = "ZONE")]
+    window_tz: Option<String>,
+
+    /// With --after, return one FREQ*INTERVAL period of occurrences instead of --count
+    #[arg(long)]
+    after_one_period: bool,
+
+    /// Hard safety limit (default: 1000)
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Abort expansion after this many milliseconds of wall-clock time
+    #[arg(long, value_name = "MS")]
+    max_duration: Option<u64>,
+
+    /// Without a window, expand only DTSTART through DTSTART plus this ISO 8601 span (e.g. P1Y)
+    #[arg(long, value_name = "DURATION", conflicts_with_all = ["between", "start", "after"])]
+    max_span: Option<String>,
+
+    /// Replace DTSTART before expanding, to reuse the rules as a template
+    #[arg(long, value_name = "DATETIME")]
+    dtstart_override: Option<String>,
+
+    /// Keep only occurrences within local working hours (HH:MM-HH:MM)
+    #[arg(long, value_name = "HH:MM-HH:MM")]
+    business_hours: Option<String>,
+
+    /// Keep only occurrences on these local weekdays (e.g. MO-FR)
+    #[arg(long, value_name = "DAYS")]
+    business_days: Option<String>,
+
+    /// Print the parsed spec as canonical JSON and exit without expanding
+    #[arg(long)]
+    dump_spec: bool,
+
+    /// Merge RRULEs that differ only in one BYxxx list where provably equivalent
+    #[arg(long)]
+    merge_rrules_into_one: bool,
+
+    /// Display timezone for one RRULE's occurrences as INDEX=ZONE (repeatable)
+    #[arg(long, value_name = "INDEX=ZONE", action = ArgAction::Append)]
+    rrule_tz: Vec<String>,
+
+    /// Only output occurrences from this source (EXDATE/EXRULE still apply to all)
+    #[arg(long, default_value = "all")]
+    source: SourceSelection,
+
+    /// Whether an empty occurrence set is a success or an error
+    #[arg(long, default_value = "ok")]
+    empty: EmptyPolicy,
+
+    /// Flag local days with more than N occurrences
+    #[arg(long, value_name = "N")]
+    max_per_day: Option<usize>,
+
+    /// Whether exceeding --max-per-day adds a note or fails
+    #[arg(long, default_value = "warn")]
+    on_max_per_day: OverflowPolicy,
+
+    /// Summarize FREQ=WEEKLY schedules as spans of consecutive active ISO weeks
+    #[arg(long)]
+    week_spans: bool,
+
+    /// Annotate RRULE occurrences with their FREQ/INTERVAL period bounds
+    #[arg(long)]
+    with_period: bool,
+
+    /// Add each occurrence's zone abbreviation (e.g. CET/CEST) as `abbrev`
+    #[arg(long)]
+    with_abbrev: bool,
+
+    /// Add each occurrence's local weekday, day of year, ISO week and ISO year
+    #[arg(long)]
+    with_calendar_fields: bool,
+
+    /// Aggregate the --between window into a count grid by local time
+    #[arg(long)]
+    heatmap: Option<HeatmapAxes>,
+
+    /// Keep only N representative occurrences (see --sample-mode)
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// How --sample picks occurrences
+    #[arg(long, default_value = "even")]
+    sample_mode: SampleStrategy,
+
+    /// Seed for --sample-mode random (default: 0)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Emit one RFC3339 `start` with offset per occurrence instead of start_local/start_utc
+    #[arg(long)]
+    combined_datetime: bool,
+
+    /// Emit JSON `occurrences` as an object keyed by instant instead of an array
+    #[arg(long)]
+    as_map: bool,
+
+    /// First weekday of --format calendar grids
+    #[arg(long, value_enum, default_value = "MO", ignore_case = true)]
+    wkst: WeekStart,
+
+    /// Occurrence line layout for --format text
+    #[arg(long, default_value = "default")]
+    style: TextStyle,
+
+    /// PRODID for --format ics output
+    #[arg(long, default_value = DEFAULT_PRODID, allow_hyphen_values = true)]
+    prodid: String,
+
+    /// Per-event UID for --format ics ({index}, {start_utc}, {uid}, {hash})
+    #[arg(long, default_value = DEFAULT_UID_TEMPLATE)]
+    uid_template: String,
+
+    /// With --format ics, emit one VEVENT with a detected RRULE (or an RDATE list) instead of one per occurrence
+    #[arg(long)]
+    ics_compress: bool,
+
+    #[arg(long, default_value = "json")]
+    format: ExpandFormat,
+}
+
+#[derive(Args, Debug)]
+struct LintArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Optional context window [start end] for unbounded warning suppression
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    between: Option<Vec<String>>,
+
+    /// Optional context limit for unbounded warning suppression
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct DescribeArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Summarize the whole series: first/last occurrence, count and exclusions
+    #[arg(long)]
+    explain_series: bool,
+
+    /// Window [start end] inclusive for --explain-series
+    #[arg(long, num_args = 2, value_names = ["START", "END"], requires = "explain_series")]
+    between: Option<Vec<String>>,
+
+    /// Hard safety limit for --explain-series (default: 1000)
+    #[arg(long, requires = "explain_series")]
+    limit: Option<usize>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct NormalizeArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct ValidateArgs {
+    #[command(flatten)]
+    input: InputArgs,
+}
+
+#[derive(Args, Debug)]
+struct SelftestArgs {
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct PivotArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Pivot datetime (exclusive)
+    #[arg(long)]
+    at: String,
+
+    /// Abort the search after this many milliseconds of wall-clock time
+    #[arg(long, value_name = "MS")]
+    max_duration: Option<u64>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct ExplainArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Datetime to explain
+    #[arg(long)]
+    at: String,
+
+    /// List every matching EXDATE/EXRULE (with indices) under `exclusions`
+    #[arg(long)]
+    all_exclusions: bool,
+
+    /// When no rule generates --at, report which conditions of the closest RRULE fail
+    #[arg(long)]
+    why_not: bool,
+
+    /// Emit one flat object (at, included, source, rule_index, excluded_by) without notes
+    #[arg(long)]
+    flat: bool,
+
+    /// Suggest the nearest occurrence when it is at most this far from --at (ISO 8601, e.g. PT15M)
+    #[arg(long, value_name = "DURATION", default_value = "PT1H")]
+    suggest_within: String,
+
+    /// Abort the nearest_before/nearest_after search after this many milliseconds
+    #[arg(long, value_name = "MS")]
+    max_duration: Option<u64>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct CountArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Window [start end] inclusive; required for unbounded rules unless --limit is given
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    between: Option<Vec<String>>,
+
+    /// Stop counting once N occurrences were found
+    #[arg(long, value_name = "N")]
+    at_least: Option<usize>,
+
+    /// Also report the count per breakdown key (`source`: RRULE vs RDATE)
+    #[arg(long)]
+    by: Option<CountBreakdown>,
+
+    /// Hard safety limit (default: 1000)
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct SimulateArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Start datetime; occurrences at or after it are listed
+    #[arg(long)]
+    after: String,
+
+    /// Number of occurrences to list
+    #[arg(long, default_value_t = 10)]
+    count: usize,
+
+    /// Hard safety limit (default: 1000)
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// ICS or JSON spec files, detected from the extension or contents
+    #[arg(
+        required_unless_present = "ndjson",
+        conflicts_with = "ndjson",
+        value_name = "FILE"
+    )]
+    inputs: Vec<PathBuf>,
+
+    /// Read one JSON spec per line and print one `{id, result}` line per input line
+    #[arg(long, value_name = "FILE")]
+    ndjson: Option<PathBuf>,
+
+    /// With --ndjson, print `{id, error: {code, message}}` for failing lines and keep going
+    #[arg(long, requires = "ndjson")]
+    json_lines_errors: bool,
+
+    /// Fallback IANA timezone for inputs that carry none
+    #[arg(long)]
+    tz: Option<String>,
+
+    /// Reject ICS inputs that violate RFC 5545 requirements
+    #[arg(long)]
+    rfc_strict: bool,
+
+    /// Window [start end] inclusive
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    between: Option<Vec<String>>,
+
+    /// Start datetime for after/count query
+    #[arg(long)]
+    after: Option<String>,
+
+    /// Number of occurrences to return with --after
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// Hard safety limit per input (default: 1000)
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Collect failing inputs under `errors` instead of aborting; exit nonzero only if all fail
+    #[arg(long, conflicts_with = "ndjson")]
+    continue_on_error: bool,
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Commands::Expand(args) => run_expand(*args),
+        Commands::Lint(args) => run_lint(args),
+        Commands::Explain(args) => run_explain(args),
+        Commands::Count(args) => run_count(args),
+        Commands::Simulate(args) => run_simulate(args),
+        Commands::Batch(args) => run_batch(args),
+        Commands::Capabilities => print_json(&capabilities()),
+        Commands::Describe(args) => run_describe(args),
+        Commands::Normalize(args) => run_normalize(args),
+        Commands::Next(args) => run_pivot(args, next_occurrence_within),
+        Commands::Prev(args) => run_pivot(args, previous_occurrence_within),
+        Commands::Validate(args) => return Ok(run_validate(args)),
+        Commands::Selftest(args) => run_selftest(args),
+    }?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_expand(args: ExpandArgs) -> Result<()> {
+    let mut spec = build_spec(&args.input)?;
+    let mut notes = Vec::new();
+    if let Some(raw) = &args.dtstart_override {
+        let tz = parse_timezone(&spec.tz)?;
+        let (dtstart, dtstart_type) = parse_iso_datetime_with(raw, &tz, args.input.dst_policy())?;
+        let original = spec.dtstart.format("%Y-%m-%dT%H:%M:%S").to_string();
+        spec.override_dtstart(dtstart, dtstart_type)?;
+        notes.push(format!("DTSTART overridden (was {original})"));
+    }
+    if args.merge_rrules_into_one {
+        let merged = merge_rrules(&spec.rrules);
+        spec.rrules = merged.rrules;
+        notes.extend(merged.notes);
+    }
+    if args.dump_spec {
+        return print_json(&spec_to_json(&spec));
+    }
+    let hard_limit = args.limit.unwrap_or(1000);
+
+    if hard_limit == 0 {
+        return Err(anyhow!(CoreError::InvalidLimit(hard_limit)));
+    }
+
+    if args.as_map && !matches!(args.format, ExpandFormat::Json) {
+        bail!("--as-map requires --format json");
+    }
+    if !matches!(args.style, TextStyle::Default) && !matches!(args.format, ExpandFormat::Text) {
+        bail!("--style requires --format text");
+    }
+    if args.ics_compress && !matches!(args.format, ExpandFormat::Ics) {
+        bail!("--ics-compress requires --format ics");
+    }
+    // The other formats render from start_local/start_utc, which combining
+    // replaces.
+    if args.combined_datetime
+        && !matches!(
+            args.format,
+            ExpandFormat::Json | ExpandFormat::Jsonl | ExpandFormat::Text
+        )
+    {
+        bail!("--combined-datetime requires --format json, jsonl or text");
+    }
+
+    let between = resolve_between(args.between.clone(), args.start.clone(), args.end.clone())?;
+    if args.exclusive_end && between.is_none() {
+        bail!("--exclusive-end requires a --between (or --start/--end) window");
+    }
+    if args.heatmap.is_some() && between.is_none() {
+        bail!("--heatmap requires a --between (or --start/--end) window");
+    }
+    if matches!(args.format, ExpandFormat::Calendar) && between.is_none() {
+        bail!("--format calendar requires a --between (or --start/--end) window");
+    }
+    let mut query = build_query(
+        &spec,
+        between.as_ref(),
+        args.after.as_deref(),
+        args.count,
+        args.after_one_period,
+        WindowInput {
+            tz: args.window_tz.as_deref(),
+            dst_policy: args.input.dst_policy(),
+        },
+        args.exclusive_end,
+    )?;
+    // clap keeps --max-span away from --between/--after, so the query is
+    // otherwise unbounded.
+    if let Some(span) = &args.max_span {
+        let end = ExpansionSpan::parse(span)?.end_from(spec.dtstart)?;
+        query = ExpandQuery::Before { end };
+    }
+
+    if matches!(query, ExpandQuery::Unbounded)
+        && is_potentially_unbounded(&spec)
+        && args.limit.is_none()
+    {
+        return Err(anyhow!(CoreError::UnsafeUnboundedRule));
+    }
+
+    if spec.floating && (args.with_abbrev || args.combined_datetime || !args.rrule_tz.is_empty()) {
+        bail!(
+            "floating occurrences have no zone; --with-abbrev, --combined-datetime and --rrule-tz need one"
+        );
+    }
+
+    let budget = args.max_duration.map(std::time::Duration::from_millis);
+    let options = OccurrenceOptions {
+        args: &args,
+        spec: &spec,
+        zones: parse_rule_timezones(&args.rrule_tz)?,
+        calendar: build_business_calendar(
+            args.business_hours.as_deref(),
+            args.business_days.as_deref(),
+        )?,
+    };
+
+    // Unless an option needs the whole set, JSON Lines are written as the
+    // occurrences are generated.
+    let needs_whole_set = args.sample.is_some()
+        || args.week_spans
+        || args.heatmap.is_some()
+        || args.max_per_day.is_some()
+        || matches!(args.empty, EmptyPolicy::Error);
+    if matches!(args.format, ExpandFormat::Jsonl) && !needs_whole_set {
+        return stream_expand_jsonl(&options, &query, hard_limit, budget, notes);
+    }
+
+    let mut result = expand_result_within(&spec, &query, hard_limit, budget)?;
+    result.meta.notes.extend(notes);
+    options.apply(&mut result)?;
+
+    if matches!(args.empty, EmptyPolicy::Error) && result.occurrences.is_empty() {
+        bail!("expansion produced no occurrences (--empty error)");
+    }
+
+    if let Some(max) = args.max_per_day {
+        let over = days_over_limit(&result.occurrences, max);
+        let summary = over
+            .iter()
+            .map(|day| format!("{} has {} occurrences", day.date, day.count))
+            .collect::<Vec<_>>();
+        match args.on_max_per_day {
+            OverflowPolicy::Error if !over.is_empty() => {
+                bail!("--max-per-day {max} exceeded: {}", summary.join(", "))
+            }
+            _ => result.meta.notes.extend(
+                summary
+                    .into_iter()
+                    .map(|line| format!("{line} (more than --max-per-day {max})")),
+            ),
+        }
+    }
+
+    if args.week_spans {
+        apply_week_spans(&mut result, &spec);
+    }
+
+    if let Some(axes) = args.heatmap {
+        let kind = match axes {
+            HeatmapAxes::WeekdayHour => HeatmapKind::WeekdayHour,
+            HeatmapAxes::MonthDay => HeatmapKind::MonthDay,
+        };
+        apply_heatmap(&mut result, kind);
+    }
+
+    if let Some(size) = args.sample {
+        if size == 0 {
+            bail!("--sample must be > 0");
+        }
+        let mode = match args.sample_mode {
+            SampleStrategy::Even => SampleMode::Even,
+            SampleStrategy::Head => SampleMode::Head,
+            SampleStrategy::Tail => SampleMode::Tail,
+            SampleStrategy::Random => SampleMode::Random {
+                seed: args.seed.unwrap_or(0),
+            },
+        };
+        apply_sample(&mut result, size, mode);
+    }
+
+    options.render(&mut result)?;
+
+    if !matches!(args.format, ExpandFormat::Json | ExpandFormat::Jsonl) {
+        for warning in &result.meta.warnings {
+            eprintln!("warning: {warning}");
+        }
+    }
+
+    match args.format {
+        ExpandFormat::Json if args.as_map => {
+            let mut doc = serde_json::to_value(&result)?;
+            doc["occurrences"] = occurrence_map(&result.occurrences)?;
+            print_json(&doc)?;
+        }
+        ExpandFormat::Json => print_json(&result)?,
+        ExpandFormat::Jsonl => print_expand_jsonl(&result)?,
+        ExpandFormat::Fullcalendar => print_json(&fullcalendar_events(&spec, &result.occurrences))?,
+        ExpandFormat::Text => {
+            let style = match args.style {
+                TextStyle::Default => OccurrenceStyle::Default,
+                TextStyle::Table => OccurrenceStyle::Table,
+                TextStyle::Human => OccurrenceStyle::Human,
+                TextStyle::Rfc3339 => OccurrenceStyle::Rfc3339,
+            };
+            print_expand_text(&result.occurrences, style);
+            if !result.meta.is_finite {
+                eprintln!("note: infinite series; showing a window of it");
+            }
+        }
+        ExpandFormat::Rdate => print!("{}", rdate_property(&spec, &result.occurrences)?),
+        ExpandFormat::Calendar => {
+            let window = query.window();
+            let (Some(start), Some(end)) = (window.start, window.end) else {
+                bail!("--format calendar requires a --between (or --start/--end) window");
+            };
+            print!(
+                "{}",
+                render_calendar(
+                    &result.occurrences,
+                    start.date_naive(),
+                    end.date_naive(),
+                    args.wkst.into()
+                )
+            );
+        }
+        ExpandFormat::Ics if args.ics_compress => print!(
+            "{}",
+            ics_calendar_compressed(&spec, &result.occurrences, &args.prodid, &args.uid_template)?
+        ),
+        ExpandFormat::Ics => print!(
+            "{}",
+            ics_calendar(&spec, &result.occurrences, &args.prodid, &args.uid_template)?
+        ),
+    }
+
+    Ok(())
+}
+
+/// The `expand` options that transform each occurrence on its own, so both
+/// the buffered and the streamed output can apply them.
+struct OccurrenceOptions<'a> {
+    args: &'a ExpandArgs,
+    spec: &'a RecurrenceSpec,
+    zones: BTreeMap<usize, String>,
+    calendar: Option<BusinessCalendar>,
+}
+
+impl OccurrenceOptions<'_> {
+    /// Filters and annotations, applied right after expansion.
+    fn apply(&self, result: &mut ExpandResult) -> Result<()> {
+        match self.args.source {
+            SourceSelection::Rrule => {
+                apply_source_filter(result, rrulex_core::OccurrenceSource::Rrule)
+            }
+            SourceSelection::Rdate => {
+                apply_source_filter(result, rrulex_core::OccurrenceSource::Rdate)
+            }
+            SourceSelection::All => {}
+        }
+
+        if self.args.with_period {
+            apply_periods(result, self.spec)?;
+        }
+
+        if !self.zones.is_empty() {
+            apply_rule_timezones(result, &self.zones)?;
+        }
+
+        if self.args.with_abbrev {
+            apply_abbreviations(result)?;
+        }
+
+        if self.args.with_calendar_fields {
+            apply_calendar_fields(result);
+        }
+
+        if let Some(calendar) = &self.calendar {
+            apply_business_calendar(result, calendar);
+        }
+        Ok(())
+    }
+
+    /// Reshaping for output, applied last.
+    fn render(&self, result: &mut ExpandResult) -> Result<()> {
+        if self.args.combined_datetime {
+            combine_datetimes(result)?;
+        }
+
+        if !matches!(self.args.format, ExpandFormat::Rdate | ExpandFormat::Ics) {
+            apply_floating(result, self.spec);
+        }
+        Ok(())
+    }
+}
+
+/// `--format jsonl` without options that need the whole set: the meta line,
+/// then each occurrence written and flushed as soon as it is generated.
+fn stream_expand_jsonl(
+    options: &OccurrenceOptions,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    budget: Option<std::time::Duration>,
+    notes: Vec<String>,
+) -> Result<()> {
+    let mut head = ExpandResult {
+        meta: expand_meta(options.spec, query, hard_limit)?,
+        occurrences: Vec::new(),
+        week_spans: None,
+        heatmap: None,
+    };
+    head.meta.notes.extend(notes);
+    // Records the filters and the floating zone in the meta.
+    options.apply(&mut head)?;
+    options.render(&mut head)?;
+    write_jsonl_meta(&head.meta)?;
+
+    let mut returned = 0;
+    let mut failed = None;
+    let mut scratch = head;
+    let generated = expand_for_each_within(options.spec, query, hard_limit, budget, |occ| {
+        scratch.occurrences = vec![occ];
+        let written = options
+            .apply(&mut scratch)
+            .and_then(|()| options.render(&mut scratch))
+            .and_then(|()| {
+                for occ in &scratch.occurrences {
+                    write_jsonl_line("occurrence", serde_json::to_value(occ)?)?;
+                    returned += 1;
+                }
+                Ok(())
+            });
+        match written {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                failed = Some(err);
+                ControlFlow::Break(())
+            }
+        }
+    })?;
+    if let Some(err) = failed {
+        return Err(err);
+    }
+    write_jsonl_end(returned, generated == hard_limit)
+}
+
+fn run_lint(args: LintArgs) -> Result<()> {
+    let spec = build_spec(&args.input)?;
+    let findings = lint(&spec, args.between.is_some(), args.limit.is_some());
+
+    match args.format {
+        OutputFormat::Json => print_json(&findings)?,
+        OutputFormat::Text => print_lint_text(&findings),
+    }
+
+    Ok(())
+}
+
+fn run_describe(args: DescribeArgs) -> Result<()> {
+    let spec = build_spec(&args.input)?;
+    if args.explain_series {
+        return run_explain_series(&args, &spec);
+    }
+    let descriptions = describe(&spec);
+
+    match args.format {
+        OutputFormat::Json => {
+            let rules: Vec<_> = spec
+                .rrules
+                .iter()
+                .zip(&descriptions)
+                .map(|(rule, text)| serde_json::json!({ "rrule": rule, "text": text }))
+                .collect();
+            print_json(&serde_json::json!({ "descriptions": rules }))?;
+        }
+        OutputFormat::Text => {
+            for text in &descriptions {
+                println!("{text}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the verdict on stdout as the only output, so failures are not
+/// reported a second time on stderr.
+fn run_validate(args: ValidateArgs) -> ExitCode {
+    let checked = build_spec(&args.input).and_then(|spec| Ok(validate_spec(&spec)?));
+    let (verdict, code) = match &checked {
+        Ok(()) => (serde_json::json!({ "valid": true }), ExitCode::SUCCESS),
+        Err(err) => (
+            serde_json::json!({ "valid": false, "error": error_report(err) }),
+            ExitCode::from(exit_code_for_error(err)),
+        ),
+    };
+    match print_json(&verdict) {
+        Ok(()) => code,
+        Err(err) => {
+            eprintln!("Error: {err:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_selftest(args: SelftestArgs) -> Result<()> {
+    let results = selftest();
+    let failed = results.iter().filter(|result| !result.passed).count();
+    let passed = results.len() - failed;
 
-    /// RDATE values (repeatable)
-    #[arg(long, action = ArgAction::Append)]
-    rdate: Vec<String>,
+    match args.format {
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "cases": results,
+            "passed": passed,
+            "failed": failed,
+        }))?,
+        OutputFormat::Text => {
+            for result in &results {
+                if result.passed {
+                    println!("ok   {}", result.name);
+                    continue;
+                }
+                println!("FAIL {}", result.name);
+                println!("  expected: {}", result.expected.join(", "));
+                match &result.error {
+                    Some(error) => println!("  error: {error}"),
+                    None => println!("  actual:   {}", result.actual.join(", ")),
+                }
+            }
+            println!("{passed} passed, {failed} failed");
+        }
+    }
 
-    /// EXRULE strings (repeatable)
-    #[arg(long, action = ArgAction::Append)]
-    exrule: Vec<String>,
+    if failed > 0 {
+        bail!("{failed} of {} selftest cases failed", results.len());
+    }
+    Ok(())
+}
 
-    /// EXDATE values (repeatable)
-    #[arg(long, action = ArgAction::Append)]
-    exdate: Vec<String>,
+fn run_explain_series(args: &DescribeArgs, spec: &RecurrenceSpec) -> Result<()> {
+    let window = match &args.between {
+        Some(between) => {
+            let tz = parse_timezone(&spec.tz)?;
+            let (start, end) = parse_between(between, &tz, args.input.dst_policy())?;
+            Window::from_between(start, end)?
+        }
+        None => Window::unbounded(),
+    };
+    let summary = summarize_series(spec, &window, args.limit.unwrap_or(1000))?;
+
+    match args.format {
+        OutputFormat::Json => print_json(&summary)?,
+        OutputFormat::Text => {
+            for text in &summary.description {
+                println!("{text}");
+            }
+            let or_none = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+            println!("first: {}", or_none(&summary.first));
+            println!("last: {}", or_none(&summary.last));
+            println!(
+                "count: {}",
+                summary
+                    .count
+                    .map_or("unbounded".to_string(), |count| count.to_string())
+            );
+            match (summary.exdates, summary.exrules) {
+                (Some(exdates), Some(exrules)) => {
+                    println!("exclusions: {exdates} EXDATE, {exrules} EXRULE");
+                }
+                _ => println!("exclusions: unbounded"),
+            }
+        }
+    }
+    Ok(())
 }
 
-#[derive(Args, Debug)]
-struct ExpandArgs {
-    #[command(flatten)]
-    input: InputArgs,
+fn run_normalize(args: NormalizeArgs) -> Result<()> {
+    let mut spec = build_spec(&args.input)?;
+    let canonical = |rules: &[String]| {
+        rules
+            .iter()
+            .map(|rule| canonicalize_rule(rule))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    spec.rrules = canonical(&spec.rrules)?;
+    spec.exrules = canonical(&spec.exrules)?;
 
-    /// Window [start end] inclusive
-    #[arg(long, num_args = 2, value_names = ["START", "END"])]
-    between: Option<Vec<String>>,
+    match args.format {
+        OutputFormat::Json => print_json(&spec_to_json(&spec))?,
+        OutputFormat::Text => {
+            for rule in &spec.rrules {
+                println!("RRULE:{rule}");
+            }
+            for rule in &spec.exrules {
+                println!("EXRULE:{rule}");
+            }
+        }
+    }
 
-    /// Start datetime for after/count query
-    #[arg(long)]
-    after: Option<String>,
+    Ok(())
+}
 
-    /// Number of occurrences to return with --after
-    #[arg(long)]
-    count: Option<usize>,
+fn run_explain(args: ExplainArgs) -> Result<()> {
+    let spec = build_spec(&args.input)?;
+    let within = EventDuration::parse(&args.suggest_within)?;
+    let options = ExplainOptions {
+        all_exclusions: args.all_exclusions,
+        why_not: args.why_not,
+        suggest_within: Some(within.to_exact()?),
+        budget: args.max_duration.map(std::time::Duration::from_millis),
+    };
+    let result = explain_local_with(&spec, &args.at, options)?;
 
-    /// Hard safety limit (default: 1000)
-    #[arg(long)]
-    limit: Option<usize>,
+    if args.flat {
+        let flat = result.flat();
+        match args.format {
+            OutputFormat::Json => print_json(&flat)?,
+            OutputFormat::Text => print_flat_explain_text(&flat),
+        }
+        return Ok(());
+    }
 
-    #[arg(long, default_value = "json")]
-    format: OutputFormat,
+    match args.format {
+        OutputFormat::Json => print_json(&result)?,
+        OutputFormat::Text => print_explain_text(&result),
+    }
+
+    Ok(())
 }
 
-#[derive(Args, Debug)]
-struct LintArgs {
-    #[command(flatten)]
-    input: InputArgs,
+fn run_count(args: CountArgs) -> Result<()> {
+    let spec = build_spec(&args.input)?;
+    let tz = parse_timezone(&spec.tz)?;
+    let window = match &args.between {
+        Some(between) => {
+            let (start, end) = parse_between(between, &tz, args.input.dst_policy())?;
+            Window::from_between(start, end)?
+        }
+        None if is_potentially_unbounded(&spec) && args.limit.is_none() => {
+            return Err(anyhow!(CoreError::UnsafeUnboundedRule));
+        }
+        None => Window::unbounded(),
+    };
 
-    /// Optional context window [start end] for unbounded warning suppression
-    #[arg(long, num_args = 2, value_names = ["START", "END"])]
-    between: Option<Vec<String>>,
+    let options = CountOptions {
+        at_least: args.at_least,
+        by_source: matches!(args.by, Some(CountBreakdown::Source)),
+    };
+    let result = count_occurrences(&spec, &window, options, args.limit.unwrap_or(1000))?;
 
-    /// Optional context limit for unbounded warning suppression
-    #[arg(long)]
-    limit: Option<usize>,
+    match args.format {
+        OutputFormat::Json => print_json(&result)?,
+        OutputFormat::Text => print_count_text(&result),
+    }
 
-    #[arg(long, default_value = "json")]
-    format: OutputFormat,
+    Ok(())
 }
 
-#[derive(Args, Debug)]
-struct ExplainArgs {
-    #[command(flatten)]
-    input: InputArgs,
+fn run_batch(args: BatchArgs) -> Result<()> {
+    if let Some(path) = &args.ndjson {
+        return run_batch_ndjson(&args, path);
+    }
 
-    /// Datetime to explain
-    #[arg(long)]
-    at: String,
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
 
-    #[arg(long, default_value = "json")]
-    format: OutputFormat,
-}
+    for path in &args.inputs {
+        let input = InputArgs {
+            input: Some(path.clone()),
+            tz: args.tz.clone(),
+            rfc_strict: args.rfc_strict,
+            ..InputArgs::default()
+        };
+        let expanded = build_spec(&input).and_then(|spec| batch_expand(&args, &spec));
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+        let name = path.display().to_string();
+        match expanded {
+            Ok(result) => results.push(serde_json::json!({ "input": name, "result": result })),
+            Err(err) if args.continue_on_error => {
+                errors.push(serde_json::json!({ "input": name, "error": format!("{err:#}") }));
+            }
+            Err(err) => return Err(err.context(name)),
+        }
+    }
 
-    match cli.command {
-        Commands::Expand(args) => run_expand(args),
-        Commands::Lint(args) => run_lint(args),
-        Commands::Explain(args) => run_explain(args),
+    print_json(&serde_json::json!({ "results": results, "errors": errors }))?;
+    if results.is_empty() {
+        bail!("all {} inputs failed", errors.len());
     }
+    Ok(())
 }
 
-fn run_expand(args: ExpandArgs) -> Result<()> {
-    let spec = build_spec(&args.input)?;
-    let hard_limit = args.limit.unwrap_or(1000);
+/// Expands every line of an NDJSON file as a JSON spec. Each line may carry an
+/// `id` member that is echoed back; otherwise the 1-based line number is used.
+fn run_batch_ndjson(args: &BatchArgs, path: &PathBuf) -> Result<()> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read input file {}", path.display()))?;
+    let mut succeeded = 0;
+    let mut failed = 0;
 
-    if hard_limit == 0 {
-        return Err(anyhow!(CoreError::InvalidLimit(hard_limit)));
+    for (index, line) in raw.lines().enumerate() {
+        let (given, spec) = parse_json_spec_line(line, args.tz.as_deref());
+        let id = given.unwrap_or_else(|| serde_json::json!(index + 1));
+        let expanded = spec
+            .map_err(anyhow::Error::from)
+            .and_then(|spec| batch_expand(args, &spec));
+
+        match expanded {
+            Ok(result) => {
+                succeeded += 1;
+                print_json_line(&serde_json::json!({ "id": id, "result": result }))?;
+            }
+            Err(err) if args.json_lines_errors => {
+                failed += 1;
+                print_json_line(&serde_json::json!({
+                    "id": id,
+                    "error": error_report(&err),
+                }))?;
+            }
+            Err(err) => return Err(err.context(format!("{} line {}", path.display(), index + 1))),
+        }
+    }
+
+    if succeeded == 0 && failed > 0 {
+        bail!("all {failed} lines failed");
     }
+    Ok(())
+}
 
+fn batch_expand(args: &BatchArgs, spec: &RecurrenceSpec) -> Result<ExpandResult> {
     let query = build_query(
-        &spec,
+        spec,
         args.between.as_ref(),
         args.after.as_deref(),
         args.count,
+        false,
+        WindowInput {
+            tz: None,
+            dst_policy: DstPolicy::Reject,
+        },
+        false,
     )?;
-
     if matches!(query, ExpandQuery::Unbounded)
-        && is_potentially_unbounded(&spec)
+        && is_potentially_unbounded(spec)
         && args.limit.is_none()
     {
         return Err(anyhow!(CoreError::UnsafeUnboundedRule));
     }
+    let mut result = expand_result(spec, &query, args.limit.unwrap_or(1000))?;
+    apply_floating(&mut result, spec);
+    Ok(result)
+}
 
-    let result = expand_result(&spec, &query, hard_limit)?;
+fn parse_rule_timezones(values: &[String]) -> Result<BTreeMap<usize, String>> {
+    let mut zones = BTreeMap::new();
+    for value in values {
+        let (index, zone) = value
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--rrule-tz expects INDEX=ZONE, got '{value}'"))?;
+        let index: usize = index
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid rule index in --rrule-tz '{value}'"))?;
+        zones.insert(index, zone.trim().to_string());
+    }
+    Ok(zones)
+}
 
-    match args.format {
-        OutputFormat::Json => print_json(&result)?,
-        OutputFormat::Text => print_expand_text(&result.occurrences),
+fn build_business_calendar(
+    hours: Option<&str>,
+    days: Option<&str>,
+) -> Result<Option<BusinessCalendar>> {
+    if hours.is_none() && days.is_none() {
+        return Ok(None);
     }
 
-    Ok(())
+    let mut calendar = BusinessCalendar::default();
+    if let Some(hours) = hours {
+        (calendar.start, calendar.end) = BusinessCalendar::parse_hours(hours)?;
+    }
+    if let Some(days) = days {
+        calendar.days = BusinessCalendar::parse_days(days)?;
+    }
+    Ok(Some(calendar))
 }
 
-fn run_lint(args: LintArgs) -> Result<()> {
+/// `next_occurrence_within` or `previous_occurrence_within`.
+type PivotSearch = fn(
+    &RecurrenceSpec,
+    DateTime<Tz>,
+    Option<std::time::Duration>,
+) -> Result<Option<Occurrence>, CoreError>;
+
+fn run_pivot(args: PivotArgs, find: PivotSearch) -> Result<()> {
     let spec = build_spec(&args.input)?;
-    let findings = lint(&spec, args.between.is_some(), args.limit.is_some());
+    let tz = parse_timezone(&spec.tz)?;
+    let (at, _) = parse_iso_datetime_with(&args.at, &tz, args.input.dst_policy())?;
+    let budget = args.max_duration.map(std::time::Duration::from_millis);
+    let occurrence = find(&spec, at, budget)?;
 
     match args.format {
-        OutputFormat::Json => print_json(&findings)?,
-        OutputFormat::Text => print_lint_text(&findings),
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "at": at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "occurrence": occurrence,
+        }))?,
+        OutputFormat::Text => match &occurrence {
+            Some(occ) => print_expand_text(std::slice::from_ref(occ), OccurrenceStyle::Default),
+            None => println!("none"),
+        },
     }
 
     Ok(())
 }
 
-fn run_explain(args: ExplainArgs) -> Result<()> {
+fn run_simulate(args: SimulateArgs) -> Result<()> {
     let spec = build_spec(&args.input)?;
     let tz = parse_timezone(&spec.tz)?;
-    let (at, _) = parse_iso_datetime(&args.at, &tz)?;
+    let (start, _) = parse_iso_datetime_with(&args.after, &tz, args.input.dst_policy())?;
 
-    let result = explain(&spec, at)?;
+    let result = simulate(&spec, start, args.count, args.limit.unwrap_or(1000))?;
 
     match args.format {
         OutputFormat::Json => print_json(&result)?,
-        OutputFormat::Text => print_explain_text(&result),
+        OutputFormat::Text => print_simulate_text(&result),
     }
 
     Ok(())
 }
 
+/// How naive `--between`/`--after` values are read: in `--window-tz` (the
+/// spec's zone when `None`) with the input's `--dst-policy`.
+#[derive(Clone, Copy)]
+struct WindowInput<'a> {
+    tz: Option<&'a str>,
+    dst_policy: DstPolicy,
+}
+
 fn build_query(
     spec: &RecurrenceSpec,
     between: Option<&Vec<String>>,
     after: Option<&str>,
     count: Option<usize>,
+    after_one_period: bool,
+    input: WindowInput<'_>,
+    exclusive_end: bool,
 ) -> Result<ExpandQuery> {
     let has_between = between.is_some();
     let has_after = after.is_some();
     let has_count = count.is_some();
 
-    if has_between && (has_after || has_count) {
-        bail!("--between cannot be combined with --after/--count");
-    }
-
-    if has_after ^ has_count {
-        bail!("--after and --count must be provided together");
+    if has_between && has_after {
+        bail!("--between cannot be combined with --after");
     }
 
     let tz = parse_timezone(&spec.tz)?;
+    // Naive window values are read in --window-tz (default: the spec's zone)
+    // and then carried in the spec's zone like every other datetime.
+    let input_tz = match input.tz {
+        Some(name) => parse_timezone(name)?,
+        None => tz,
+    };
+    let parse = |value: &str| -> Result<DateTime<Tz>> {
+        let (dt, _) = parse_iso_datetime_with(value, &input_tz, input.dst_policy)?;
+        Ok(dt.with_timezone(&tz))
+    };
+
+    if after_one_period {
+        let Some(after) = after else {
+            bail!("--after-one-period requires --after");
+        };
+        if has_count {
+            bail!("--after-one-period cannot be combined with --count");
+        }
+        let start = parse(after)?;
+        let end = one_period_end(spec, start)?;
+        return Ok(ExpandQuery::Between {
+            start,
+            end,
+            inclusive_end: true,
+            count: None,
+        });
+    }
 
+    // With a window, --count alone keeps its first N occurrences.
     if let Some(values) = between {
-        let (start, _) = parse_iso_datetime(&values[0], &tz)?;
-        let (end, _) = parse_iso_datetime(&values[1], &tz)?;
-        if start > end {
-            bail!("--between start must be <= end");
+        if count == Some(0) {
+            return Err(anyhow!(CoreError::InvalidCount(0)));
         }
-        return Ok(ExpandQuery::Between { start, end });
+        let (start, end) = parse_between(values, &input_tz, input.dst_policy)?;
+        return Ok(ExpandQuery::Between {
+            start: start.with_timezone(&tz),
+            end: end.with_timezone(&tz),
+            inclusive_end: !exclusive_end,
+            count,
+        });
+    }
+
+    if has_after ^ has_count {
+        bail!("--after and --count must be provided together");
     }
 
     if let (Some(after), Some(count)) = (after, count) {
         if count == 0 {
             return Err(anyhow!(CoreError::InvalidCount(count)));
         }
-        let (start, _) = parse_iso_datetime(after, &tz)?;
+        let start = parse(after)?;
         return Ok(ExpandQuery::After { start, count });
     }
 
     Ok(ExpandQuery::Unbounded)
 }
 
+/// Folds `--start`/`--end` into the `--between` pair they stand for.
+fn resolve_between(
+    between: Option<Vec<String>>,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<Option<Vec<String>>> {
+    match (between, start, end) {
+        (between, None, None) => Ok(between),
+        (Some(_), _, _) => bail!("--start/--end cannot be combined with --between"),
+        (None, Some(start), Some(end)) => Ok(Some(vec![start, end])),
+        (None, _, _) => bail!("--start and --end must be provided together"),
+    }
+}
+
+fn parse_between(
+    values: &[String],
+    tz: &Tz,
+    policy: DstPolicy,
+) -> Result<(DateTime<Tz>, DateTime<Tz>)> {
+    let (start, _) = parse_iso_datetime_with(&values[0], tz, policy)?;
+    let (end, _) = parse_iso_datetime_with(&values[1], tz, policy)?;
+    Window::from_between(start, end)?;
+    Ok((start, end))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SpecFormat {
+    Ics,
+    Json,
+}
+
 fn build_spec(input: &InputArgs) -> Result<RecurrenceSpec> {
-    if let Some(path) = &input.ics {
-        reject_extra_direct_flags(input)?;
-        let raw = fs::read_to_string(path)
-            .with_context(|| format!("failed to read ICS file {}", path.display()))?;
-        return parse_ics_spec(&raw, input.tz.as_deref()).map_err(Into::into);
+    let mut spec = load_spec(input)?;
+    if let Some(raw) = &input.duration {
+        spec.duration = Some(EventDuration::parse(raw)?);
+    }
+    if let Some(alias) = timezone_fallback(&spec.tz) {
+        eprintln!(
+            "warning: timezone {} is missing from the bundled tzdata; using {alias}",
+            spec.tz
+        );
+    }
+    Ok(spec)
+}
+
+impl InputArgs {
+    /// `--dst-policy`, which also governs naive window and pivot values.
+    fn dst_policy(&self) -> DstPolicy {
+        match self.dst_policy {
+            DstResolution::Reject => DstPolicy::Reject,
+            DstResolution::Earliest => DstPolicy::Earliest,
+            DstResolution::Latest => DstPolicy::Latest,
+            DstResolution::ShiftForward => DstPolicy::ShiftForward,
+        }
+    }
+}
+
+fn load_spec(input: &InputArgs) -> Result<RecurrenceSpec> {
+    let dst_policy = input.dst_policy();
+    let sources = [
+        (input.ics.as_ref(), Some(SpecFormat::Ics), "--ics"),
+        (input.json.as_ref(), Some(SpecFormat::Json), "--json"),
+        (input.input.as_ref(), None, "--input"),
+    ];
+    let given: Vec<_> = sources
+        .iter()
+        .filter(|(path, _, _)| path.is_some())
+        .collect();
+    if given.len() > 1 {
+        bail!("--ics, --json and --input are mutually exclusive");
+    }
+
+    if let Some((Some(path), format, flag)) = given.first() {
+        reject_extra_direct_flags(input, flag)?;
+        let invalid = |reason: String| match format {
+            Some(SpecFormat::Json) => CoreError::InvalidJson(reason),
+            _ => CoreError::InvalidIcs(reason),
+        };
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read input file {}", path.display()))?;
+        let raw = String::from_utf8(bytes)
+            .map_err(|err| invalid(format!("{} is not valid UTF-8: {err}", path.display())))?;
+        let format = match format {
+            Some(format) => *format,
+            None => detect_spec_format(path, &raw)?,
+        };
+        return match format {
+            SpecFormat::Ics => {
+                let options = IcsOptions {
+                    rfc_strict: input.rfc_strict,
+                    floating: input.floating,
+                    dst_policy,
+                };
+                parse_ics_event(&raw, input.tz.as_deref(), options, input.uid.as_deref())
+                    .map_err(Into::into)
+            }
+            SpecFormat::Json => {
+                reject_uid_selector(input)?;
+                if input.floating {
+                    bail!(
+                        "--floating applies to ICS and flag input; set \"floating\": true in JSON specs"
+                    );
+                }
+                if dst_policy != DstPolicy::Reject {
+                    bail!("--dst-policy applies to ICS and flag input; use offsets in JSON specs");
+                }
+                parse_json_spec(&raw, input.tz.as_deref()).map_err(Into::into)
+            }
+        };
     }
+    reject_uid_selector(input)?;
 
     let dtstart_raw = input
         .dtstart
         .as_deref()
         .ok_or_else(|| anyhow!("--dtstart is required when --ics is not used"))?;
-    let tz_raw = input
-        .tz
-        .as_deref()
-        .ok_or_else(|| anyhow!("--tz is required when --ics is not used"))?;
+    let tz_raw = match input.tz.as_deref() {
+        Some(tz) => tz,
+        None if input.floating => Tz::UTC.name(),
+        None => bail!("--tz is required when --ics is not used"),
+    };
 
     if input.rrule.is_empty() {
         bail!("at least one --rrule is required when --ics is not used");
     }
 
     let tz = parse_timezone(tz_raw)?;
-    let (dtstart, dtstart_type) = parse_iso_datetime(dtstart_raw, &tz)?;
+    let (dtstart, dtstart_type) = parse_iso_datetime_with(dtstart_raw, &tz, dst_policy)?;
 
     let mut rdates = Vec::with_capacity(input.rdate.len());
     for raw in &input.rdate {
-        let (dt, _kind) = parse_iso_datetime(raw, &tz)?;
+        let (dt, _kind) = parse_iso_datetime_with(raw, &tz, dst_policy)?;
         rdates.push(dt);
     }
 
     let mut exdates = Vec::with_capacity(input.exdate.len());
     for raw in &input.exdate {
-        let (dt, _kind) = parse_iso_datetime(raw, &tz)?;
+        let (dt, _kind) = parse_iso_datetime_with(raw, &tz, dst_policy)?;
         exdates.push(dt);
     }
 
+    let (rdates, duplicate_rdates) = dedupe_dates(rdates);
+    let (exdates, duplicate_exdates) = dedupe_dates(exdates);
+
     Ok(RecurrenceSpec {
         dtstart,
         dtstart_type: match dtstart_type {
@@ -271,25 +1563,134 @@ fn build_spec(input: &InputArgs) -> Result<RecurrenceSpec> {
             DateValueType::DateTime => DateValueType::DateTime,
         },
         tz: tz_raw.to_string(),
-        rrules: input.rrule.clone(),
+        rrules: input
+            .rrule
+            .iter()
+            .map(|rule| parse_rrule_shorthand(rule).unwrap_or_else(|| rule.clone()))
+            .collect(),
         rdates,
         exrules: input.exrule.clone(),
         exdates,
+        location: None,
+        summary: None,
+        geo: None,
+        duplicate_rdates,
+        duplicate_exdates,
+        diagnostics: SpecDiagnostics::default(),
+        overrides: Vec::new(),
+        uid: None,
+        uid_synthesized: false,
+        duration: None,
+        method: None,
+        floating: input.floating,
     })
 }
 
-fn reject_extra_direct_flags(input: &InputArgs) -> Result<()> {
+/// Picks the parser for `--input`: the `.ics`/`.json` extension wins,
+/// otherwise the contents decide.
+fn detect_spec_format(path: &std::path::Path, raw: &str) -> Result<SpecFormat> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("ics") => return Ok(SpecFormat::Ics),
+        Some(ext) if ext.eq_ignore_ascii_case("json") => return Ok(SpecFormat::Json),
+        _ => {}
+    }
+
+    let content = raw.trim_start_matches('\u{feff}').trim_start();
+    let looks_ics = raw.contains("BEGIN:VCALENDAR");
+    let looks_json = content.starts_with('{');
+    match (looks_ics, looks_json) {
+        (true, false) => Ok(SpecFormat::Ics),
+        (false, true) => Ok(SpecFormat::Json),
+        _ => bail!(
+            "could not detect whether {} is ICS or JSON; use --ics or --json",
+            path.display()
+        ),
+    }
+}
+
+fn reject_extra_direct_flags(input: &InputArgs, flag: &str) -> Result<()> {
     if input.dtstart.is_some()
         || !input.rrule.is_empty()
         || !input.rdate.is_empty()
         || !input.exrule.is_empty()
         || !input.exdate.is_empty()
     {
-        bail!("--ics cannot be combined with --dtstart/--rrule/--rdate/--exrule/--exdate");
+        bail!("{flag} cannot be combined with --dtstart/--rrule/--rdate/--exrule/--exdate");
+    }
+    Ok(())
+}
+
+fn reject_uid_selector(input: &InputArgs) -> Result<()> {
+    if input.uid.is_some() {
+        bail!("--uid selects a VEVENT and requires ICS input");
     }
     Ok(())
 }
 
+/// One grid per month touched by `start..=end`; each day is marked `*` when
+/// it has occurrences, followed by the count when there is more than one.
+fn render_calendar(
+    occurrences: &[rrulex_core::Occurrence],
+    start: NaiveDate,
+    end: NaiveDate,
+    wkst: Weekday,
+) -> String {
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for occ in occurrences {
+        let local = occ.start.as_deref().unwrap_or(&occ.start_local);
+        if let Some(date) = local.get(..10).and_then(|d| d.parse::<NaiveDate>().ok()) {
+            *per_day.entry(date).or_default() += 1;
+        }
+    }
+
+    let weekdays: Vec<Weekday> = std::iter::successors(Some(wkst), |day| Some(day.succ()))
+        .take(7)
+        .collect();
+    let mut out = String::new();
+    let mut month = start.with_day(1).unwrap_or(start);
+    while month <= end {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let name = Month::try_from(month.month() as u8).map_or("", |m| m.name());
+        out.push_str(&format!("{name} {}\n", month.year()));
+        let header: Vec<String> = weekdays
+            .iter()
+            .map(|day| format!("{:<5}", &day.to_string()[..2]))
+            .collect();
+        out.push_str(header.concat().trim_end());
+        out.push('\n');
+
+        let mut line = "     ".repeat(month.weekday().days_since(wkst) as usize);
+        let mut day = month;
+        while day.month() == month.month() {
+            let mark = match per_day.get(&day) {
+                None => String::new(),
+                Some(1) => "*".to_string(),
+                Some(count) => format!("*{count}"),
+            };
+            line.push_str(&format!("{:>2}{mark:<3}", day.day()));
+            if day.weekday() == wkst.pred() {
+                out.push_str(line.trim_end());
+                out.push('\n');
+                line.clear();
+            }
+            match day.succ_opt() {
+                Some(next) => day = next,
+                None => break,
+            }
+        }
+        if !line.trim().is_empty() {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        month = month
+            .checked_add_months(Months::new(1))
+            .unwrap_or(NaiveDate::MAX);
+    }
+    out
+}
+
 fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
     let raw = serde_json::to_value(value)?;
     let canonical = canonical_json(&raw);
@@ -297,18 +1698,70 @@ fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
     Ok(())
 }
 
-fn print_expand_text(occurrences: &[rrulex_core::Occurrence]) {
+fn print_json_line<T: serde::Serialize>(value: &T) -> Result<()> {
+    let raw = serde_json::to_value(value)?;
+    println!("{}", serde_json::to_string(&canonical_json(&raw))?);
+    Ok(())
+}
+
+/// Writes the result as JSON Lines, each object tagged with `type`: the meta
+/// first, then one line per occurrence, then any week spans or heatmap, and
+/// last the `end` line with the counts.
+fn print_expand_jsonl(result: &ExpandResult) -> Result<()> {
+    write_jsonl_meta(&result.meta)?;
+    for occ in &result.occurrences {
+        write_jsonl_line("occurrence", serde_json::to_value(occ)?)?;
+    }
+    if let Some(spans) = &result.week_spans {
+        write_jsonl_line("week_spans", serde_json::to_value(spans)?)?;
+    }
+    if let Some(heatmap) = &result.heatmap {
+        write_jsonl_line("heatmap", serde_json::to_value(heatmap)?)?;
+    }
+    write_jsonl_end(result.occurrences.len(), result.meta.limit_reached)
+}
+
+/// The meta line leaves the counts to the `end` line, since a streamed
+/// expansion only knows them once it is done.
+fn write_jsonl_meta(meta: &rrulex_core::ExpandMeta) -> Result<()> {
+    let mut value = serde_json::to_value(meta)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("returned");
+        fields.remove("limit_reached");
+    }
+    write_jsonl_line("meta", value)
+}
+
+fn write_jsonl_end(returned: usize, limit_reached: bool) -> Result<()> {
+    write_jsonl_line(
+        "end",
+        serde_json::json!({ "returned": returned, "limit_reached": limit_reached }),
+    )
+}
+
+/// One compact canonical line tagged with `type`, flushed right away.
+fn write_jsonl_line(kind: &str, value: serde_json::Value) -> Result<()> {
+    let mut line = serde_json::Map::new();
+    line.insert("type".to_string(), kind.into());
+    match value {
+        serde_json::Value::Object(fields) => line.extend(fields),
+        other => {
+            line.insert(kind.to_string(), other);
+        }
+    }
+    let mut out = std::io::stdout().lock();
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string(&canonical_json(&serde_json::Value::Object(line)))?
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+fn print_expand_text(occurrences: &[rrulex_core::Occurrence], style: OccurrenceStyle) {
     for occ in occurrences {
-        println!(
-            "{} ({}) {} idx={}",
-            occ.start_local,
-            occ.start_utc,
-            match occ.source {
-                rrulex_core::OccurrenceSource::Rrule => "RRULE",
-                rrulex_core::OccurrenceSource::Rdate => "RDATE",
-            },
-            occ.rule_index
-        );
+        println!("{}", format_occurrence(occ, style));
     }
 }
 
@@ -324,6 +1777,24 @@ fn print_lint_text(findings: &Findings) {
     }
 }
 
+/// Prints the flat result as one `key=value` line; unset fields are skipped.
+fn print_flat_explain_text(result: &FlatExplainResult) {
+    let mut line = format!("at={} included={}", result.at, result.included);
+    if let Some(source) = &result.source {
+        line.push_str(match source {
+            rrulex_core::OccurrenceSource::Rrule => " source=RRULE",
+            rrulex_core::OccurrenceSource::Rdate => " source=RDATE",
+        });
+    }
+    if let Some(idx) = result.rule_index {
+        line.push_str(&format!(" rule_index={idx}"));
+    }
+    if let Some(excluded) = &result.excluded_by {
+        line.push_str(&format!(" excluded_by={excluded}"));
+    }
+    println!("{line}");
+}
+
 fn print_explain_text(result: &ExplainResult) {
     println!("at: {}", result.at);
     println!("included: {}", result.included);
@@ -342,26 +1813,114 @@ fn print_explain_text(result: &ExplainResult) {
     if let Some(excluded) = &result.excluded_by {
         println!("excluded_by: {excluded}");
     }
+    for exclusion in result.exclusions.iter().flatten() {
+        println!("excluded_by: {} idx={}", exclusion.kind, exclusion.index);
+    }
+    for matched in result.day_matches.iter().flatten() {
+        println!("match: {matched}");
+    }
+    if let Some(before) = &result.nearest_before {
+        println!("nearest_before: {before}");
+    }
+    if let Some(after) = &result.nearest_after {
+        println!("nearest_after: {after}");
+    }
+    if let Some(why_not) = &result.why_not {
+        for check in &why_not.checks {
+            println!(
+                "check: {} {} ({})",
+                check.check,
+                if check.passed { "ok" } else { "FAILED" },
+                check.detail
+            );
+        }
+    }
     for note in &result.notes {
         println!("note: {note}");
     }
 }
 
+fn print_simulate_text(result: &SimulateResult) {
+    for simulated in &result.occurrences {
+        let occ = &simulated.occurrence;
+        println!(
+            "{} ({}) gap={}",
+            occ.start_local,
+            occ.start_utc,
+            simulated.gap_from_previous.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+fn print_count_text(result: &CountResult) {
+    match result.at_least {
+        Some(true) => println!(">={}", result.count),
+        _ => println!("{}", result.count),
+    }
+    if let Some(sources) = result.by_source {
+        println!("RRULE {}", sources.rrule);
+        println!("RDATE {}", sources.rdate);
+    }
+}
+
 fn exit_code_for_error(err: &anyhow::Error) -> u8 {
-    if let Some(CoreError::LimitExceeded { .. } | CoreError::UnsafeUnboundedRule) =
-        err.downcast_ref::<CoreError>()
-    {
-        3
-    } else {
-        2
+    match err.downcast_ref::<CoreError>() {
+        Some(CoreError::LimitExceeded { .. } | CoreError::UnsafeUnboundedRule) => 3,
+        Some(CoreError::TimeLimitExceeded { .. }) => 4,
+        _ => 2,
+    }
+}
+
+/// `--error-format json` view of `err`. The message keeps any context added
+/// on the way up; errors raised by the CLI itself are `INVALID_INPUT`.
+fn error_report(err: &anyhow::Error) -> ErrorReport {
+    let report = match err.downcast_ref::<CoreError>() {
+        Some(core) => core.report(),
+        None => ErrorReport {
+            code: "INVALID_INPUT",
+            ..ErrorReport::default()
+        },
+    };
+    ErrorReport {
+        message: format!("{err:#}"),
+        ..report
     }
 }
 
+/// The rule on its own line with the part blamed by `reason` underlined,
+/// or `None` when no part can be pinned down.
+fn rule_snippet(rule: &str, reason: &str) -> Option<String> {
+    let (start, end) = invalid_rule_span(rule, reason)?;
+    let indent = rule[..start].chars().count();
+    let width = rule[start..end].chars().count().max(1);
+    Some(format!(
+        "  {rule}\n  {}{} {reason}",
+        " ".repeat(indent),
+        "^".repeat(width)
+    ))
+}
+
 fn main() -> ExitCode {
-    match run() {
-        Ok(()) => ExitCode::SUCCESS,
+    let cli = Cli::parse();
+    let pretty_errors = cli.pretty_errors;
+    let error_format = cli.error_format;
+    match run(cli) {
+        Ok(code) => code,
+        Err(err) if matches!(error_format, ErrorFormat::Json) => {
+            eprintln!("{}", serde_json::json!({ "error": error_report(&err) }));
+            ExitCode::from(exit_code_for_error(&err))
+        }
         Err(err) => {
-            eprintln!("Error: {err:#}");
+            let snippet = match err.downcast_ref::<CoreError>() {
+                Some(CoreError::InvalidRrule { rule, reason }) if pretty_errors => {
+                    rule_snippet(rule, reason)
+                }
+                _ => None,
+            };
+            match snippet {
+                Some(snippet) => eprintln!("Error: invalid RRULE\n{snippet}"),
+                None => eprintln!("Error: {err:#}"),
+            }
             ExitCode::from(exit_code_for_error(&err))
         }
     }
@@ -12,6 +12,7 @@ struct FixtureCase {
     expected_exit: i32,
     golden: Option<String>,
     stderr_contains: Option<String>,
+    stdout_contains: Option<String>,
 }
 
 fn project_root() -> PathBuf {
@@ -107,6 +108,13 @@ fn fixture_cases() {
             );
         }
 
+        if let Some(expected_fragment) = case.stdout_contains.as_deref() {
+            assert!(
+                stdout.contains(expected_fragment),
+                "Expected stdout for {case_name} to contain '{expected_fragment}', got:\n{stdout}"
+            );
+        }
+
         if case.expected_exit != 0 {
             continue;
         }
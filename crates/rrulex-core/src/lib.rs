@@ -1,10 +1,20 @@
-use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
-use chrono_tz::Tz as ChronoTz;
-use rrule::{RRule, RRuleSet, Tz, Unvalidated};
-use serde::Serialize;
-use std::collections::{BTreeMap, HashMap};
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, LocalResult, Months, NaiveDate, NaiveDateTime,
+    NaiveTime, Offset, TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz as ChronoTz};
+use rrule::{Frequency, NWeekday, RRule, RRuleSet, Unvalidated};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::rc::Rc;
+use std::time::{Duration as StdDuration, Instant};
 use thiserror::Error;
 
+pub use rrule::Tz;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DateValueType {
     Date,
@@ -20,6 +30,422 @@ pub struct RecurrenceSpec {
     pub rdates: Vec<DateTime<Tz>>,
     pub exrules: Vec<String>,
     pub exdates: Vec<DateTime<Tz>>,
+    pub location: Option<String>,
+    /// VEVENT `SUMMARY`, unescaped.
+    pub summary: Option<String>,
+    pub geo: Option<Geo>,
+    /// RDATE values dropped because the same instant was already listed.
+    pub duplicate_rdates: Vec<DateTime<Tz>>,
+    /// EXDATE values dropped because the same instant was already listed.
+    pub duplicate_exdates: Vec<DateTime<Tz>>,
+    /// How the ICS input was written, kept for [`lint`]; empty for specs
+    /// built from JSON or flags.
+    pub diagnostics: SpecDiagnostics,
+    /// `RECURRENCE-ID` events of the same UID that move single instances,
+    /// one per instance (the highest `SEQUENCE` wins), sorted by instance.
+    pub overrides: Vec<InstanceOverride>,
+    /// VEVENT `UID`; `None` for specs built from direct flags.
+    pub uid: Option<String>,
+    /// Set when the ICS input had no `UID` and [`RecurrenceSpec::uid`] was
+    /// derived from the event contents instead.
+    pub uid_synthesized: bool,
+    /// Length of each occurrence, from `DURATION`/`DTEND` or `--duration`;
+    /// `None` leaves occurrence end times unset.
+    pub duration: Option<EventDuration>,
+    /// iTIP `METHOD` of the enclosing VCALENDAR (`PUBLISH`, `REQUEST`,
+    /// `CANCEL`, ...), uppercased.
+    pub method: Option<String>,
+    /// DTSTART carries no zone at all. Such specs are expanded with UTC as a
+    /// stand-in `tz`, which has no transitions and so keeps wall-clock times
+    /// exact; [`apply_floating`] removes the stand-in before output.
+    pub floating: bool,
+}
+
+/// Details of the ICS source that only [`lint`] reports on; the values
+/// themselves are already resolved into the [`RecurrenceSpec`].
+#[derive(Debug, Clone, Default)]
+pub struct SpecDiagnostics {
+    /// Explicit `TZID` of each ICS RDATE/EXDATE line, in file order.
+    pub property_tzids: Vec<PropertyTzid>,
+    /// ICS DATE-TIME values written without seconds (`NAME:YYYYMMDDTHHMM`),
+    /// accepted with seconds set to 0.
+    pub short_datetimes: Vec<String>,
+}
+
+/// A `RECURRENCE-ID` VEVENT moving one instance of the series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceOverride {
+    /// The instance being replaced, as the master event generates it.
+    pub recurrence_id: DateTime<Tz>,
+    /// Where that instance takes place instead (the override's DTSTART).
+    pub start: DateTime<Tz>,
+    /// The override's `SEQUENCE`, 0 when absent.
+    pub sequence: u32,
+}
+
+/// An RFC 5545 duration. `days` are nominal and added in local wall-clock
+/// time, so `P1D` across a DST change keeps the time of day; `seconds` are
+/// exact, so `PT24H` is always 86400 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventDuration {
+    pub days: i64,
+    pub seconds: i64,
+}
+
+impl EventDuration {
+    /// Parses `P1W`, `P1D`, `PT1H30M`, `P1DT12H` and similar. Year and month
+    /// designators and negative durations are rejected.
+    pub fn parse(value: &str) -> Result<Self, CoreError> {
+        let invalid = |reason: &str| CoreError::InvalidDuration {
+            input: value.to_string(),
+            reason: reason.to_string(),
+        };
+        let raw = value.trim().to_ascii_uppercase();
+        let raw = raw.strip_prefix('+').unwrap_or(&raw);
+        if raw.starts_with('-') {
+            return Err(invalid("must not be negative"));
+        }
+        let rest = raw
+            .strip_prefix('P')
+            .ok_or_else(|| invalid("expected ISO 8601 like PT1H30M or P1D"))?;
+        let (date_part, time_part) = match rest.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (rest, None),
+        };
+
+        let mut duration = Self::default();
+        let mut any = false;
+        for (number, unit) in duration_components(date_part).ok_or_else(|| invalid("malformed"))? {
+            let days = match unit {
+                'W' => number.checked_mul(7),
+                'D' => Some(number),
+                'Y' | 'M' => {
+                    return Err(invalid("years and months have no fixed length; use days"));
+                }
+                _ => return Err(invalid("unknown date designator")),
+            };
+            duration.days = days
+                .and_then(|days| duration.days.checked_add(days))
+                .ok_or_else(|| invalid("too large"))?;
+            any = true;
+        }
+        if let Some(time_part) = time_part {
+            let components = duration_components(time_part).ok_or_else(|| invalid("malformed"))?;
+            if components.is_empty() {
+                return Err(invalid("T must be followed by hours, minutes or seconds"));
+            }
+            for (number, unit) in components {
+                let seconds = match unit {
+                    'H' => number.checked_mul(3600),
+                    'M' => number.checked_mul(60),
+                    'S' => Some(number),
+                    _ => return Err(invalid("unknown time designator")),
+                };
+                duration.seconds = seconds
+                    .and_then(|seconds| duration.seconds.checked_add(seconds))
+                    .ok_or_else(|| invalid("too large"))?;
+            }
+            any = true;
+        }
+        if !any {
+            return Err(invalid("expected ISO 8601 like PT1H30M or P1D"));
+        }
+        Ok(duration)
+    }
+
+    /// The duration with every day counted as exactly 24 hours, or an
+    /// `InvalidDuration` error when that does not fit a [`Duration`].
+    pub fn to_exact(&self) -> Result<Duration, CoreError> {
+        Duration::try_days(self.days)
+            .zip(Duration::try_seconds(self.seconds))
+            .and_then(|(days, seconds)| days.checked_add(&seconds))
+            .ok_or_else(|| CoreError::InvalidDuration {
+                input: self.to_string(),
+                reason: "too large".to_string(),
+            })
+    }
+
+    /// End of an occurrence starting at `start`: days in wall-clock time
+    /// (a nonexistent local result moves forward past the gap), then the
+    /// exact seconds. Fails when the end lies outside chrono's date range.
+    pub fn end_of(&self, start: DateTime<Tz>) -> Result<DateTime<Tz>, CoreError> {
+        let out_of_range = || CoreError::InvalidDuration {
+            input: self.to_string(),
+            reason: "ends beyond the supported date range".to_string(),
+        };
+        let shifted = if self.days == 0 {
+            start
+        } else {
+            let days = Duration::try_days(self.days).ok_or_else(out_of_range)?;
+            let local = start
+                .naive_local()
+                .checked_add_signed(days)
+                .ok_or_else(out_of_range)?;
+            match resolve_local(start.timezone(), local, "") {
+                Ok((dt, _)) => dt,
+                Err(_) => start.checked_add_signed(days).ok_or_else(out_of_range)?,
+            }
+        };
+        Duration::try_seconds(self.seconds)
+            .and_then(|seconds| shifted.checked_add_signed(seconds))
+            .ok_or_else(out_of_range)
+    }
+}
+
+impl std::fmt::Display for EventDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P")?;
+        if self.days > 0 {
+            write!(f, "{}D", self.days)?;
+        }
+        if self.seconds > 0 || self.days == 0 {
+            write!(f, "T")?;
+            let (hours, minutes, seconds) = (
+                self.seconds / 3600,
+                self.seconds / 60 % 60,
+                self.seconds % 60,
+            );
+            if hours > 0 {
+                write!(f, "{hours}H")?;
+            }
+            if minutes > 0 {
+                write!(f, "{minutes}M")?;
+            }
+            if seconds > 0 || self.seconds == 0 {
+                write!(f, "{seconds}S")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An ISO 8601 span that, unlike [`EventDuration`], may use years and
+/// months (`P1Y`, `P6M`, `P1Y2M10D`), for bounding an expansion in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExpansionSpan {
+    pub months: u32,
+    pub duration: EventDuration,
+}
+
+impl ExpansionSpan {
+    /// Parses any [`EventDuration`] with optional leading `Y`/`M` date
+    /// designators; `M` after `T` still means minutes.
+    pub fn parse(value: &str) -> Result<Self, CoreError> {
+        let invalid = |reason: &str| CoreError::InvalidDuration {
+            input: value.to_string(),
+            reason: reason.to_string(),
+        };
+        let raw = value.trim().to_ascii_uppercase();
+        let Some(rest) = raw.strip_prefix('P') else {
+            return EventDuration::parse(value).map(|duration| Self {
+                months: 0,
+                duration,
+            });
+        };
+        let (date_part, time_part) = match rest.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (rest, None),
+        };
+        let components = duration_components(date_part).ok_or_else(|| invalid("malformed"))?;
+        let split = components
+            .iter()
+            .position(|(_, unit)| !matches!(unit, 'Y' | 'M'))
+            .unwrap_or(components.len());
+        let mut months: u32 = 0;
+        for (number, unit) in &components[..split] {
+            let add = match unit {
+                'Y' => number.checked_mul(12),
+                _ => Some(*number),
+            };
+            months = add
+                .and_then(|add| u32::try_from(add).ok())
+                .and_then(|add| months.checked_add(add))
+                .ok_or_else(|| invalid("too large"))?;
+        }
+
+        let remainder: String = components[split..]
+            .iter()
+            .map(|(number, unit)| format!("{number}{unit}"))
+            .collect();
+        if remainder.is_empty() && time_part.is_none() {
+            if split == 0 {
+                return Err(invalid("expected ISO 8601 like P1Y, P6M or P30D"));
+            }
+            return Ok(Self {
+                months,
+                duration: EventDuration::default(),
+            });
+        }
+        let mut rest = format!("P{remainder}");
+        if let Some(time) = time_part {
+            rest.push('T');
+            rest.push_str(time);
+        }
+        let duration = EventDuration::parse(&rest).map_err(|err| match err {
+            CoreError::InvalidDuration { reason, .. } => invalid(&reason),
+            other => other,
+        })?;
+        Ok(Self { months, duration })
+    }
+
+    /// `start` plus the span: months and days in wall-clock time (clamped
+    /// to the month's last day), then the exact seconds.
+    pub fn end_from(&self, start: DateTime<Tz>) -> Result<DateTime<Tz>, CoreError> {
+        let out_of_range = || CoreError::InvalidDuration {
+            input: self.to_string(),
+            reason: "span ends beyond the supported date range".to_string(),
+        };
+        let shifted = if self.months == 0 {
+            start
+        } else {
+            let local = start
+                .naive_local()
+                .checked_add_months(Months::new(self.months))
+                .ok_or_else(out_of_range)?;
+            resolve_local(start.timezone(), local, "")
+                .map(|(dt, _)| dt)
+                .map_err(|_| out_of_range())?
+        };
+        self.duration.end_of(shifted).map_err(|_| out_of_range())
+    }
+}
+
+impl std::fmt::Display for ExpansionSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.months == 0 {
+            return write!(f, "{}", self.duration);
+        }
+        write!(f, "P")?;
+        if self.months >= 12 {
+            write!(f, "{}Y", self.months / 12)?;
+        }
+        if !self.months.is_multiple_of(12) {
+            write!(f, "{}M", self.months % 12)?;
+        }
+        if self.duration != EventDuration::default() {
+            write!(f, "{}", &self.duration.to_string()[1..])?;
+        }
+        Ok(())
+    }
+}
+
+/// `1H30M` → `[(1, 'H'), (30, 'M')]`; `None` on stray characters.
+fn duration_components(value: &str) -> Option<Vec<(i64, char)>> {
+    let mut out = Vec::new();
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else {
+            out.push((digits.parse().ok()?, c));
+            digits.clear();
+        }
+    }
+    digits.is_empty().then_some(out)
+}
+
+impl RecurrenceSpec {
+    /// Re-anchors the spec at a new DTSTART (e.g. to reuse a rule template),
+    /// re-validating every rule against it. `COUNT` then counts from the new
+    /// start; `UNTIL`, RDATEs and EXDATEs stay absolute.
+    pub fn override_dtstart(
+        &mut self,
+        dtstart: DateTime<Tz>,
+        dtstart_type: DateValueType,
+    ) -> Result<(), CoreError> {
+        let mut candidate = self.clone();
+        candidate.dtstart = dtstart;
+        candidate.dtstart_type = dtstart_type;
+        parse_validated_rules(&candidate)?;
+        *self = candidate;
+        Ok(())
+    }
+
+    /// RFC 5545 `X-` parts of every RRULE and EXRULE, in rule order. They
+    /// stay in the rule strings but are stripped before validation.
+    pub fn rule_extensions(&self) -> Vec<RuleExtension> {
+        let rrules = self
+            .rrules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| ("RRULE", i, rule));
+        let exrules = self
+            .exrules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| ("EXRULE", i, rule));
+        rrules
+            .chain(exrules)
+            .flat_map(|(property, rule_index, rule)| {
+                parse_rule_parts(rule)
+                    .into_iter()
+                    .filter(RulePart::is_extension)
+                    .map(move |part| RuleExtension {
+                        property: property.to_string(),
+                        rule_index,
+                        name: part.key,
+                        value: part.value,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The `TZID` parameter of one RDATE or EXDATE property line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyTzid {
+    /// `RDATE` or `EXDATE`.
+    pub property: String,
+    pub tzid: String,
+}
+
+/// A non-standard `X-NAME=value` rule part, see
+/// [`RecurrenceSpec::rule_extensions`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleExtension {
+    /// `RRULE` or `EXRULE`.
+    pub property: String,
+    /// Position among the rules of that property.
+    pub rule_index: usize,
+    pub name: String,
+    pub value: String,
+}
+
+/// How a local time that a DST transition makes ambiguous (fall-back) or
+/// nonexistent (spring-forward) is resolved to an instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DstPolicy {
+    /// Fail with [`CoreError::InvalidDateTime`] in either case.
+    #[default]
+    Reject,
+    /// The first occurrence of an ambiguous time, still on the
+    /// pre-transition (summer) offset. Nonexistent times are rejected.
+    Earliest,
+    /// The second occurrence of an ambiguous time, on the post-transition
+    /// offset. Nonexistent times are rejected.
+    Latest,
+    /// A nonexistent time becomes the instant just after the gap, so 02:30
+    /// on a 02:00→03:00 night is 03:00. Ambiguous times are rejected.
+    ShiftForward,
+}
+
+/// Parser options for [`parse_ics_spec_with`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IcsOptions {
+    /// Reject input that RFC 5545 does not allow, such as a VEVENT without
+    /// `UID`, instead of filling in the gaps.
+    pub rfc_strict: bool,
+    /// Accept a DTSTART with neither `TZID` nor `Z` (and no fallback zone) as
+    /// floating time instead of rejecting it.
+    pub floating: bool,
+    /// Resolution of ambiguous local DTSTART/DTEND/RDATE/EXDATE values.
+    pub dst_policy: DstPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Geo {
+    pub lat: f64,
+    pub lon: f64,
 }
 
 #[derive(Debug, Clone)]
@@ -27,14 +453,231 @@ pub enum ExpandQuery {
     Between {
         start: DateTime<Tz>,
         end: DateTime<Tz>,
+        /// Whether an occurrence exactly at `end` is included; `false` makes
+        /// the window half-open, `[start, end)`, so adjacent windows can be
+        /// chained without counting the shared boundary twice.
+        inclusive_end: bool,
+        /// Keep only the window's first `count` occurrences. Without it the
+        /// query fails once the window holds more than the hard limit.
+        count: Option<usize>,
     },
     After {
         start: DateTime<Tz>,
         count: usize,
     },
+    /// Everything up to `end` inclusive, typically DTSTART plus an
+    /// [`ExpansionSpan`]; like `Unbounded`, it stops quietly at the limit.
+    Before {
+        end: DateTime<Tz>,
+    },
     Unbounded,
 }
 
+impl ExpandQuery {
+    /// The window this query covers; `After` is open-ended.
+    pub fn window(&self) -> Window {
+        match self {
+            ExpandQuery::Between { start, end, .. } => Window {
+                start: Some(*start),
+                end: Some(*end),
+            },
+            ExpandQuery::After { start, .. } => Window {
+                start: Some(*start),
+                end: None,
+            },
+            ExpandQuery::Before { end } => Window {
+                start: None,
+                end: Some(*end),
+            },
+            ExpandQuery::Unbounded => Window::unbounded(),
+        }
+    }
+
+    /// Whether `end` itself is left out of the window.
+    pub fn is_end_exclusive(&self) -> bool {
+        matches!(
+            self,
+            ExpandQuery::Between {
+                inclusive_end: false,
+                ..
+            }
+        )
+    }
+
+    /// The inclusive window actually generated: a half-open end is pulled
+    /// back by [`HALF_OPEN_END`], which drops exactly the instants equal to
+    /// it since all instants are whole seconds. [`window_links`] steps this
+    /// same closed window.
+    fn generated_window(&self) -> Window {
+        let window = self.window();
+        if !self.is_end_exclusive() {
+            return window;
+        }
+        Window {
+            end: window.end.map(|end| end - HALF_OPEN_END),
+            ..window
+        }
+    }
+
+    /// The window echoed in [`ExpandMeta`], flagging a half-open end.
+    pub fn window_meta(&self) -> WindowMeta {
+        WindowMeta {
+            end_exclusive: self.is_end_exclusive(),
+            ..self.window().meta()
+        }
+    }
+}
+
+/// How far a half-open window end lies past the last instant it includes.
+const HALF_OPEN_END: Duration = Duration::seconds(1);
+
+/// An inclusive time window; `None` leaves that side open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: Option<DateTime<Tz>>,
+    pub end: Option<DateTime<Tz>>,
+}
+
+impl Window {
+    pub fn unbounded() -> Self {
+        Self {
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Builds a closed window, rejecting `start > end`.
+    pub fn from_between(start: DateTime<Tz>, end: DateTime<Tz>) -> Result<Self, CoreError> {
+        if start > end {
+            return Err(CoreError::InvalidWindow);
+        }
+        Ok(Self {
+            start: Some(start),
+            end: Some(end),
+        })
+    }
+
+    pub fn contains(&self, instant: DateTime<Tz>) -> bool {
+        self.start.is_none_or(|start| instant >= start) && self.end.is_none_or(|end| instant <= end)
+    }
+
+    /// Span between both ends, or `None` if either side is open.
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.end? - self.start?)
+    }
+
+    pub fn meta(&self) -> WindowMeta {
+        let format = |dt: DateTime<Tz>| dt.format("%Y-%m-%dT%H:%M:%S").to_string();
+        WindowMeta {
+            start: self.start.map(format),
+            end: self.end.map(format),
+            end_exclusive: false,
+        }
+    }
+
+    /// The adjacent window of the same size after this one.
+    ///
+    /// Windows aligned to whole local months (e.g. `03-01T00:00:00` to
+    /// `03-31T23:59:59`) step by calendar months; anything else steps by its
+    /// local wall-clock length, starting one second after `end`, so a day
+    /// window stays a day window across a DST change.
+    pub fn next(&self) -> Option<Window> {
+        self.step(1)
+    }
+
+    /// The adjacent window of the same size before this one; see [`Window::next`].
+    pub fn prev(&self) -> Option<Window> {
+        self.step(-1)
+    }
+
+    fn step(&self, direction: i32) -> Option<Window> {
+        let (start, end) = (self.start?, self.end?);
+        let tz = start.timezone();
+        let (start_local, end_local) = (start.naive_local(), end.naive_local());
+
+        if let Some((months, last_second)) = month_aligned_span(start_local, end_local) {
+            let shift = |months: i32| -> Option<NaiveDateTime> {
+                let months = Months::new(months.unsigned_abs());
+                if direction > 0 {
+                    start_local.checked_add_months(months)
+                } else {
+                    start_local.checked_sub_months(months)
+                }
+            };
+            let new_start = shift(months)?;
+            let mut new_end = if direction > 0 {
+                shift(2 * months)?
+            } else {
+                start_local
+            };
+            if last_second {
+                new_end -= Duration::seconds(1);
+            }
+            let (new_start, _) = resolve_local(tz, new_start, "").ok()?;
+            let (new_end, _) = resolve_local(tz, new_end, "").ok()?;
+            return Some(Window {
+                start: Some(new_start),
+                end: Some(new_end),
+            });
+        }
+
+        let span = end_local - start_local;
+        let one = Duration::seconds(1);
+        let (new_start, new_end) = if direction > 0 {
+            (end_local + one, end_local + one + span)
+        } else {
+            (start_local - one - span, start_local - one)
+        };
+        let (new_start, _) = resolve_local(tz, new_start, "").ok()?;
+        let (new_end, _) = resolve_local(tz, new_end, "").ok()?;
+        Some(Window {
+            start: Some(new_start),
+            end: Some(new_end),
+        })
+    }
+}
+
+/// Detects `[first-of-month 00:00:00, first-of-month(+N) 00:00:00 or -1s]`
+/// windows, returning the month count and whether `end` is the last second.
+fn month_aligned_span(start: NaiveDateTime, end: NaiveDateTime) -> Option<(i32, bool)> {
+    if start.day() != 1 || start.time() != NaiveTime::MIN {
+        return None;
+    }
+    let last_second = end.time() == NaiveTime::from_hms_opt(23, 59, 59)?;
+    let end = if last_second {
+        end + Duration::seconds(1)
+    } else {
+        end
+    };
+    if end.day() != 1 || end.time() != NaiveTime::MIN {
+        return None;
+    }
+    let months =
+        (end.year() * 12 + end.month() as i32) - (start.year() * 12 + start.month() as i32);
+    (months > 0).then_some((months, last_second))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowLinks {
+    pub next: WindowLink,
+    pub prev: WindowLink,
+}
+
+/// Ready-made `--between` arguments for an adjacent window.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowLink {
+    pub between: [String; 2],
+}
+
+impl WindowLink {
+    fn from_window(window: &Window) -> Option<Self> {
+        let meta = window.meta();
+        Some(Self {
+            between: [meta.start?, meta.end?],
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum OccurrenceSource {
@@ -44,11 +687,56 @@ pub enum OccurrenceSource {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Occurrence {
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub start_local: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub start_utc: String,
-    pub tz: String,
+    /// RFC 3339 local time with its UTC offset; replaces `start_local` and
+    /// `start_utc` after [`combine_datetimes`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    /// Display zone; `None` for floating occurrences, see [`apply_floating`].
+    pub tz: Option<String>,
     pub source: OccurrenceSource,
     pub rule_index: usize,
+    /// Zone abbreviation in effect at the occurrence (`CET`/`CEST`); set by
+    /// [`apply_abbreviations`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abbrev: Option<String>,
+    /// Enclosing recurrence period; set by [`apply_periods`].
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub period: Option<OccurrencePeriod>,
+    /// Start plus the spec's duration; `null` when the spec has none.
+    pub end_local: Option<String>,
+    pub end_utc: Option<String>,
+    /// Local start of the instance a RECURRENCE-ID override moved here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence_id: Option<String>,
+    /// The applied override's `SEQUENCE`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u32>,
+    /// Local weekday (`MO`..`SU`); set by [`apply_calendar_fields`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekday: Option<String>,
+    /// Local day of the year, 1-based; set by [`apply_calendar_fields`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day_of_year: Option<u32>,
+    /// Local ISO 8601 week number; set by [`apply_calendar_fields`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso_week: Option<u32>,
+    /// The ISO week-numbering year `iso_week` belongs to, which differs
+    /// from the calendar year around New Year.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso_year: Option<i32>,
+}
+
+/// Bounds of the FREQ/INTERVAL period an occurrence falls in, in the spec's
+/// local time. Both are `None` for RDATE occurrences, which belong to no
+/// period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OccurrencePeriod {
+    pub period_start: Option<String>,
+    pub period_end: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -63,21 +751,283 @@ pub struct RulesMeta {
 pub struct WindowMeta {
     pub start: Option<String>,
     pub end: Option<String>,
+    /// Set for half-open `[start, end)` windows; omitted when `end` is inclusive.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub end_exclusive: bool,
 }
 
+/// Metadata echoed alongside expanded occurrences.
+///
+/// Windows are inclusive on both ends unless `window.end_exclusive` says the
+/// end was left out (`ExpandQuery::Between` with `inclusive_end: false`).
+/// `UNTIL` is inclusive as well; for a
+/// DATE `DTSTART`, a DATE `UNTIL=YYYYMMDD` covers that whole local day, so an
+/// occurrence on the UNTIL date is always included.
 #[derive(Debug, Clone, Serialize)]
 pub struct ExpandMeta {
     pub dtstart: String,
-    pub tz: String,
+    pub tz: Option<String>,
     pub rules: RulesMeta,
     pub window: WindowMeta,
     pub limit: usize,
+    /// Number of occurrences in the result.
+    pub returned: usize,
+    /// Whether expansion stopped at `limit`, so later occurrences may be
+    /// missing.
+    pub limit_reached: bool,
+    /// Whether the series ends: every RRULE has COUNT or UNTIL (RDATEs are
+    /// finite by nature). See [`is_finite`].
+    pub is_finite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<Geo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// `X-` rule parts, which play no part in expansion.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rule_extensions: Vec<RuleExtension>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_calendar: Option<BusinessCalendarMeta>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Likely input mistakes noticed during expansion, such as an EXDATE in
+    /// the window that matches no generated instant.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Adjacent windows of the same size; only set for `--between` queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<WindowLinks>,
+    /// How the occurrences were thinned out; set by [`apply_sample`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample: Option<SampleMeta>,
+    /// The only source kept in the output; set by [`apply_source_filter`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_filter: Option<OccurrenceSource>,
+}
+
+/// Text rendering for [`format_occurrence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OccurrenceStyle {
+    /// `start_local[ abbrev] (start_utc) SOURCE idx=N`, the `expand --format text` line.
+    #[default]
+    Default,
+    /// Fixed-width `local  utc  SOURCE  N` columns.
+    Table,
+    /// Weekday and month names, e.g. `Sun 1 Mar 2026, 10:00 CET (RRULE 0)`.
+    Human,
+    /// The RFC 3339 start with its UTC offset, and nothing else.
+    Rfc3339,
+}
+
+/// Strategy for [`apply_sample`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleMode {
+    /// Evenly spaced, always keeping the first and last occurrence.
+    Even,
+    Head,
+    Tail,
+    /// Uniformly chosen with a seeded generator, so runs are reproducible.
+    Random {
+        seed: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SampleMeta {
+    pub mode: String,
+    /// Occurrences before sampling.
+    pub total: usize,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BusinessCalendarMeta {
+    pub hours: String,
+    pub days: Vec<String>,
+}
+
+/// Working hours and days used to post-filter occurrences in local time.
+///
+/// Hours are half-open (`[start, end)`); an `end` before `start` wraps past
+/// midnight and equal bounds cover the whole day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessCalendar {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub days: Vec<Weekday>,
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::MIN,
+            end: NaiveTime::MIN,
+            days: ALL_WEEKDAYS.to_vec(),
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// Parses `HH:MM-HH:MM` working hours.
+    pub fn parse_hours(value: &str) -> Result<(NaiveTime, NaiveTime), CoreError> {
+        let invalid = || CoreError::InvalidBusinessCalendar(format!("bad hours '{value}'"));
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+        Ok((start, end))
+    }
+
+    /// Parses working days such as `MO-FR`, `MO,WE,FR` or `SA-MO`.
+    pub fn parse_days(value: &str) -> Result<Vec<Weekday>, CoreError> {
+        let invalid = || CoreError::InvalidBusinessCalendar(format!("bad days '{value}'"));
+        let mut days = Vec::new();
+        for part in value.split(',') {
+            let (from, to) = part.split_once('-').unwrap_or((part, part));
+            let from = parse_weekday_code(from).ok_or_else(invalid)?;
+            let to = parse_weekday_code(to).ok_or_else(invalid)?;
+            let mut day = from;
+            loop {
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+                if day == to {
+                    break;
+                }
+                day = day.succ();
+            }
+        }
+        Ok(days)
+    }
+
+    pub fn contains(&self, local: NaiveDateTime) -> bool {
+        if !self.days.contains(&local.weekday()) {
+            return false;
+        }
+        let time = local.time();
+        match self.start.cmp(&self.end) {
+            Ordering::Equal => true,
+            Ordering::Less => time >= self.start && time < self.end,
+            Ordering::Greater => time >= self.start || time < self.end,
+        }
+    }
+
+    fn meta(&self) -> BusinessCalendarMeta {
+        BusinessCalendarMeta {
+            hours: format!(
+                "{}-{}",
+                self.start.format("%H:%M"),
+                self.end.format("%H:%M")
+            ),
+            days: self
+                .days
+                .iter()
+                .map(|day| weekday_code(*day).to_string())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ExpandResult {
     pub meta: ExpandMeta,
     pub occurrences: Vec<Occurrence>,
+    /// Runs of consecutive active ISO weeks; set by [`apply_week_spans`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_spans: Option<Vec<WeekSpan>>,
+    /// Occurrence counts aggregated into a grid; set by [`apply_heatmap`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap: Option<Heatmap>,
+}
+
+/// Grid axes for [`apply_heatmap`], both judged on local occurrence times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapKind {
+    /// Rows `MO`..`SU`, columns hours `00`..`23`.
+    WeekdayHour,
+    /// Rows months `01`..`12`, columns days `01`..`31`.
+    MonthDay,
+}
+
+/// Occurrence counts where `counts[row][column]` matches `rows`/`columns`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heatmap {
+    pub kind: String,
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+    pub counts: Vec<Vec<usize>>,
+}
+
+/// Consecutive ISO weeks (`2026-W10`) that share the same active weekdays.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WeekSpan {
+    pub from_week: String,
+    pub to_week: String,
+    pub weekdays: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulateResult {
+    pub meta: ExpandMeta,
+    pub occurrences: Vec<SimulatedOccurrence>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedOccurrence {
+    #[serde(flatten)]
+    pub occurrence: Occurrence,
+    /// Exact elapsed time since the previous occurrence as an ISO 8601
+    /// duration (`PT23H`); `None` for the first one.
+    pub gap_from_previous: Option<String>,
+}
+
+/// A local calendar day and how many occurrences start on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayCount {
+    pub date: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergedRules {
+    pub rrules: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CountResult {
+    pub count: usize,
+    /// Present when a threshold was requested: `true` once it was reached and
+    /// counting stopped early, `false` when the window holds fewer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_least: Option<bool>,
+    /// Present when [`CountOptions::by_source`] was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub by_source: Option<SourceCounts>,
+}
+
+/// Occurrences per [`OccurrenceSource`], attributed as in expanded output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SourceCounts {
+    #[serde(rename = "RRULE")]
+    pub rrule: usize,
+    #[serde(rename = "RDATE")]
+    pub rdate: usize,
+}
+
+/// Options for [`count_occurrences`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountOptions {
+    /// Stop as soon as this many occurrences were seen.
+    pub at_least: Option<usize>,
+    /// Also tally occurrences per source.
+    pub by_source: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -85,6 +1035,14 @@ pub struct Finding {
     pub code: String,
     pub message: String,
     pub details: Option<String>,
+    /// Index of the rule the finding concerns, counting `rrules` first and
+    /// then `exrules`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_index: Option<usize>,
+    /// Byte offsets `(start, end)` of the offending `KEY=VALUE` part within
+    /// the rule string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<(usize, usize)>,
 }
 
 #[derive(Debug, Clone, Default, Serialize)]
@@ -100,6 +1058,25 @@ impl Findings {
     }
 }
 
+/// One-shot overview of a whole series; see [`summarize_series`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesSummary {
+    /// One sentence per RRULE, as [`describe`] phrases it.
+    pub description: Vec<String>,
+    pub first: Option<String>,
+    /// `None` for an infinite series queried without a window end.
+    pub last: Option<String>,
+    /// Occurrences in the series (or window); `None` when unbounded.
+    pub count: Option<usize>,
+    pub is_finite: bool,
+    /// Instances within the window removed by an EXDATE; `None` whenever
+    /// `count` is, and EXDATEs matching no instance are not counted.
+    pub exdates: Option<usize>,
+    /// Instances within the window removed by an EXRULE; `None` whenever
+    /// `count` is. An instance also listed as EXDATE counts for both.
+    pub exrules: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ExplainResult {
     pub at: String,
@@ -107,9 +1084,105 @@ pub struct ExplainResult {
     pub generated_by: Option<OccurrenceSource>,
     pub generated_rule_index: Option<usize>,
     pub excluded_by: Option<String>,
+    /// Local start times of all occurrences on the queried day; only set
+    /// when `--at` is a date.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day_matches: Option<Vec<String>>,
+    /// Every EXDATE/EXRULE matching `at`; only set with
+    /// [`ExplainOptions::all_exclusions`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusions: Option<Vec<Exclusion>>,
+    /// Structural checks of the closest RRULE against a time it did not
+    /// generate; only set with [`ExplainOptions::why_not`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub why_not: Option<WhyNot>,
+    /// Local start of the last occurrence strictly before `at`, as
+    /// [`previous_occurrence`] finds it; only set when `at` is not included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nearest_before: Option<String>,
+    /// Local start of the first occurrence strictly after `at`, as
+    /// [`next_occurrence`] finds it; only set when `at` is not included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nearest_after: Option<String>,
     pub notes: Vec<String>,
 }
 
+/// Why the RRULE failing the fewest checks did not generate `at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WhyNot {
+    pub rule_index: usize,
+    /// Names of the failed checks, in evaluation order.
+    pub failed: Vec<String>,
+    pub checks: Vec<RuleCheck>,
+}
+
+/// One condition an RRULE puts on its occurrences, e.g. `byday`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleCheck {
+    pub check: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Exclusion {
+    /// `EXDATE` or `EXRULE`.
+    pub kind: String,
+    /// Position within the spec's `exdates` or `exrules`.
+    pub index: usize,
+}
+
+/// Options for [`explain_with`] and [`explain_local_with`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExplainOptions {
+    /// Report every matching exclusion instead of only the first reason.
+    pub all_exclusions: bool,
+    /// When no rule generates `at`, report which of the closest RRULE's
+    /// conditions it fails.
+    pub why_not: bool,
+    /// When no rule generates `at` but an occurrence lies at most this far
+    /// away, add a "Did you mean ...?" note naming it.
+    pub suggest_within: Option<Duration>,
+    /// Abort the `nearest_before`/`nearest_after` search with
+    /// [`CoreError::TimeLimitExceeded`] once it has run longer than this.
+    pub budget: Option<StdDuration>,
+}
+
+/// Single-level, log-friendly view of an [`ExplainResult`] without notes.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlatExplainResult {
+    pub at: String,
+    pub included: bool,
+    pub source: Option<OccurrenceSource>,
+    pub rule_index: Option<usize>,
+    pub excluded_by: Option<String>,
+}
+
+impl ExplainResult {
+    pub fn flat(&self) -> FlatExplainResult {
+        FlatExplainResult {
+            at: self.at.clone(),
+            included: self.included,
+            source: self.generated_by.clone(),
+            rule_index: self.generated_rule_index,
+            excluded_by: self.excluded_by.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub freq: Vec<&'static str>,
+    /// RRULE/EXRULE parts accepted by validation.
+    pub rule_parts: Vec<&'static str>,
+    pub unsupported_rule_parts: Vec<&'static str>,
+    pub ics_properties: Vec<&'static str>,
+    /// Accepted, but obsoleted by RFC 5545.
+    pub deprecated_properties: Vec<&'static str>,
+    pub value_types: Vec<&'static str>,
+    pub inputs: Vec<&'static str>,
+}
+
 #[derive(Debug, Error)]
 pub enum CoreError {
     #[error("unknown timezone '{0}'")]
@@ -127,7 +1200,14 @@ pub enum CoreError {
     #[error("invalid ICS input: {0}")]
     InvalidIcs(String),
 
-    #[error("hard limit exceeded ({limit}). Use a smaller window or a higher --limit")]
+    #[error("invalid JSON input: {0}")]
+    InvalidJson(String),
+
+    /// More than `limit` occurrences survived exclusion; dates removed by
+    /// EXRULE/EXDATE never count toward the limit.
+    #[error(
+        "hard limit exceeded ({limit} included occurrences; excluded dates do not count). Use a smaller window or a higher --limit"
+    )]
     LimitExceeded { limit: usize },
 
     #[error("invalid limit '{0}': limit must be > 0")]
@@ -138,18 +1218,222 @@ pub enum CoreError {
 
     #[error("unbounded RRULE requires --between, --after/--count, or explicit --limit")]
     UnsafeUnboundedRule,
-}
 
-pub fn parse_timezone(value: &str) -> Result<Tz, CoreError> {
-    value
+    #[error(
+        "expansion exceeded the time budget ({budget_ms} ms). Use a smaller window or a higher --max-duration"
+    )]
+    TimeLimitExceeded { budget_ms: u128 },
+
+    #[error("--between start must be <= end")]
+    InvalidWindow,
+
+    #[error("invalid business calendar: {0}")]
+    InvalidBusinessCalendar(String),
+
+    #[error("invalid PRODID: {0}")]
+    InvalidProdid(String),
+
+    #[error("invalid UID template: {0}")]
+    InvalidUidTemplate(String),
+
+    #[error("invalid duration '{input}': {reason}")]
+    InvalidDuration { input: String, reason: String },
+
+    #[error("no RRULE with index {index}; indexes start at 0 and the spec has {rule_count}")]
+    InvalidRuleIndex { index: usize, rule_count: usize },
+}
+
+/// Machine-readable form of an error: a stable `code` per [`CoreError`]
+/// variant, the display message, and the variant's own fields.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl CoreError {
+    /// Stable identifier for the variant, e.g. `LIMIT_EXCEEDED`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CoreError::InvalidTimezone(_) => "INVALID_TIMEZONE",
+            CoreError::InvalidDateTime { .. } => "INVALID_DATETIME",
+            CoreError::InvalidRrule { .. } => "INVALID_RRULE",
+            CoreError::MissingField(_) => "MISSING_FIELD",
+            CoreError::InvalidIcs(_) => "INVALID_ICS",
+            CoreError::InvalidJson(_) => "INVALID_JSON",
+            CoreError::LimitExceeded { .. } => "LIMIT_EXCEEDED",
+            CoreError::InvalidLimit(_) => "INVALID_LIMIT",
+            CoreError::InvalidCount(_) => "INVALID_COUNT",
+            CoreError::UnsafeUnboundedRule => "UNSAFE_UNBOUNDED_RULE",
+            CoreError::TimeLimitExceeded { .. } => "TIME_LIMIT_EXCEEDED",
+            CoreError::InvalidWindow => "INVALID_WINDOW",
+            CoreError::InvalidBusinessCalendar(_) => "INVALID_BUSINESS_CALENDAR",
+            CoreError::InvalidProdid(_) => "INVALID_PRODID",
+            CoreError::InvalidUidTemplate(_) => "INVALID_UID_TEMPLATE",
+            CoreError::InvalidDuration { .. } => "INVALID_DURATION",
+            CoreError::InvalidRuleIndex { .. } => "INVALID_RULE_INDEX",
+        }
+    }
+
+    /// The error as an [`ErrorReport`]; `message` is the `Display` text.
+    pub fn report(&self) -> ErrorReport {
+        let report = ErrorReport {
+            code: self.code(),
+            message: self.to_string(),
+            ..ErrorReport::default()
+        };
+        match self {
+            CoreError::InvalidTimezone(input) => ErrorReport {
+                input: Some(input.clone()),
+                ..report
+            },
+            CoreError::InvalidDateTime { input, reason }
+            | CoreError::InvalidDuration { input, reason } => ErrorReport {
+                input: Some(input.clone()),
+                reason: Some(reason.clone()),
+                ..report
+            },
+            CoreError::InvalidRrule { rule, reason } => ErrorReport {
+                rule: Some(rule.clone()),
+                reason: Some(reason.clone()),
+                ..report
+            },
+            CoreError::MissingField(field) => ErrorReport {
+                field: Some(field.clone()),
+                ..report
+            },
+            CoreError::InvalidIcs(reason)
+            | CoreError::InvalidJson(reason)
+            | CoreError::InvalidBusinessCalendar(reason)
+            | CoreError::InvalidProdid(reason)
+            | CoreError::InvalidUidTemplate(reason) => ErrorReport {
+                reason: Some(reason.clone()),
+                ..report
+            },
+            CoreError::LimitExceeded { limit } | CoreError::InvalidLimit(limit) => ErrorReport {
+                limit: Some(*limit),
+                ..report
+            },
+            CoreError::InvalidCount(count) => ErrorReport {
+                count: Some(*count),
+                ..report
+            },
+            CoreError::TimeLimitExceeded { budget_ms } => ErrorReport {
+                budget_ms: Some(*budget_ms),
+                ..report
+            },
+            CoreError::InvalidRuleIndex { index, rule_count } => ErrorReport {
+                index: Some(*index),
+                count: Some(*rule_count),
+                ..report
+            },
+            CoreError::UnsafeUnboundedRule | CoreError::InvalidWindow => report,
+        }
+    }
+}
+
+pub fn parse_timezone(value: &str) -> Result<Tz, CoreError> {
+    parse_chrono_tz(value).map(Tz::from)
+}
+
+/// Zones added or renamed in recent tzdata releases, with the older name
+/// that carries the same rules. Only consulted when the bundled `chrono_tz`
+/// predates the new name.
+const TIMEZONE_ALIASES: [(&str, &str); 5] = [
+    // Split from Ojinaga in 2022g.
+    ("America/Ciudad_Juarez", "America/Ojinaga"),
+    // Renamed in 2022b, 2021b, 2020a and 2016d.
+    ("Europe/Kyiv", "Europe/Kiev"),
+    ("Pacific/Kanton", "Pacific/Enderbury"),
+    ("America/Nuuk", "America/Godthab"),
+    ("Asia/Yangon", "Asia/Rangoon"),
+];
+
+/// The older zone standing in for `name` because the bundled tzdata does
+/// not know it, so callers can warn that an alias is in use.
+pub fn timezone_fallback(name: &str) -> Option<&'static str> {
+    fallback_for(name, |zone| zone.parse::<ChronoTz>().is_ok())
+}
+
+fn fallback_for(name: &str, known: impl Fn(&str) -> bool) -> Option<&'static str> {
+    if known(name) {
+        return None;
+    }
+    TIMEZONE_ALIASES
+        .iter()
+        .find(|(new, _)| *new == name)
+        .map(|(_, old)| *old)
+}
+
+fn parse_chrono_tz(value: &str) -> Result<ChronoTz, CoreError> {
+    value
         .parse::<ChronoTz>()
-        .map(Tz::from)
+        .or_else(|err| timezone_fallback(value).ok_or(err)?.parse::<ChronoTz>())
         .map_err(|_| CoreError::InvalidTimezone(value.to_string()))
 }
 
+/// Parses `YYYY-MM-DD`, naive `YYYY-MM-DDTHH:MM:SS` or RFC 3339.
+///
+/// Naive values are wall-clock time in `tz`; an explicit offset or `Z` keeps
+/// the absolute instant, which is only converted into `tz` for display.
+/// Ambiguous local times are rejected, see [`parse_iso_datetime_with`].
 pub fn parse_iso_datetime(
     value: &str,
     tz: &Tz,
+) -> Result<(DateTime<Tz>, DateValueType), CoreError> {
+    parse_iso_datetime_with(value, tz, DstPolicy::Reject)
+}
+
+/// [`parse_iso_datetime`] resolving fall-back ambiguities with `policy`.
+pub fn parse_iso_datetime_with(
+    value: &str,
+    tz: &Tz,
+    policy: DstPolicy,
+) -> Result<(DateTime<Tz>, DateValueType), CoreError> {
+    let (dt, kind) = parse_iso_datetime_any_year(value, tz, policy)?;
+    // Instants are whole seconds throughout, as in RFC 5545, which is what
+    // lets a half-open window end be closed one second early.
+    if dt.nanosecond() != 0 {
+        return Err(CoreError::InvalidDateTime {
+            input: value.to_string(),
+            reason: "fractional seconds are not supported; times are whole seconds".to_string(),
+        });
+    }
+    Ok((within_max_year(dt, value)?, kind))
+}
+
+/// Fails when `dt` lies past [`MAX_YEAR`] in UTC, which a local time late on
+/// 9999-12-31 west of Greenwich does.
+fn within_max_year(dt: DateTime<Tz>, input: &str) -> Result<DateTime<Tz>, CoreError> {
+    if dt.with_timezone(&Utc).year() > MAX_YEAR {
+        return Err(CoreError::InvalidDateTime {
+            input: input.to_string(),
+            reason: format!("year is beyond {MAX_YEAR} in UTC"),
+        });
+    }
+    Ok(dt)
+}
+
+fn parse_iso_datetime_any_year(
+    value: &str,
+    tz: &Tz,
+    policy: DstPolicy,
 ) -> Result<(DateTime<Tz>, DateValueType), CoreError> {
     if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
         let local = date
@@ -158,7 +1442,7 @@ pub fn parse_iso_datetime(
                 input: value.to_string(),
                 reason: "could not build midnight datetime".to_string(),
             })?;
-        return localize(*tz, local, value).map(|dt| (dt, DateValueType::Date));
+        return localize(*tz, local, value, policy).map(|dt| (dt, DateValueType::Date));
     }
 
     if let Ok(fixed) = DateTime::parse_from_rfc3339(value) {
@@ -166,7 +1450,19 @@ pub fn parse_iso_datetime(
     }
 
     if let Ok(local) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
-        return localize(*tz, local, value).map(|dt| (dt, DateValueType::DateTime));
+        return localize(*tz, local, value, policy).map(|dt| (dt, DateValueType::DateTime));
+    }
+
+    let year_digits = value
+        .trim_start_matches('+')
+        .split('-')
+        .next()
+        .unwrap_or_default();
+    if year_digits.len() > 4 && year_digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(CoreError::InvalidDateTime {
+            input: value.to_string(),
+            reason: format!("year is beyond {MAX_YEAR}"),
+        });
     }
 
     Err(CoreError::InvalidDateTime {
@@ -176,26 +1472,371 @@ pub fn parse_iso_datetime(
 }
 
 pub fn parse_ics_spec(input: &str, fallback_tz: Option<&str>) -> Result<RecurrenceSpec, CoreError> {
+    parse_ics_spec_with(input, fallback_tz, IcsOptions::default())
+}
+
+/// Parses a single-event ICS file; a file with several VEVENTs is rejected,
+/// use [`parse_ics_event`] to select one by UID.
+pub fn parse_ics_spec_with(
+    input: &str,
+    fallback_tz: Option<&str>,
+    options: IcsOptions,
+) -> Result<RecurrenceSpec, CoreError> {
+    parse_ics_event(input, fallback_tz, options, None)
+}
+
+/// Parses every VEVENT of a calendar into its own spec, each with its own
+/// DTSTART, rule set and timezone resolution. Properties of nested
+/// components (VALARM, ...) are ignored; input without any `BEGIN:VEVENT`
+/// is read as one event.
+pub fn parse_ics_calendar(
+    input: &str,
+    fallback_tz: Option<&str>,
+    options: IcsOptions,
+) -> Result<Vec<RecurrenceSpec>, CoreError> {
+    let (overrides, events): (Vec<_>, Vec<_>) = split_vevents(input)
+        .into_iter()
+        .partition(|event| is_override_event(&event.lines));
+    events
+        .into_iter()
+        .map(|event| {
+            let mut spec = parse_ics_event_lines(event, fallback_tz, options)?;
+            attach_overrides(&mut spec, &overrides, options)?;
+            Ok(spec)
+        })
+        .collect()
+}
+
+/// Parses the VEVENT whose UID (given or synthesized) is `uid`. Without a
+/// selector the file must hold exactly one VEVENT. Only the selected event
+/// is parsed when it carries an explicit UID, so unsupported sibling events
+/// (e.g. single, non-recurring ones) do not get in the way.
+///
+/// VEVENTs with a `RECURRENCE-ID` are not counted as events of their own;
+/// they are attached as [`InstanceOverride`]s to the master with their UID.
+pub fn parse_ics_event(
+    input: &str,
+    fallback_tz: Option<&str>,
+    options: IcsOptions,
+    uid: Option<&str>,
+) -> Result<RecurrenceSpec, CoreError> {
+    let (overrides, events): (Vec<_>, Vec<_>) = split_vevents(input)
+        .into_iter()
+        .partition(|event| is_override_event(&event.lines));
+    let mut spec = select_ics_event(events, fallback_tz, options, uid)?;
+    attach_overrides(&mut spec, &overrides, options)?;
+    Ok(spec)
+}
+
+fn select_ics_event(
+    mut events: Vec<IcsEvent>,
+    fallback_tz: Option<&str>,
+    options: IcsOptions,
+    uid: Option<&str>,
+) -> Result<RecurrenceSpec, CoreError> {
+    let Some(wanted) = uid else {
+        if events.len() > 1 {
+            let uids: Vec<String> = events
+                .iter()
+                .map(|event| event_uid(&event.lines).unwrap_or_else(|| "(no UID)".to_string()))
+                .collect();
+            return Err(CoreError::InvalidIcs(format!(
+                "file contains {} VEVENTs (UIDs: {}); select one with --uid",
+                events.len(),
+                uids.join(", ")
+            )));
+        }
+        let event = events.pop().unwrap_or(IcsEvent {
+            lines: Vec::new(),
+            method: None,
+        });
+        return parse_ics_event_lines(event, fallback_tz, options);
+    };
+
+    let explicit: Vec<usize> = (0..events.len())
+        .filter(|&index| event_uid(&events[index].lines).as_deref() == Some(wanted))
+        .collect();
+    let mut matches = match explicit.as_slice() {
+        // Only UID-less events can match through their synthesized UID.
+        [] => events
+            .into_iter()
+            .filter(|event| event_uid(&event.lines).is_none())
+            .map(|event| parse_ics_event_lines(event, fallback_tz, options))
+            .filter(|spec| {
+                spec.as_ref()
+                    .map_or(true, |spec| spec.uid.as_deref() == Some(wanted))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        [index] => {
+            let event = events.swap_remove(*index);
+            vec![parse_ics_event_lines(event, fallback_tz, options)?]
+        }
+        many => {
+            return Err(CoreError::InvalidIcs(format!(
+                "{} VEVENTs share UID '{wanted}'",
+                many.len()
+            )));
+        }
+    };
+    match matches.len() {
+        0 => Err(CoreError::InvalidIcs(format!(
+            "no VEVENT with UID '{wanted}'"
+        ))),
+        1 => Ok(matches.remove(0)),
+        n => Err(CoreError::InvalidIcs(format!(
+            "{n} VEVENTs share UID '{wanted}'"
+        ))),
+    }
+}
+
+/// Unfolded property lines of one top-level VEVENT, without the BEGIN/END
+/// markers and nested components, plus its calendar's `METHOD`.
+struct IcsEvent {
+    lines: Vec<String>,
+    method: Option<String>,
+}
+
+/// Splits the input into VEVENTs. Concatenated VCALENDAR blocks are read
+/// one after another; each event takes the `METHOD` of its own calendar.
+fn split_vevents(input: &str) -> Vec<IcsEvent> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
     let lines = unfold_ics_lines(input);
+    let marker = |line: &str| {
+        let (name, value) = line.split_once(':')?;
+        let name = name.trim().to_ascii_uppercase();
+        let value = value.trim().to_ascii_uppercase();
+        matches!(name.as_str(), "BEGIN" | "END").then_some((name, value))
+    };
+    let is = |marker: &Option<(String, String)>, name: &str, value: &str| {
+        marker
+            .as_ref()
+            .is_some_and(|(n, v)| n == name && v == value)
+    };
+
+    let mut events: Vec<IcsEvent> = Vec::new();
+    let mut current: Option<Vec<String>> = None;
+    let mut nested = 0usize;
+    let mut method: Option<String> = None;
+    // First event of the current VCALENDAR; METHOD may follow its events.
+    let mut calendar_start = 0;
+    let assign_method = |events: &mut Vec<IcsEvent>, from: usize, method: &Option<String>| {
+        for event in events.iter_mut().skip(from) {
+            event.method = method.clone();
+        }
+    };
+
+    for line in &lines {
+        let found = marker(line);
+        let Some(event) = current.as_mut() else {
+            if is(&found, "BEGIN", "VEVENT") {
+                current = Some(Vec::new());
+            } else if is(&found, "BEGIN", "VCALENDAR") {
+                method = None;
+                calendar_start = events.len();
+            } else if is(&found, "END", "VCALENDAR") {
+                assign_method(&mut events, calendar_start, &method);
+                method = None;
+                calendar_start = events.len();
+            } else if let Some(colon) = find_outside_quotes(line, ':') {
+                let (name, _) = parse_property_head(&line[..colon]);
+                if name == "METHOD" {
+                    method = Some(line[colon + 1..].trim().to_ascii_uppercase());
+                }
+            }
+            continue;
+        };
+        match found {
+            Some((name, value)) if name == "END" && value == "VEVENT" && nested == 0 => {
+                let lines = current.take().unwrap_or_default();
+                events.push(IcsEvent {
+                    lines,
+                    method: None,
+                });
+            }
+            Some((name, _)) if name == "BEGIN" => nested += 1,
+            Some((name, _)) if name == "END" => nested = nested.saturating_sub(1),
+            _ if nested == 0 => event.push(line.clone()),
+            _ => {}
+        }
+    }
+    if let Some(lines) = current {
+        events.push(IcsEvent {
+            lines,
+            method: None,
+        });
+    }
+    assign_method(&mut events, calendar_start, &method);
+
+    if events.is_empty() {
+        events.push(IcsEvent { lines, method });
+    }
+    events
+}
+
+/// The event's explicit `UID` property, if any.
+fn event_uid(lines: &[String]) -> Option<String> {
+    lines.iter().find_map(|line| {
+        let colon = find_outside_quotes(line, ':')?;
+        let (name, _) = parse_property_head(&line[..colon]);
+        (name == "UID").then(|| unescape_ics_text(line[colon + 1..].trim()))
+    })
+}
+
+/// Whether the event carries a `RECURRENCE-ID`, i.e. replaces one instance
+/// of another event rather than defining a series.
+fn is_override_event(lines: &[String]) -> bool {
+    lines.iter().any(|line| {
+        find_outside_quotes(line, ':')
+            .is_some_and(|colon| parse_property_head(&line[..colon]).0 == "RECURRENCE-ID")
+    })
+}
+
+/// Attaches the override events sharing the master's explicit UID. For
+/// every RECURRENCE-ID the override with the highest `SEQUENCE` wins (the
+/// later one in the file on a tie); overrides naming a date the series
+/// does not generate are dropped.
+fn attach_overrides(
+    spec: &mut RecurrenceSpec,
+    events: &[IcsEvent],
+    options: IcsOptions,
+) -> Result<(), CoreError> {
+    if spec.uid_synthesized {
+        return Ok(());
+    }
+    let mut latest: BTreeMap<i64, InstanceOverride> = BTreeMap::new();
+    for event in events {
+        if event_uid(&event.lines) != spec.uid {
+            continue;
+        }
+        let parsed = parse_override(&event.lines, spec, options)?;
+        let key = parsed.recurrence_id.timestamp();
+        if latest
+            .get(&key)
+            .is_none_or(|current| parsed.sequence >= current.sequence)
+        {
+            latest.insert(key, parsed);
+        }
+    }
+    spec.overrides = latest.into_values().collect();
+    // Rule errors surface on expansion; keep the overrides unchecked here.
+    if let Ok((rrules, exrules)) = parse_validated_rules(spec) {
+        let generated: Vec<bool> = spec
+            .overrides
+            .iter()
+            .map(|o| {
+                let at = Window {
+                    start: Some(o.recurrence_id),
+                    end: Some(o.recurrence_id),
+                };
+                series_window_dates(spec, &rrules, &exrules, &at, Deadline::default())
+                    .next()
+                    .is_some()
+            })
+            .collect();
+        let mut keep = generated.into_iter();
+        spec.overrides.retain(|_| keep.next().unwrap_or(false));
+    }
+    Ok(())
+}
+
+/// Reads one override VEVENT. Times without TZID take the master's zone;
+/// `DTSTART` defaults to the RECURRENCE-ID (a pure SEQUENCE bump).
+fn parse_override(
+    lines: &[String],
+    spec: &RecurrenceSpec,
+    options: IcsOptions,
+) -> Result<InstanceOverride, CoreError> {
+    let mut recurrence_id = None;
+    let mut start = None;
+    let mut sequence = 0;
+    for line in lines {
+        let Some(colon) = find_outside_quotes(line, ':') else {
+            continue;
+        };
+        let value = line[colon + 1..].trim();
+        let (name, params) = parse_property_head(&line[..colon]);
+        match name.as_str() {
+            "RECURRENCE-ID" | "DTSTART" => {
+                if name == "RECURRENCE-ID"
+                    && params
+                        .get("RANGE")
+                        .is_some_and(|range| range.eq_ignore_ascii_case("THISANDFUTURE"))
+                {
+                    return Err(CoreError::InvalidIcs(
+                        "RECURRENCE-ID;RANGE=THISANDFUTURE is not supported".to_string(),
+                    ));
+                }
+                check_value_type(&name, &params, value)?;
+                let tz = resolve_property_tz(&params, Some(&spec.tz))?;
+                let value_type = parse_value_type_for_multi(&params, value);
+                let parsed = parse_ics_datetime_value(value, &tz, value_type, options.dst_policy)?;
+                if name == "DTSTART" {
+                    start = Some(parsed);
+                } else {
+                    recurrence_id = Some(parsed);
+                }
+            }
+            "SEQUENCE" => {
+                sequence = value
+                    .parse()
+                    .map_err(|_| CoreError::InvalidIcs(format!("invalid SEQUENCE '{value}'")))?;
+            }
+            _ => {}
+        }
+    }
+    let recurrence_id =
+        recurrence_id.ok_or_else(|| CoreError::MissingField("RECURRENCE-ID".to_string()))?;
+    Ok(InstanceOverride {
+        recurrence_id,
+        start: start.unwrap_or(recurrence_id),
+        sequence,
+    })
+}
 
+fn parse_ics_event_lines(
+    event: IcsEvent,
+    fallback_tz: Option<&str>,
+    options: IcsOptions,
+) -> Result<RecurrenceSpec, CoreError> {
+    let IcsEvent { lines, method } = event;
     let mut dtstart: Option<DateTime<Tz>> = None;
     let mut dtstart_type = DateValueType::DateTime;
     let mut tz_name: Option<String> = fallback_tz.map(ToOwned::to_owned);
+    let mut floating = false;
     let mut rrules = Vec::new();
     let mut rdates = Vec::new();
     let mut exrules = Vec::new();
     let mut exdates = Vec::new();
+    let mut location = None;
+    let mut summary = None;
+    let mut property_tzids = Vec::new();
+    let mut short_datetimes = Vec::new();
+    let mut geo = None;
+    let mut uid = None;
+    let mut duration = None;
+    let mut dtend: Option<(String, HashMap<String, String>)> = None;
 
     for line in lines {
-        let Some((head, raw_value)) = line.split_once(':') else {
+        let Some(colon) = find_outside_quotes(&line, ':') else {
             continue;
         };
+        let (head, raw_value) = (&line[..colon], &line[colon + 1..]);
 
         let value = raw_value.trim();
         let (name, params) = parse_property_head(head);
+        if matches!(name.as_str(), "DTSTART" | "DTEND" | "RDATE" | "EXDATE") {
+            short_datetimes.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|part| lacks_seconds(part))
+                    .map(|part| format!("{name}:{part}")),
+            );
+        }
 
         match name.as_str() {
             "DTSTART" => {
+                check_value_type(&name, &params, value)?;
                 let value_type = match params.get("VALUE") {
                     Some(v) if v.eq_ignore_ascii_case("DATE") => DateValueType::Date,
                     _ => {
@@ -216,6 +1857,9 @@ pub fn parse_ics_spec(input: &str, fallback_tz: Option<&str>) -> Result<Recurren
                     Tz::UTC
                 } else if let Some(ref tzid) = tzid {
                     parse_timezone(tzid)?
+                } else if options.floating {
+                    floating = true;
+                    Tz::UTC
                 } else {
                     return Err(CoreError::InvalidIcs(
                         "DTSTART without TZID must be UTC (..Z) or --tz must be provided"
@@ -223,7 +1867,7 @@ pub fn parse_ics_spec(input: &str, fallback_tz: Option<&str>) -> Result<Recurren
                     ));
                 };
 
-                let parsed = parse_ics_datetime_value(value, &tz, value_type)?;
+                let parsed = parse_ics_datetime_value(value, &tz, value_type, options.dst_policy)?;
                 dtstart = Some(parsed);
                 dtstart_type = value_type;
                 tz_name = Some(tzid.unwrap_or_else(|| tz.name().to_string()));
@@ -231,31 +1875,82 @@ pub fn parse_ics_spec(input: &str, fallback_tz: Option<&str>) -> Result<Recurren
             "RRULE" => rrules.push(value.to_string()),
             "EXRULE" => exrules.push(value.to_string()),
             "RDATE" => {
+                check_value_type(&name, &params, value)?;
+                if let Some(tzid) = params.get("TZID") {
+                    property_tzids.push(PropertyTzid {
+                        property: name.clone(),
+                        tzid: tzid.clone(),
+                    });
+                }
                 let tz = resolve_property_tz(&params, tz_name.as_deref())?;
                 let value_type = parse_value_type_for_multi(&params, value);
-                let parsed = parse_ics_multi_datetime_values(value, &tz, value_type)?;
+                let parsed =
+                    parse_ics_multi_datetime_values(value, &tz, value_type, options.dst_policy)?;
                 rdates.extend(parsed);
             }
             "EXDATE" => {
+                check_value_type(&name, &params, value)?;
+                if let Some(tzid) = params.get("TZID") {
+                    property_tzids.push(PropertyTzid {
+                        property: name.clone(),
+                        tzid: tzid.clone(),
+                    });
+                }
                 let tz = resolve_property_tz(&params, tz_name.as_deref())?;
                 let value_type = parse_value_type_for_multi(&params, value);
-                let parsed = parse_ics_multi_datetime_values(value, &tz, value_type)?;
+                let parsed =
+                    parse_ics_multi_datetime_values(value, &tz, value_type, options.dst_policy)?;
                 exdates.extend(parsed);
             }
+            "LOCATION" => location = Some(unescape_ics_text(value)),
+            "SUMMARY" => summary = Some(unescape_ics_text(value)),
+            "GEO" => geo = Some(parse_geo(value)?),
+            "UID" => uid = Some(unescape_ics_text(value)),
+            "DURATION" => duration = Some(EventDuration::parse(value)?),
+            "DTEND" => dtend = Some((value.to_string(), params)),
             _ => {}
         }
     }
 
     let dtstart = dtstart.ok_or_else(|| CoreError::MissingField("DTSTART".to_string()))?;
+    if let Some((value, params)) = dtend {
+        if duration.is_some() {
+            return Err(CoreError::InvalidIcs(
+                "DTEND and DURATION are mutually exclusive".to_string(),
+            ));
+        }
+        duration = Some(dtend_duration(
+            dtstart,
+            dtstart_type,
+            &value,
+            &params,
+            tz_name.as_deref(),
+            options.dst_policy,
+        )?);
+    }
     let tz = tz_name.unwrap_or_else(|| dtstart.timezone().name().to_string());
 
+    let (rdates, duplicate_rdates) = dedupe_dates(rdates);
+    let (exdates, duplicate_exdates) = dedupe_dates(exdates);
+
     if rrules.is_empty() && rdates.is_empty() {
         return Err(CoreError::MissingField(
             "at least one RRULE or RDATE".to_string(),
         ));
     }
 
-    Ok(RecurrenceSpec {
+    if uid.is_none() && options.rfc_strict {
+        return Err(CoreError::InvalidIcs(
+            "VEVENT has no UID (required in --rfc-strict mode)".to_string(),
+        ));
+    }
+    if let Some(value) = short_datetimes.first().filter(|_| options.rfc_strict) {
+        return Err(CoreError::InvalidIcs(format!(
+            "{value} has no seconds (required in --rfc-strict mode)"
+        )));
+    }
+
+    let mut spec = RecurrenceSpec {
         dtstart,
         dtstart_type,
         tz,
@@ -263,14 +1958,317 @@ pub fn parse_ics_spec(input: &str, fallback_tz: Option<&str>) -> Result<Recurren
         rdates,
         exrules,
         exdates,
+        location,
+        summary,
+        geo,
+        duplicate_rdates,
+        duplicate_exdates,
+        diagnostics: SpecDiagnostics {
+            property_tzids,
+            short_datetimes,
+        },
+        overrides: Vec::new(),
+        uid,
+        uid_synthesized: false,
+        duration,
+        method,
+        floating,
+    };
+    if spec.uid.is_none() {
+        spec.uid = Some(synthesize_uid(&spec));
+        spec.uid_synthesized = true;
+    }
+    Ok(spec)
+}
+
+/// The event length implied by DTEND: whole days for DATE events, otherwise
+/// the exact span, which RFC 5545 applies unchanged to every instance.
+fn dtend_duration(
+    dtstart: DateTime<Tz>,
+    dtstart_type: DateValueType,
+    value: &str,
+    params: &HashMap<String, String>,
+    tz_name: Option<&str>,
+    policy: DstPolicy,
+) -> Result<EventDuration, CoreError> {
+    check_value_type("DTEND", params, value)?;
+    let tz = resolve_property_tz(params, tz_name)?;
+    let dtend = parse_ics_datetime_value(value, &tz, dtstart_type, policy)?;
+    if dtend < dtstart {
+        return Err(CoreError::InvalidIcs("DTEND is before DTSTART".to_string()));
+    }
+    Ok(match dtstart_type {
+        DateValueType::Date => EventDuration {
+            days: (dtend.date_naive() - dtstart.date_naive()).num_days(),
+            seconds: 0,
+        },
+        DateValueType::DateTime => EventDuration {
+            days: 0,
+            seconds: (dtend - dtstart).num_seconds(),
+        },
+    })
+}
+
+/// Derives a stable UID from the recurrence contents so repeated runs over
+/// the same UID-less input agree.
+fn synthesize_uid(spec: &RecurrenceSpec) -> String {
+    format!("{:016x}@rrulex", spec_fingerprint(spec))
+}
+
+/// FNV-1a hash over the normalized recurrence definition.
+fn spec_fingerprint(spec: &RecurrenceSpec) -> u64 {
+    let format = |dt: &DateTime<Tz>| dt.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string();
+    let mut parts = vec![format(&spec.dtstart), spec.tz.clone()];
+    parts.extend(spec.rrules.iter().map(|rule| format!("RRULE:{rule}")));
+    parts.extend(spec.rdates.iter().map(|dt| format!("RDATE:{}", format(dt))));
+    parts.extend(spec.exrules.iter().map(|rule| format!("EXRULE:{rule}")));
+    parts.extend(
+        spec.exdates
+            .iter()
+            .map(|dt| format!("EXDATE:{}", format(dt))),
+    );
+    parts.extend(spec.duration.map(|duration| format!("DURATION:{duration}")));
+    if spec.floating {
+        parts.push("FLOATING".to_string());
+    }
+
+    fnv1a(parts.join("\n").as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// JSON spec document; field names mirror the CLI's direct-input flags.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonSpecInput {
+    dtstart: String,
+    tz: Option<String>,
+    #[serde(default)]
+    rrule: Vec<String>,
+    #[serde(default)]
+    rdate: Vec<String>,
+    #[serde(default)]
+    exrule: Vec<String>,
+    #[serde(default)]
+    exdate: Vec<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    floating: bool,
+    /// Only NDJSON batch lines may carry one; see [`parse_json_spec_line`].
+    id: Option<serde_json::Value>,
+}
+
+/// Parses a JSON spec such as
+/// `{"dtstart": "2026-03-01T10:00:00", "tz": "Europe/Berlin", "rrule": ["FREQ=DAILY;COUNT=3"]}`.
+///
+/// `fallback_tz` applies when the document has no `tz`; `"floating": true`
+/// documents take no zone at all.
+pub fn parse_json_spec(
+    input: &str,
+    fallback_tz: Option<&str>,
+) -> Result<RecurrenceSpec, CoreError> {
+    let doc: JsonSpecInput =
+        serde_json::from_str(input).map_err(|err| CoreError::InvalidJson(err.to_string()))?;
+    if doc.id.is_some() {
+        return Err(CoreError::InvalidJson(
+            "`id` is only accepted on batch --ndjson lines".to_string(),
+        ));
+    }
+    json_spec_from(doc, fallback_tz)
+}
+
+/// Just the `id` of a line that is not a valid spec, for reporting it.
+#[derive(Debug, Deserialize)]
+struct JsonLineId {
+    id: Option<serde_json::Value>,
+}
+
+/// Parses one NDJSON batch line, a [`parse_json_spec`] document that may
+/// also carry an `id` member. The line is read as given, so parse errors
+/// point into it; the `id` is returned even when the spec is invalid.
+pub fn parse_json_spec_line(
+    input: &str,
+    fallback_tz: Option<&str>,
+) -> (Option<serde_json::Value>, Result<RecurrenceSpec, CoreError>) {
+    match serde_json::from_str::<JsonSpecInput>(input) {
+        Ok(mut doc) => (doc.id.take(), json_spec_from(doc, fallback_tz)),
+        Err(err) => {
+            let id = serde_json::from_str::<JsonLineId>(input)
+                .ok()
+                .and_then(|line| line.id);
+            (id, Err(CoreError::InvalidJson(err.to_string())))
+        }
+    }
+}
+
+fn json_spec_from(
+    doc: JsonSpecInput,
+    fallback_tz: Option<&str>,
+) -> Result<RecurrenceSpec, CoreError> {
+    let tz_name = match doc.tz {
+        Some(_) if doc.floating => {
+            return Err(CoreError::InvalidJson(
+                "a floating spec cannot set tz".to_string(),
+            ));
+        }
+        _ if doc.floating => Tz::UTC.name().to_string(),
+        Some(tz) => tz,
+        None => fallback_tz
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| CoreError::MissingField("tz".to_string()))?,
+    };
+    let tz = parse_timezone(&tz_name)?;
+    let (dtstart, dtstart_type) = parse_iso_datetime(&doc.dtstart, &tz)?;
+
+    let parse_dates = |values: &[String]| -> Result<Vec<DateTime<Tz>>, CoreError> {
+        values
+            .iter()
+            .map(|raw| parse_iso_datetime(raw, &tz).map(|(dt, _)| dt))
+            .collect()
+    };
+    let (rdates, duplicate_rdates) = dedupe_dates(parse_dates(&doc.rdate)?);
+    let (exdates, duplicate_exdates) = dedupe_dates(parse_dates(&doc.exdate)?);
+
+    if doc.rrule.is_empty() && rdates.is_empty() {
+        return Err(CoreError::MissingField(
+            "at least one rrule or rdate".to_string(),
+        ));
+    }
+    let duration = doc
+        .duration
+        .as_deref()
+        .map(EventDuration::parse)
+        .transpose()?;
+
+    Ok(RecurrenceSpec {
+        dtstart,
+        dtstart_type,
+        tz: tz_name,
+        rrules: doc.rrule,
+        rdates,
+        exrules: doc.exrule,
+        exdates,
+        location: None,
+        summary: None,
+        geo: None,
+        duplicate_rdates,
+        duplicate_exdates,
+        diagnostics: SpecDiagnostics::default(),
+        overrides: Vec::new(),
+        uid: None,
+        uid_synthesized: false,
+        duration,
+        method: None,
+        floating: doc.floating,
     })
 }
 
+/// Canonical JSON form of `spec` in the [`parse_json_spec`] format, for cache
+/// keys and for inspecting what was parsed from ICS or flags.
+///
+/// Rules are sorted as strings and dates by instant; dates are local ISO
+/// strings with the spec's offset (`YYYY-MM-DD` for DATE specs). The format
+/// has no place for ICS-only data, so the round-trip is lossy for ICS specs:
+/// `uid`, `summary`, `location`, `geo`, `method`, the `RECURRENCE-ID`
+/// `overrides` and the [`SpecDiagnostics`] are dropped, and so are the
+/// duplicate RDATE/EXDATE values. Parsing the output yields the same series
+/// only when the spec has no overrides.
+pub fn spec_to_json(spec: &RecurrenceSpec) -> serde_json::Value {
+    let tz = spec.dtstart.timezone();
+    let format = |dt: &DateTime<Tz>| {
+        let local = dt.with_timezone(&tz);
+        match spec.dtstart_type {
+            DateValueType::Date => local.format("%Y-%m-%d").to_string(),
+            DateValueType::DateTime if spec.floating => {
+                local.format("%Y-%m-%dT%H:%M:%S").to_string()
+            }
+            DateValueType::DateTime => local.to_rfc3339(),
+        }
+    };
+    let dates = |dates: &[DateTime<Tz>]| {
+        let mut dates = dates.to_vec();
+        dates.sort();
+        dates.iter().map(format).collect::<Vec<_>>()
+    };
+    let rules = |rules: &[String]| {
+        let mut rules = rules.to_vec();
+        rules.sort();
+        rules
+    };
+    let mut doc = serde_json::json!({
+        "dtstart": format(&spec.dtstart),
+        "tz": spec.tz,
+        "rrule": rules(&spec.rrules),
+        "rdate": dates(&spec.rdates),
+        "exrule": rules(&spec.exrules),
+        "exdate": dates(&spec.exdates),
+    });
+    if let Some(duration) = spec.duration {
+        doc["duration"] = serde_json::Value::String(duration.to_string());
+    }
+    if spec.floating {
+        doc["tz"] = serde_json::Value::Null;
+        doc["floating"] = serde_json::Value::Bool(true);
+    }
+    doc
+}
+
+/// Checks that the spec's timezone resolves and every RRULE/EXRULE validates
+/// against DTSTART, without generating any occurrence.
+pub fn validate_spec(spec: &RecurrenceSpec) -> Result<(), CoreError> {
+    parse_timezone(&spec.tz)?;
+    parse_validated_rules(spec)?;
+    Ok(())
+}
+
 pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findings {
     let mut out = Findings::default();
 
-    for rule in spec.rrules.iter().chain(spec.exrules.iter()) {
-        let fields = parse_rule_fields(rule);
+    for (rule_index, rule) in spec.rrules.iter().chain(spec.exrules.iter()).enumerate() {
+        let parts = parse_rule_parts(rule);
+        let fields: HashMap<String, String> = parts
+            .iter()
+            .map(|part| (part.key.clone(), part.value.clone()))
+            .collect();
+        let span_of = |key: &str| {
+            parts
+                .iter()
+                .rev()
+                .find(|part| part.key == key)
+                .map(|part| part.span)
+        };
+
+        for part in empty_rule_parts(&parts) {
+            out.errors.push(Finding {
+                code: "E002".to_string(),
+                message: format!("{} has an empty value", part.key),
+                details: Some(format!(
+                    "Remove '{}=' or give it a value; empty parts are otherwise ignored or misread.",
+                    part.key
+                )),
+                rule_index: Some(rule_index),
+                span: Some(part.span),
+            });
+        }
+
+        if let Some(year) = until_year_beyond_max(rule, spec.dtstart) {
+            out.errors.push(Finding {
+                code: "E003".to_string(),
+                message: format!("UNTIL year {year} is beyond {MAX_YEAR}"),
+                details: Some(format!(
+                    "Dates after {MAX_YEAR} cannot be generated; drop UNTIL for an open-ended series."
+                )),
+                rule_index: Some(rule_index),
+                span: span_of("UNTIL"),
+            });
+        }
 
         if let Some(until) = fields.get("UNTIL") {
             let until_is_date = until.len() == 8 && until.chars().all(|c| c.is_ascii_digit());
@@ -283,6 +2281,8 @@ pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findin
                     details: Some(
                         "DTSTART is DATE but UNTIL is DATE-TIME. Use UNTIL=YYYYMMDD.".to_string(),
                     ),
+                    rule_index: Some(rule_index),
+                    span: span_of("UNTIL"),
                 });
             }
 
@@ -294,6 +2294,8 @@ pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findin
                         "DTSTART is DATE-TIME but UNTIL is DATE. Use UNTIL=YYYYMMDDTHHMMSS(Z)."
                             .to_string(),
                     ),
+                    rule_index: Some(rule_index),
+                    span: span_of("UNTIL"),
                 });
             }
 
@@ -301,10 +2303,16 @@ pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findin
                 out.warnings.push(Finding {
                     code: "W001".to_string(),
                     message: "UNTIL appears as local/floating time".to_string(),
-                    details: Some(
-                        "Prefer UNTIL with 'Z' (UTC) to avoid timezone ambiguity across systems."
-                            .to_string(),
-                    ),
+                    details: Some(if spec.floating {
+                        "Read as floating wall-clock time like DTSTART.".to_string()
+                    } else {
+                        format!(
+                            "rrulex reads it as local time in {}; RFC 5545 requires UTC ('Z') with a zoned DTSTART, and other systems may reject it.",
+                            spec.tz
+                        )
+                    }),
+                    rule_index: Some(rule_index),
+                    span: span_of("UNTIL"),
                 });
             }
         }
@@ -318,6 +2326,39 @@ pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findin
                 details: Some(
                     "No COUNT/UNTIL and no --between/--limit context was provided.".to_string(),
                 ),
+                rule_index: Some(rule_index),
+                span: None,
+            });
+        }
+
+        let freq = fields.get("FREQ").map(String::as_str);
+        let interval = fields
+            .get("INTERVAL")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(1);
+        let per_day = freq.and_then(|freq| occurrences_per_day(freq, interval));
+        let has_by_part = fields.keys().any(|key| key.starts_with("BY"));
+        if rule_index < spec.rrules.len()
+            && per_day.is_some_and(|n| n > 1)
+            && !has_by_part
+            && !has_count
+            && !has_until
+        {
+            out.warnings.push(Finding {
+                code: "W004".to_string(),
+                message: "High-risk unbounded sub-daily rule".to_string(),
+                details: Some(format!(
+                    "FREQ={}{} with no BYxxx narrowing and no COUNT/UNTIL fires without end, about {} times a day; expanding it is expensive even with a window. Add COUNT/UNTIL or BYxxx parts.",
+                    freq.unwrap_or_default(),
+                    if interval > 1 {
+                        format!(";INTERVAL={interval}")
+                    } else {
+                        String::new()
+                    },
+                    per_day.unwrap_or_default()
+                )),
+                rule_index: Some(rule_index),
+                span: span_of("FREQ"),
             });
         }
 
@@ -343,81 +2384,296 @@ pub fn lint(spec: &RecurrenceSpec, has_between: bool, has_limit: bool) -> Findin
                         "BYSETPOS is typically only meaningful with BYDAY/BYMONTHDAY/etc."
                             .to_string(),
                     ),
+                    rule_index: Some(rule_index),
+                    span: span_of("BYSETPOS"),
                 });
             }
         }
-    }
-
-    out
-}
 
-pub fn is_potentially_unbounded(spec: &RecurrenceSpec) -> bool {
-    spec.rrules
-        .iter()
-        .any(|rule| !rule_has_count_or_until(rule))
-}
+        let is_rrule = rule_index < spec.rrules.len();
+        if is_rrule && interval > 1 && !spec.rdates.is_empty() {
+            out.hints.push(Finding {
+                code: "H001".to_string(),
+                message: "RDATEs are not affected by RRULE INTERVAL".to_string(),
+                details: Some(format!(
+                    "INTERVAL={interval} only spaces RRULE occurrences; RDATE values are added literally."
+                )),
+                rule_index: Some(rule_index),
+                span: span_of("INTERVAL"),
+            });
+        }
 
-pub fn expand(
-    spec: &RecurrenceSpec,
-    query: &ExpandQuery,
-    hard_limit: usize,
-) -> Result<Vec<Occurrence>, CoreError> {
-    if hard_limit == 0 {
-        return Err(CoreError::InvalidLimit(hard_limit));
-    }
+        if is_rrule && has_count && !spec.rdates.is_empty() {
+            out.warnings.push(Finding {
+                code: "W009".to_string(),
+                message: "COUNT does not bound RDATEs".to_string(),
+                details: Some(format!(
+                    "COUNT={} limits only this RRULE; the {} RDATE value(s) are added on top (RFC 5545 §3.8.5.3: the set is the union of RRULE and RDATE instances), so the series can have more than COUNT occurrences.",
+                    fields.get("COUNT").map(String::as_str).unwrap_or_default(),
+                    spec.rdates.len()
+                )),
+                rule_index: Some(rule_index),
+                span: span_of("COUNT"),
+            });
+        }
 
-    let tz = parse_timezone(&spec.tz)?;
-    let (rrules, exrules) = parse_validated_rules(spec)?;
+        if is_rrule && let Some(mismatch) = dtstart_pattern_mismatch(spec, rule, &fields) {
+            out.warnings.push(Finding {
+                code: "W010".to_string(),
+                message: "DTSTART does not match the rule's own pattern".to_string(),
+                details: Some(format!(
+                    "{mismatch}. rrulex does not emit DTSTART as an occurrence here, but RFC 5545 counts DTSTART as the first instance, so other calendars may show it. Move DTSTART onto the first intended occurrence."
+                )),
+                rule_index: Some(rule_index),
+                span: span_of("BYDAY").or_else(|| span_of("BYMONTHDAY")),
+            });
+        }
 
-    let mut set = RRuleSet::new(spec.dtstart)
-        .set_rrules(rrules.clone())
-        .set_exrules(exrules.clone());
-    for dt in &spec.rdates {
-        set = set.rdate(*dt);
-    }
-    for dt in &spec.exdates {
-        set = set.exdate(*dt);
+        if is_rrule
+            && freq == Some("WEEKLY")
+            && interval > 1
+            && fields.contains_key("BYDAY")
+            && !fields.contains_key("WKST")
+        {
+            out.warnings.push(Finding {
+                code: "W005".to_string(),
+                message: "Weekly INTERVAL>1 rule with BYDAY depends on WKST".to_string(),
+                details: Some(wkst_comparison(spec, rule)),
+                rule_index: Some(rule_index),
+                span: span_of("INTERVAL"),
+            });
+        }
+
+        let ordinal_tokens: Vec<&str> = fields
+            .get("BYDAY")
+            .map(|byday| {
+                byday
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|token| {
+                        parse_byday_token(token).is_some_and(|(ordinal, _)| ordinal.is_some())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let has_ordinal_byday = !ordinal_tokens.is_empty();
+        match freq {
+            Some("MONTHLY") | None => {}
+            Some("YEARLY") if has_ordinal_byday && !fields.contains_key("BYMONTH") => {
+                out.warnings.push(Finding {
+                    code: "W006".to_string(),
+                    message: "Ordinal BYDAY in a YEARLY rule without BYMONTH".to_string(),
+                    details: Some(format!(
+                        "{} counts weekdays within the whole year (e.g. 20MO is the 20th Monday of the year), not within a month. Add BYMONTH to count within that month.",
+                        ordinal_tokens.join(",")
+                    )),
+                    rule_index: Some(rule_index),
+                    span: span_of("BYDAY"),
+                });
+            }
+            Some("YEARLY") => {}
+            Some(other) if has_ordinal_byday => {
+                out.errors.push(Finding {
+                    code: "E004".to_string(),
+                    message: format!("Ordinal BYDAY is not allowed with FREQ={other}"),
+                    details: Some(format!(
+                        "{} has a numeric prefix, which RFC 5545 allows only in MONTHLY and YEARLY rules. Drop the number or change FREQ.",
+                        ordinal_tokens.join(",")
+                    )),
+                    rule_index: Some(rule_index),
+                    span: span_of("BYDAY"),
+                });
+            }
+            Some(_) => {}
+        }
+        if fields.contains_key("BYWEEKNO") {
+            if freq.is_some_and(|freq| freq != "YEARLY") {
+                out.errors.push(Finding {
+                    code: "E005".to_string(),
+                    message: format!(
+                        "BYWEEKNO is only allowed with FREQ=YEARLY, not FREQ={}",
+                        freq.unwrap_or_default()
+                    ),
+                    details: Some(
+                        "RFC 5545 defines BYWEEKNO only for YEARLY rules. Use FREQ=YEARLY, or drop BYWEEKNO."
+                            .to_string(),
+                    ),
+                    rule_index: Some(rule_index),
+                    span: span_of("BYWEEKNO"),
+                });
+            }
+            if !fields.contains_key("WKST") {
+                out.warnings.push(Finding {
+                    code: "W008".to_string(),
+                    message: "BYWEEKNO without WKST".to_string(),
+                    details: Some(
+                        "Week numbers depend on the week start; without WKST they default to ISO 8601 weeks starting on Monday. Set WKST so every consumer numbers weeks the same way."
+                            .to_string(),
+                    ),
+                    rule_index: Some(rule_index),
+                    span: span_of("BYWEEKNO"),
+                });
+            }
+        }
+
+        let extensions: Vec<&RulePart> = parts.iter().filter(|part| part.is_extension()).collect();
+        if let Some(first) = extensions.first() {
+            let names: Vec<&str> = extensions.iter().map(|part| part.key.as_str()).collect();
+            out.hints.push(Finding {
+                code: "H006".to_string(),
+                message: format!("{} stripped for validation", names.join(", ")),
+                details: Some(
+                    "X- parts are RFC 5545 extensions with no effect on recurrence; they are kept in the rule and echoed as meta.rule_extensions."
+                        .to_string(),
+                ),
+                rule_index: Some(rule_index),
+                span: Some(first.span),
+            });
+        }
+
+        if has_ordinal_byday && fields.contains_key("BYSETPOS") {
+            out.hints.push(Finding {
+                code: "H004".to_string(),
+                message: "Ordinal BYDAY combined with BYSETPOS".to_string(),
+                details: Some(
+                    "BYSETPOS selects among the ordinal BYDAY matches, which is usually redundant or conflicting. Use ordinal BYDAY (e.g. 1MO) or plain BYDAY with BYSETPOS, not both."
+                        .to_string(),
+                ),
+                rule_index: Some(rule_index),
+                span: span_of("BYSETPOS"),
+            });
+        }
     }
 
-    let dates = collect_dates(set, query, hard_limit)?;
+    for (kind, duplicates) in [
+        ("RDATE", &spec.duplicate_rdates),
+        ("EXDATE", &spec.duplicate_exdates),
+    ] {
+        if duplicates.is_empty() {
+            continue;
+        }
+        let values = duplicates
+            .iter()
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.hints.push(Finding {
+            code: "H002".to_string(),
+            message: format!("Duplicate {kind} values were removed"),
+            details: Some(format!(
+                "Kept the first occurrence of each instant; dropped: {values}."
+            )),
+            rule_index: None,
+            span: None,
+        });
+    }
 
-    let rdate_index: HashMap<i64, usize> = spec
-        .rdates
-        .iter()
-        .enumerate()
-        .map(|(i, dt)| (dt.timestamp(), i))
-        .collect();
+    if !spec.diagnostics.short_datetimes.is_empty() {
+        out.hints.push(Finding {
+            code: "H007".to_string(),
+            message: "DATE-TIME values without seconds were accepted".to_string(),
+            details: Some(format!(
+                "RFC 5545 requires YYYYMMDDTHHMMSS; read with seconds 0: {}.",
+                spec.diagnostics.short_datetimes.join(", ")
+            )),
+            rule_index: None,
+            span: None,
+        });
+    }
 
-    let mut out = Vec::with_capacity(dates.len());
-    for dt in dates {
-        let local = dt.with_timezone(&tz);
-        let ts = local.timestamp();
+    let mut reported = HashSet::new();
+    for PropertyTzid { property, tzid } in &spec.diagnostics.property_tzids {
+        if tzid.eq_ignore_ascii_case(&spec.tz) || !reported.insert((property, tzid)) {
+            continue;
+        }
+        out.warnings.push(Finding {
+            code: "W007".to_string(),
+            message: format!("{property} TZID differs from DTSTART"),
+            details: Some(format!(
+                "{property} uses TZID={tzid} but DTSTART is in {}; its values still count in {tzid}. This is often a copy-paste error.",
+                spec.tz
+            )),
+            rule_index: None,
+            span: None,
+        });
+    }
 
-        let (source, rule_index) = if let Some(index) = rdate_index.get(&ts) {
-            (OccurrenceSource::Rdate, *index)
-        } else {
-            let mut found = None;
-            for (idx, rule) in rrules.iter().enumerate() {
-                if matches_rule_at(spec.dtstart, rule, local) {
-                    found = Some(idx);
-                    break;
-                }
-            }
-            (OccurrenceSource::Rrule, found.unwrap_or(0))
-        };
+    if spec.uid_synthesized {
+        out.hints.push(Finding {
+            code: "H003".to_string(),
+            message: "VEVENT has no UID".to_string(),
+            details: Some(format!(
+                "Using synthesized UID {}; add a UID property for a stable identity.",
+                spec.uid.as_deref().unwrap_or_default()
+            )),
+            rule_index: None,
+            span: None,
+        });
+    }
 
-        out.push(Occurrence {
-            start_local: local.format("%Y-%m-%dT%H:%M:%S").to_string(),
-            start_utc: local
-                .with_timezone(&Utc)
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            tz: spec.tz.clone(),
-            source,
-            rule_index,
+    if spec.method.as_deref() == Some("CANCEL") {
+        out.hints.push(Finding {
+            code: "H005".to_string(),
+            message: "Event is a cancellation (METHOD:CANCEL)".to_string(),
+            details: Some(
+                "The calendar cancels these occurrences; consumers should remove them rather than schedule them."
+                    .to_string(),
+            ),
+            rule_index: None,
+            span: None,
         });
     }
 
+    out
+}
+
+/// Splits `dates` into the first occurrence of each instant and the later
+/// duplicates, preserving order.
+pub fn dedupe_dates(dates: Vec<DateTime<Tz>>) -> (Vec<DateTime<Tz>>, Vec<DateTime<Tz>>) {
+    let mut seen = HashSet::new();
+    let (mut unique, mut duplicates) = (Vec::new(), Vec::new());
+    for dt in dates {
+        if seen.insert(dt.timestamp()) {
+            unique.push(dt);
+        } else {
+            duplicates.push(dt);
+        }
+    }
+    (unique, duplicates)
+}
+
+pub fn is_potentially_unbounded(spec: &RecurrenceSpec) -> bool {
+    spec.rrules
+        .iter()
+        .any(|rule| !rule_has_count_or_until(rule))
+}
+
+/// Whether the spec describes a bounded series, i.e. every RRULE carries
+/// COUNT or UNTIL. RDATE-only specs are always finite.
+pub fn is_finite(spec: &RecurrenceSpec) -> bool {
+    !is_potentially_unbounded(spec)
+}
+
+pub fn expand(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+) -> Result<Vec<Occurrence>, CoreError> {
+    expand_within(spec, query, hard_limit, None)
+}
+
+fn expand_within(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    budget: Option<StdDuration>,
+) -> Result<Vec<Occurrence>, CoreError> {
+    let mut out = Vec::new();
+    for_each_occurrence(spec, query, hard_limit, budget, |occurrence| {
+        out.push(occurrence);
+        ControlFlow::Continue(())
+    })?;
+
     out.sort_by(|a, b| {
         a.start_utc
             .cmp(&b.start_utc)
@@ -428,27 +2684,296 @@ pub fn expand(
     Ok(out)
 }
 
-pub fn expand_result(
+/// Streams occurrences to `f` in chronological order instead of collecting
+/// them, stopping early when `f` returns [`ControlFlow::Break`].
+///
+/// Query handling and `hard_limit` match [`expand`]. Returns the number of
+/// occurrences passed to `f`.
+pub fn expand_for_each(
     spec: &RecurrenceSpec,
     query: &ExpandQuery,
     hard_limit: usize,
-) -> Result<ExpandResult, CoreError> {
-    let occurrences = expand(spec, query, hard_limit)?;
+    f: impl FnMut(Occurrence) -> ControlFlow<()>,
+) -> Result<usize, CoreError> {
+    for_each_occurrence(spec, query, hard_limit, None, f)
+}
 
-    let (window_start, window_end) = match query {
-        ExpandQuery::Between { start, end } => (
-            Some(start.format("%Y-%m-%dT%H:%M:%S").to_string()),
-            Some(end.format("%Y-%m-%dT%H:%M:%S").to_string()),
-        ),
-        ExpandQuery::After { start, .. } => {
-            (Some(start.format("%Y-%m-%dT%H:%M:%S").to_string()), None)
+/// Like [`expand_for_each`], but aborts with [`CoreError::TimeLimitExceeded`]
+/// once generation has run longer than `budget`.
+pub fn expand_for_each_within(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    budget: Option<StdDuration>,
+    f: impl FnMut(Occurrence) -> ControlFlow<()>,
+) -> Result<usize, CoreError> {
+    for_each_occurrence(spec, query, hard_limit, budget, f)
+}
+
+/// Lazy, chronological occurrences of a spec within a window, with the same
+/// `source`/`rule_index` attribution as [`expand`].
+///
+/// EXDATE/EXRULE exclusion happens inside the iterator, so excluded dates are
+/// never yielded and `.count()` or `.take(n)` stay correct. There is no
+/// `hard_limit`: an unbounded series ends only at year 9999, so cap it with
+/// `.take(n)` or the window. Invalid rules or zones are reported as a single
+/// `Err` item. Occurrences are not floated; see [`apply_floating`].
+pub struct OccurrenceIter<'a> {
+    spec: &'a RecurrenceSpec,
+    tz: Tz,
+    rrules: Vec<RRule>,
+    rdate_index: HashMap<i64, usize>,
+    dates: Box<dyn Iterator<Item = DateTime<Tz>> + 'a>,
+    error: Option<CoreError>,
+}
+
+impl<'a> OccurrenceIter<'a> {
+    pub fn new(spec: &'a RecurrenceSpec, window: &Window) -> Self {
+        Self::try_new(spec, window).unwrap_or_else(|error| Self {
+            spec,
+            tz: Tz::UTC,
+            rrules: Vec::new(),
+            rdate_index: HashMap::new(),
+            dates: Box::new(std::iter::empty()),
+            error: Some(error),
+        })
+    }
+
+    fn try_new(spec: &'a RecurrenceSpec, window: &Window) -> Result<Self, CoreError> {
+        Self::try_new_within(spec, window, Deadline::default())
+    }
+
+    fn try_new_within(
+        spec: &'a RecurrenceSpec,
+        window: &Window,
+        deadline: Deadline,
+    ) -> Result<Self, CoreError> {
+        let tz = parse_timezone(&spec.tz)?;
+        let (rrules, exrules) = parse_validated_rules(spec)?;
+        let dates = spec_window_dates_within(spec, &rrules, &exrules, window, deadline);
+        Ok(Self {
+            spec,
+            tz,
+            rdate_index: build_rdate_index(spec),
+            rrules,
+            dates,
+            error: None,
+        })
+    }
+}
+
+impl Iterator for OccurrenceIter<'_> {
+    type Item = Result<Occurrence, CoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let dt = self.dates.next()?;
+        Some(build_occurrence(
+            self.spec,
+            &self.tz,
+            &self.rrules,
+            &self.rdate_index,
+            dt,
+        ))
+    }
+}
+
+/// The first occurrence strictly after `after`, honoring EXDATE/EXRULE, or
+/// `None` when the series ends before it. The set is walked lazily, so no
+/// window or limit has to be picked.
+pub fn next_occurrence(
+    spec: &RecurrenceSpec,
+    after: DateTime<Tz>,
+) -> Result<Option<Occurrence>, CoreError> {
+    next_occurrence_within(spec, after, None)
+}
+
+/// Like [`next_occurrence`], but aborts with [`CoreError::TimeLimitExceeded`]
+/// once the walk has run longer than `budget`.
+pub fn next_occurrence_within(
+    spec: &RecurrenceSpec,
+    after: DateTime<Tz>,
+    budget: Option<StdDuration>,
+) -> Result<Option<Occurrence>, CoreError> {
+    occurrence_near(spec, budget, |rrules, exrules, deadline| {
+        next_date(spec, rrules, exrules, after, deadline)
+    })
+}
+
+/// The last occurrence strictly before `before`, or `None` when the series
+/// starts at or after it.
+///
+/// The `rrule` crate only walks forward, so this generates every date from
+/// DTSTART up to `before`; bound a far pivot with
+/// [`previous_occurrence_within`].
+pub fn previous_occurrence(
+    spec: &RecurrenceSpec,
+    before: DateTime<Tz>,
+) -> Result<Option<Occurrence>, CoreError> {
+    previous_occurrence_within(spec, before, None)
+}
+
+/// Like [`previous_occurrence`], but aborts with
+/// [`CoreError::TimeLimitExceeded`] once the walk has run longer than `budget`.
+pub fn previous_occurrence_within(
+    spec: &RecurrenceSpec,
+    before: DateTime<Tz>,
+    budget: Option<StdDuration>,
+) -> Result<Option<Occurrence>, CoreError> {
+    occurrence_near(spec, budget, |rrules, exrules, deadline| {
+        previous_date(spec, rrules, exrules, before, deadline)
+    })
+}
+
+fn next_date(
+    spec: &RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    after: DateTime<Tz>,
+    deadline: Deadline,
+) -> Option<DateTime<Tz>> {
+    let window = Window {
+        start: Some(after),
+        end: None,
+    };
+    spec_window_dates_within(spec, rrules, exrules, &window, deadline).find(|dt| *dt > after)
+}
+
+fn previous_date(
+    spec: &RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    before: DateTime<Tz>,
+    deadline: Deadline,
+) -> Option<DateTime<Tz>> {
+    let window = Window {
+        start: None,
+        end: Some(before),
+    };
+    spec_window_dates_within(spec, rrules, exrules, &window, deadline)
+        .filter(|dt| *dt < before)
+        .last()
+}
+
+fn occurrence_near(
+    spec: &RecurrenceSpec,
+    budget: Option<StdDuration>,
+    find: impl FnOnce(&[RRule], &[RRule], Deadline) -> Option<DateTime<Tz>>,
+) -> Result<Option<Occurrence>, CoreError> {
+    let tz = parse_timezone(&spec.tz)?;
+    let (rrules, exrules) = parse_validated_rules(spec)?;
+    let rdate_index = build_rdate_index(spec);
+    let deadline = Deadline::new(budget);
+    let picked = find(&rrules, &exrules, deadline.clone());
+    deadline.check()?;
+    picked
+        .map(|dt| {
+            let mut occurrence = build_occurrence(spec, &tz, &rrules, &rdate_index, dt)?;
+            if spec.floating {
+                float_occurrence(&mut occurrence);
+            }
+            Ok(occurrence)
+        })
+        .transpose()
+}
+
+fn for_each_occurrence(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    budget: Option<StdDuration>,
+    mut f: impl FnMut(Occurrence) -> ControlFlow<()>,
+) -> Result<usize, CoreError> {
+    if hard_limit == 0 {
+        return Err(CoreError::InvalidLimit(hard_limit));
+    }
+
+    // Setup errors surface here; a later `Err` item (an event end out of
+    // range) stops the walk and is returned instead of the count.
+    let deadline = Deadline::new(budget);
+    let occurrences =
+        OccurrenceIter::try_new_within(spec, &query.generated_window(), deadline.clone())?;
+    let mut failed = None;
+    let emitted = for_each_date(occurrences, query, hard_limit, |item| match item {
+        Ok(occurrence) => f(occurrence),
+        Err(error) => {
+            failed = Some(error);
+            ControlFlow::Break(())
+        }
+    })?;
+    deadline.check()?;
+    failed.map_or(Ok(emitted), Err)
+}
+
+/// Links to the neighbouring windows of a `Between` query. A `count` may
+/// stop the result short of the window's end, and the next window would
+/// then skip whatever lay in between, so counted queries get no links.
+fn window_links(query: &ExpandQuery) -> Option<WindowLinks> {
+    if !matches!(query, ExpandQuery::Between { count: None, .. }) {
+        return None;
+    }
+    // Step the closed window that is generated and reopen the end of a
+    // half-open one, so it links to windows that share its boundaries.
+    let closed = query.generated_window();
+    let reopen = |window: Window| {
+        if !query.is_end_exclusive() {
+            return window;
+        }
+        Window {
+            end: window.end.map(|end| end + HALF_OPEN_END),
+            ..window
         }
-        ExpandQuery::Unbounded => (None, None),
     };
+    Some(WindowLinks {
+        next: WindowLink::from_window(&reopen(closed.next()?))?,
+        prev: WindowLink::from_window(&reopen(closed.prev()?))?,
+    })
+}
+
+pub fn expand_result(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+) -> Result<ExpandResult, CoreError> {
+    expand_result_within(spec, query, hard_limit, None)
+}
+
+/// Like [`expand_result`], but aborts with [`CoreError::TimeLimitExceeded`]
+/// once generation has run longer than `budget`.
+pub fn expand_result_within(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    budget: Option<StdDuration>,
+) -> Result<ExpandResult, CoreError> {
+    let occurrences = expand_within(spec, query, hard_limit, budget)?;
+    let mut meta = expand_meta(spec, query, hard_limit)?;
+    meta.returned = occurrences.len();
+    meta.limit_reached = occurrences.len() == hard_limit;
+
+    Ok(ExpandResult {
+        meta,
+        occurrences,
+        week_spans: None,
+        heatmap: None,
+    })
+}
+
+/// The meta [`expand_result`] reports for `query`, built without expanding:
+/// `returned` is 0 and `limit_reached` false until the caller fills them
+/// in, e.g. after streaming with [`expand_for_each_within`].
+pub fn expand_meta(
+    spec: &RecurrenceSpec,
+    query: &ExpandQuery,
+    hard_limit: usize,
+) -> Result<ExpandMeta, CoreError> {
+    let warnings = unmatched_exdate_warnings(spec, &query.generated_window())?;
 
-    let meta = ExpandMeta {
+    Ok(ExpandMeta {
         dtstart: spec.dtstart.format("%Y-%m-%dT%H:%M:%S").to_string(),
-        tz: spec.tz.clone(),
+        tz: Some(spec.tz.clone()),
         rules: RulesMeta {
             rrule: spec.rrules.clone(),
             rdate: spec
@@ -463,423 +2988,6707 @@ pub fn expand_result(
                 .map(|d| d.format("%Y-%m-%dT%H:%M:%S").to_string())
                 .collect(),
         },
-        window: WindowMeta {
-            start: window_start,
-            end: window_end,
-        },
+        window: query.window_meta(),
         limit: hard_limit,
-    };
-
-    Ok(ExpandResult { meta, occurrences })
+        returned: 0,
+        limit_reached: false,
+        is_finite: is_finite(spec),
+        uid: spec.uid.clone(),
+        location: spec.location.clone(),
+        summary: spec.summary.clone(),
+        geo: spec.geo,
+        method: spec.method.clone(),
+        rule_extensions: spec.rule_extensions(),
+        business_calendar: None,
+        notes: Vec::new(),
+        warnings,
+        links: window_links(query),
+        sample: None,
+        source_filter: None,
+    })
 }
 
-pub fn explain(spec: &RecurrenceSpec, at: DateTime<Tz>) -> Result<ExplainResult, CoreError> {
-    let tz = parse_timezone(&spec.tz)?;
-    let at_local = at.with_timezone(&tz);
-    let at_ts = at_local.timestamp();
-
-    let (rrules, exrules) = parse_validated_rules(spec)?;
-
-    let rdate_index: HashMap<i64, usize> = spec
-        .rdates
+/// EXDATEs inside `window` that no RRULE/RDATE generates, so they exclude
+/// nothing; typically off by a DST offset or a few seconds. The closest
+/// generated instant within a day is named to make the mismatch obvious.
+fn unmatched_exdate_warnings(
+    spec: &RecurrenceSpec,
+    window: &Window,
+) -> Result<Vec<String>, CoreError> {
+    let exdates: Vec<&DateTime<Tz>> = spec
+        .exdates
         .iter()
-        .enumerate()
-        .map(|(i, dt)| (dt.timestamp(), i))
+        .filter(|exdate| window.contains(**exdate))
         .collect();
+    if exdates.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let exdate_hit = spec.exdates.iter().any(|d| d.timestamp() == at_ts);
-
-    let mut generated_by = None;
-    let mut generated_rule_index = None;
+    let (rrules, _) = parse_validated_rules(spec)?;
+    let tz = spec.dtstart.timezone();
+    let format = |dt: &DateTime<Tz>| {
+        dt.with_timezone(&tz)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    };
+    let mut warnings = Vec::new();
+    for exdate in exdates {
+        let generated = spec.rdates.contains(exdate)
+            || rrules
+                .iter()
+                .any(|rule| matches_rule_at(spec.dtstart, rule, *exdate));
+        if generated {
+            continue;
+        }
 
-    if let Some(idx) = rdate_index.get(&at_ts) {
-        generated_by = Some(OccurrenceSource::Rdate);
-        generated_rule_index = Some(*idx);
-    } else {
-        for (idx, rule) in rrules.iter().enumerate() {
-            if matches_rule_at(spec.dtstart, rule, at_local) {
-                generated_by = Some(OccurrenceSource::Rrule);
-                generated_rule_index = Some(idx);
-                break;
-            }
+        let (from, to) = (*exdate - Duration::days(1), *exdate + Duration::days(1));
+        let nearby = rrules
+            .iter()
+            .flat_map(|rule| {
+                RRuleSet::new(spec.dtstart)
+                    .rrule(rule.clone())
+                    .after(from)
+                    .before(to)
+                    .all(u16::MAX)
+                    .dates
+            })
+            .chain(
+                spec.rdates
+                    .iter()
+                    .copied()
+                    .filter(|rdate| (from..=to).contains(rdate)),
+            )
+            .min_by_key(|dt| (*dt - *exdate).num_seconds().abs());
+        let mut warning = format!("EXDATE {} matched no occurrence", format(exdate));
+        if let Some(nearest) = nearby {
+            warning.push_str(&format!(" (nearest: {})", format(&nearest)));
         }
+        warnings.push(warning);
     }
+    Ok(warnings)
+}
 
-    let exrule_hit = exrules
-        .iter()
-        .any(|rule| matches_exrule_at(spec.dtstart, rule, at_local));
-
-    let excluded_by = if exdate_hit {
-        Some("EXDATE".to_string())
-    } else if exrule_hit {
-        Some("EXRULE".to_string())
-    } else {
-        None
+/// Explains W005 with the first occurrences under `WKST=MO` and `WKST=SU`:
+/// INTERVAL skips whole weeks, so where a week begins decides which BYDAY
+/// dates share a week with DTSTART.
+fn wkst_comparison(spec: &RecurrenceSpec, rule: &str) -> String {
+    let first = |wkst: &str| -> Option<String> {
+        let rule = parse_validated_rule(
+            &format!("{rule};WKST={wkst}"),
+            spec.dtstart,
+            spec.dtstart_type,
+        )
+        .ok()?;
+        let dates = RRuleSet::new(spec.dtstart).rrule(rule).all(4).dates;
+        Some(
+            dates
+                .iter()
+                .map(|dt| dt.format("%a %Y-%m-%d").to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
     };
-
-    let included = generated_by.is_some() && excluded_by.is_none();
-
-    let mut notes = Vec::new();
-    if let Some(source) = &generated_by {
-        match source {
-            OccurrenceSource::Rrule => notes.push("Generated by RRULE".to_string()),
-            OccurrenceSource::Rdate => notes.push("Generated by RDATE".to_string()),
+    let mut details = "Without WKST weeks start on Monday; calendars using WKST=SU can expand this rule differently. Add WKST explicitly.".to_string();
+    if let (Some(monday), Some(sunday)) = (first("MO"), first("SU")) {
+        if monday == sunday {
+            details.push_str(&format!(
+                " First occurrences are the same for this DTSTART ({monday}) but can differ for others."
+            ));
+        } else {
+            details.push_str(&format!(
+                " First occurrences with WKST=MO: {monday}; with WKST=SU: {sunday}."
+            ));
         }
-    } else {
-        notes.push("Not generated by RRULE/RDATE".to_string());
     }
+    details
+}
 
-    if let Some(excluded) = &excluded_by {
-        notes.push(format!("Excluded by {excluded}"));
+/// Conservatively merges RRULEs that differ only in the values of a single
+/// BYxxx part by unioning that list.
+///
+/// A union over one BYxxx list is only equivalent to the separate rules when
+/// the set is not positional or counted, so rules with `COUNT` or `BYSETPOS`
+/// (or duplicate keys) are never merged.
+pub fn merge_rrules(rules: &[String]) -> MergedRules {
+    struct Group {
+        indices: Vec<usize>,
+        parts: Vec<(String, String)>,
     }
 
-    Ok(ExplainResult {
-        at: at_local.format("%Y-%m-%dT%H:%M:%S").to_string(),
-        included,
-        generated_by,
-        generated_rule_index,
-        excluded_by,
-        notes,
-    })
-}
+    let mut groups: Vec<Group> = Vec::new();
 
-pub fn canonical_json(value: &serde_json::Value) -> serde_json::Value {
-    match value {
-        serde_json::Value::Object(map) => {
-            let mut ordered: BTreeMap<String, serde_json::Value> = BTreeMap::new();
-            for (k, v) in map {
-                ordered.insert(k.clone(), canonical_json(v));
-            }
-            let mut out = serde_json::Map::new();
-            for (k, v) in ordered {
-                out.insert(k, v);
+    for (index, rule) in rules.iter().enumerate() {
+        let parts: Vec<(String, String)> = parse_rule_parts(rule)
+            .into_iter()
+            .map(|part| (part.key, part.value))
+            .collect();
+
+        let target = groups
+            .iter_mut()
+            .find_map(|group| mergeable_by_key(&group.parts, &parts).map(|key| (group, key)));
+
+        let Some((group, key)) = target else {
+            groups.push(Group {
+                indices: vec![index],
+                parts,
+            });
+            continue;
+        };
+
+        let incoming = parts
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or_default();
+        if let Some((_, value)) = group.parts.iter_mut().find(|(k, _)| *k == key) {
+            for item in incoming.split(',').map(str::trim) {
+                if !value
+                    .split(',')
+                    .any(|have| have.trim().eq_ignore_ascii_case(item))
+                {
+                    value.push(',');
+                    value.push_str(item);
+                }
             }
-            serde_json::Value::Object(out)
         }
-        serde_json::Value::Array(values) => {
-            serde_json::Value::Array(values.iter().map(canonical_json).collect())
+        group.indices.push(index);
+    }
+
+    let mut out = MergedRules::default();
+    for group in groups {
+        if let [single] = group.indices[..] {
+            out.rrules.push(rules[single].clone());
+            continue;
         }
-        _ => value.clone(),
+
+        let joined = group
+            .parts
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        let sources = group
+            .indices
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.notes
+            .push(format!("Merged RRULEs {sources} into '{joined}'"));
+        out.rrules.push(joined);
     }
+    out
 }
 
-fn collect_dates(
-    set: RRuleSet,
-    query: &ExpandQuery,
-    hard_limit: usize,
-) -> Result<Vec<DateTime<Tz>>, CoreError> {
-    let limit_plus_one = hard_limit
-        .checked_add(1)
-        .ok_or(CoreError::LimitExceeded { limit: hard_limit })?;
-    let limit_u16 = u16::try_from(limit_plus_one)
-        .map_err(|_| CoreError::LimitExceeded { limit: hard_limit })?;
-
-    match query {
-        ExpandQuery::Between { start, end } => {
-            let result = set.after(*start).before(*end).all(limit_u16);
-            if result.dates.len() > hard_limit {
-                return Err(CoreError::LimitExceeded { limit: hard_limit });
-            }
-            Ok(result.dates)
+/// Returns the single BYxxx key in which two rules differ, if merging them
+/// by unioning that key's values is provably equivalent.
+fn mergeable_by_key(a: &[(String, String)], b: &[(String, String)]) -> Option<String> {
+    let unsafe_key =
+        |parts: &[(String, String)]| parts.iter().any(|(k, _)| k == "COUNT" || k == "BYSETPOS");
+    if a.len() != b.len() || unsafe_key(a) || unsafe_key(b) {
+        return None;
+    }
+
+    let fields_a: HashMap<&str, &str> = a.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    let fields_b: HashMap<&str, &str> = b.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    if fields_a.len() != a.len() || fields_b.len() != b.len() {
+        return None;
+    }
+
+    let mut differing = None;
+    for (key, value_a) in &fields_a {
+        let value_b = fields_b.get(key)?;
+        if value_a.eq_ignore_ascii_case(value_b) {
+            continue;
         }
-        ExpandQuery::After { start, count } => {
-            if *count == 0 {
-                return Err(CoreError::InvalidCount(*count));
+        if !key.starts_with("BY") || differing.is_some() {
+            return None;
+        }
+        differing = Some(key.to_string());
+    }
+    differing
+}
+
+/// Re-renders RRULE-generated occurrences in a per-rule display timezone,
+/// keyed by rule index. Instants are unchanged; only `start_local`/`tz` move.
+/// An index with no RRULE behind it fails with [`CoreError::InvalidRuleIndex`].
+pub fn apply_rule_timezones(
+    result: &mut ExpandResult,
+    zones: &BTreeMap<usize, String>,
+) -> Result<(), CoreError> {
+    let rule_count = result.meta.rules.rrule.len();
+    let mut parsed = HashMap::new();
+    for (index, name) in zones {
+        if *index >= rule_count {
+            return Err(CoreError::InvalidRuleIndex {
+                index: *index,
+                rule_count,
+            });
+        }
+        parsed.insert(*index, (parse_timezone(name)?, name));
+    }
+
+    for occ in &mut result.occurrences {
+        if occ.source != OccurrenceSource::Rrule {
+            continue;
+        }
+        let Some((tz, name)) = parsed.get(&occ.rule_index) else {
+            continue;
+        };
+        let utc = occurrence_utc(occ)?;
+        occ.start_local = tz
+            .from_utc_datetime(&utc)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string();
+        occ.tz = Some((*name).clone());
+    }
+
+    Ok(())
+}
+
+/// Keeps only occurrences from `source` and records the filter in the meta.
+/// Runs on the expanded set, so EXDATE/EXRULE have already applied and the
+/// hidden occurrences still counted toward the limit.
+pub fn apply_source_filter(result: &mut ExpandResult, source: OccurrenceSource) {
+    result.occurrences.retain(|occ| occ.source == source);
+    result.meta.returned = result.occurrences.len();
+    result.meta.source_filter = Some(source);
+}
+
+/// Keeps at most `size` occurrences, chosen by `mode`, in chronological
+/// order, and records the sampling in the meta.
+pub fn apply_sample(result: &mut ExpandResult, size: usize, mode: SampleMode) {
+    let total = result.occurrences.len();
+    let keep: Vec<usize> = if size >= total {
+        (0..total).collect()
+    } else {
+        match mode {
+            SampleMode::Head => (0..size).collect(),
+            SampleMode::Tail => (total - size..total).collect(),
+            SampleMode::Even if size == 1 => vec![0],
+            SampleMode::Even => (0..size).map(|i| i * (total - 1) / (size - 1)).collect(),
+            SampleMode::Random { seed } => {
+                // Partial Fisher-Yates: the first `size` slots end up a
+                // uniform sample without replacement.
+                let mut rng = SplitMix64(seed);
+                let mut indices: Vec<usize> = (0..total).collect();
+                for i in 0..size {
+                    let j = i + (rng.next() % (total - i) as u64) as usize;
+                    indices.swap(i, j);
+                }
+                indices.truncate(size);
+                indices.sort_unstable();
+                indices
             }
-            if *count > hard_limit {
-                return Err(CoreError::LimitExceeded { limit: hard_limit });
+        }
+    };
+
+    let mut keep = keep.into_iter().peekable();
+    let mut index = 0;
+    result.occurrences.retain(|_| {
+        let kept = keep.next_if_eq(&index).is_some();
+        index += 1;
+        kept
+    });
+    result.meta.returned = result.occurrences.len();
+
+    let (name, seed) = match mode {
+        SampleMode::Even => ("even", None),
+        SampleMode::Head => ("head", None),
+        SampleMode::Tail => ("tail", None),
+        SampleMode::Random { seed } => ("random", Some(seed)),
+    };
+    result.meta.sample = Some(SampleMeta {
+        mode: name.to_string(),
+        total,
+        size: result.occurrences.len(),
+        seed,
+    });
+}
+
+/// Small, dependency-free PRNG (SplitMix64); stable across platforms, which
+/// keeps seeded samples reproducible.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Sets each occurrence's `abbrev` from its display zone (`tz`), so the
+/// switch between e.g. `CET` and `CEST` is visible across DST changes.
+pub fn apply_abbreviations(result: &mut ExpandResult) -> Result<(), CoreError> {
+    for occ in &mut result.occurrences {
+        let Some(name) = occ.tz.as_deref() else {
+            continue;
+        };
+        let zone = parse_chrono_tz(name)?;
+        let offset = zone.offset_from_utc_datetime(&occurrence_utc(occ)?);
+        occ.abbrev = Some(offset.abbreviation().to_string());
+    }
+    Ok(())
+}
+
+/// Sets each occurrence's `weekday`, `day_of_year`, `iso_week` and
+/// `iso_year` from its local date, so consumers can bucket occurrences
+/// without parsing timestamps. 2027-01-01, for instance, is in week 53 of
+/// ISO year 2026.
+pub fn apply_calendar_fields(result: &mut ExpandResult) {
+    for occ in &mut result.occurrences {
+        let Some(local) = occurrence_local(occ) else {
+            continue;
+        };
+        let week = local.iso_week();
+        occ.weekday = Some(weekday_code(local.weekday()).to_string());
+        occ.day_of_year = Some(local.ordinal());
+        occ.iso_week = Some(week.week());
+        occ.iso_year = Some(week.year());
+    }
+}
+
+/// Annotates each RRULE occurrence with the bounds of its recurrence period.
+///
+/// Periods follow the generating rule's FREQ and INTERVAL on the calendar
+/// grid, starting with the unit that contains DTSTART: weeks begin on `WKST`
+/// (default Monday), months on the 1st, hours on the hour. A weekly rule with
+/// `INTERVAL=2` therefore has two-week periods. RDATE occurrences get a
+/// period with both bounds unset.
+pub fn apply_periods(result: &mut ExpandResult, spec: &RecurrenceSpec) -> Result<(), CoreError> {
+    let tz = parse_timezone(&spec.tz)?;
+    let anchor = spec.dtstart.naive_local();
+    for occ in &mut result.occurrences {
+        let period = match occ.source {
+            OccurrenceSource::Rdate => OccurrencePeriod {
+                period_start: None,
+                period_end: None,
+            },
+            OccurrenceSource::Rrule => {
+                let rule = spec.rrules.get(occ.rule_index).ok_or_else(|| {
+                    CoreError::MissingField(format!("RRULE with index {}", occ.rule_index))
+                })?;
+                let local = tz.from_utc_datetime(&occurrence_utc(occ)?).naive_local();
+                let (start, end) = rule_period(rule, anchor, local)?;
+                OccurrencePeriod {
+                    period_start: Some(start.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                    period_end: Some(end.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                }
             }
-            let count_u16 = u16::try_from(*count)
-                .map_err(|_| CoreError::LimitExceeded { limit: hard_limit })?;
-            let result = set.after(*start).all(count_u16);
-            Ok(result.dates)
+        };
+        occ.period = Some(period);
+    }
+    Ok(())
+}
+
+/// The calendar-aligned `[start, end]` period of `rule` containing `local`,
+/// where period zero contains `anchor` (DTSTART).
+fn rule_period(
+    rule: &str,
+    anchor: NaiveDateTime,
+    local: NaiveDateTime,
+) -> Result<(NaiveDateTime, NaiveDateTime), CoreError> {
+    let fields = parse_rule_fields(rule);
+    let invalid = |reason: &str| CoreError::InvalidRrule {
+        rule: rule.to_string(),
+        reason: reason.to_string(),
+    };
+    let interval = match fields.get("INTERVAL") {
+        Some(value) => value
+            .parse::<i64>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .ok_or_else(|| invalid("INTERVAL must be a positive integer"))?,
+        None => 1,
+    };
+    let freq = fields.get("FREQ").ok_or_else(|| invalid("missing FREQ"))?;
+    let out_of_range = || invalid("period is out of range");
+
+    let months = |first: NaiveDate, per_unit: i64, elapsed: i64| {
+        let step = elapsed.div_euclid(interval) * interval * per_unit;
+        let start = first
+            .checked_add_months(Months::new(u32::try_from(step).ok()?))?
+            .and_time(NaiveTime::MIN);
+        let end =
+            start.checked_add_months(Months::new(u32::try_from(interval * per_unit).ok()?))?;
+        Some((start, end))
+    };
+    let fixed = |first: NaiveDateTime, unit: Duration| {
+        let elapsed = (local - first).num_seconds() / unit.num_seconds();
+        let start = first + unit * i32::try_from(elapsed.div_euclid(interval) * interval).ok()?;
+        Some((start, start + unit * i32::try_from(interval).ok()?))
+    };
+
+    let bounds = match freq.as_str() {
+        "YEARLY" => months(
+            NaiveDate::from_ymd_opt(anchor.year(), 1, 1).ok_or_else(out_of_range)?,
+            12,
+            i64::from(local.year() - anchor.year()),
+        ),
+        "MONTHLY" => months(
+            anchor.date().with_day(1).ok_or_else(out_of_range)?,
+            1,
+            i64::from(local.year() - anchor.year()) * 12 + i64::from(local.month())
+                - i64::from(anchor.month()),
+        ),
+        "WEEKLY" => {
+            let wkst = fields
+                .get("WKST")
+                .and_then(|value| parse_weekday_code(value))
+                .unwrap_or(Weekday::Mon);
+            let days_into_week =
+                (anchor.weekday().num_days_from_monday() + 7 - wkst.num_days_from_monday()) % 7;
+            let first = anchor.date() - Duration::days(i64::from(days_into_week));
+            fixed(first.and_time(NaiveTime::MIN), Duration::weeks(1))
+        }
+        "DAILY" => fixed(anchor.date().and_time(NaiveTime::MIN), Duration::days(1)),
+        "HOURLY" => fixed(
+            anchor
+                .date()
+                .and_hms_opt(anchor.hour(), 0, 0)
+                .ok_or_else(out_of_range)?,
+            Duration::hours(1),
+        ),
+        "MINUTELY" => fixed(
+            anchor
+                .date()
+                .and_hms_opt(anchor.hour(), anchor.minute(), 0)
+                .ok_or_else(out_of_range)?,
+            Duration::minutes(1),
+        ),
+        "SECONDLY" => fixed(anchor, Duration::seconds(1)),
+        _ => return Err(invalid("unknown FREQ")),
+    };
+    let (start, end) = bounds.ok_or_else(out_of_range)?;
+    Ok((start, end - Duration::seconds(1)))
+}
+
+/// Replaces each occurrence's `start_local`/`start_utc` pair with a single
+/// RFC 3339 `start` carrying the offset in effect at that instant.
+pub fn combine_datetimes(result: &mut ExpandResult) -> Result<(), CoreError> {
+    for occ in &mut result.occurrences {
+        let Some(name) = occ.tz.as_deref() else {
+            continue;
+        };
+        let tz = parse_timezone(name)?;
+        let local = tz.from_utc_datetime(&occurrence_utc(occ)?);
+        occ.start = Some(local.format("%Y-%m-%dT%H:%M:%S%:z").to_string());
+        occ.start_local.clear();
+        occ.start_utc.clear();
+    }
+    Ok(())
+}
+
+/// Drops the UTC stand-in of a floating spec: occurrences keep only their
+/// local times, with `tz` (also in the meta) and the UTC instants unset.
+/// A no-op for specs with a zone.
+pub fn apply_floating(result: &mut ExpandResult, spec: &RecurrenceSpec) {
+    if !spec.floating {
+        return;
+    }
+    result.meta.tz = None;
+    for occ in &mut result.occurrences {
+        float_occurrence(occ);
+    }
+}
+
+fn float_occurrence(occ: &mut Occurrence) {
+    occ.tz = None;
+    occ.start_utc.clear();
+    occ.end_utc = None;
+}
+
+/// Renders one occurrence as a single line of text, without a newline.
+///
+/// Works on occurrences in any post-processed shape: combined (`start`
+/// only, see [`combine_datetimes`]), floating, or annotated with `abbrev`.
+pub fn format_occurrence(occ: &Occurrence, style: OccurrenceStyle) -> String {
+    let source = match occ.source {
+        OccurrenceSource::Rrule => "RRULE",
+        OccurrenceSource::Rdate => "RDATE",
+    };
+    let abbrev = occ
+        .abbrev
+        .as_ref()
+        .map(|abbrev| format!(" {abbrev}"))
+        .unwrap_or_default();
+    match style {
+        OccurrenceStyle::Default => match &occ.start {
+            Some(start) => format!("{start}{abbrev} {source} idx={}", occ.rule_index),
+            None if occ.tz.is_none() => format!(
+                "{} (floating) {source} idx={}",
+                occ.start_local, occ.rule_index
+            ),
+            None => format!(
+                "{}{abbrev} ({}) {source} idx={}",
+                occ.start_local, occ.start_utc, occ.rule_index
+            ),
+        },
+        OccurrenceStyle::Table => {
+            let local = occ.start.as_deref().unwrap_or(&occ.start_local);
+            let utc = if occ.start_utc.is_empty() {
+                "-"
+            } else {
+                &occ.start_utc
+            };
+            format!("{local:<19}  {utc:<20}  {source:<5}  {}", occ.rule_index)
         }
-        ExpandQuery::Unbounded => {
-            let hard_limit_u16 = u16::try_from(hard_limit)
-                .map_err(|_| CoreError::LimitExceeded { limit: hard_limit })?;
-            let result = set.all(hard_limit_u16);
-            Ok(result.dates)
+        OccurrenceStyle::Human => {
+            let when = occurrence_local(occ)
+                .map(|local| local.format("%a %-d %b %Y, %H:%M").to_string())
+                .unwrap_or_else(|| occ.start_local.clone());
+            let zone = if occ.tz.is_none() {
+                " (floating)".to_string()
+            } else {
+                abbrev
+            };
+            format!("{when}{zone} ({source} {})", occ.rule_index)
         }
+        OccurrenceStyle::Rfc3339 => occurrence_rfc3339(occ),
     }
 }
 
-fn parse_validated_rules(spec: &RecurrenceSpec) -> Result<(Vec<RRule>, Vec<RRule>), CoreError> {
-    let mut rrules = Vec::with_capacity(spec.rrules.len());
-    for raw in &spec.rrules {
-        rrules.push(parse_validated_rule(raw, spec.dtstart)?);
+/// `start` if already combined; otherwise the offset is recovered as the
+/// gap between local and UTC time. Floating occurrences have no offset and
+/// keep their bare local time.
+fn occurrence_rfc3339(occ: &Occurrence) -> String {
+    match &occ.start {
+        Some(start) => start.clone(),
+        None => with_utc_offset(&occ.start_local, &occ.start_utc),
     }
+}
 
-    let mut exrules = Vec::with_capacity(spec.exrules.len());
-    for raw in &spec.exrules {
-        exrules.push(parse_validated_rule(raw, spec.dtstart)?);
+/// `local` with the offset that separates it from `utc` appended, or
+/// `local` unchanged when either does not parse.
+fn with_utc_offset(local: &str, utc: &str) -> String {
+    let offset = NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .zip(NaiveDateTime::parse_from_str(utc, "%Y-%m-%dT%H:%M:%SZ").ok())
+        .and_then(|(local, utc)| {
+            let seconds = i32::try_from((local - utc).num_seconds()).ok()?;
+            FixedOffset::east_opt(seconds)
+        });
+    match offset {
+        Some(offset) => format!("{local}{offset}"),
+        None => local.to_string(),
     }
+}
 
-    Ok((rrules, exrules))
+/// Summarizes the occurrences as runs of consecutive active ISO weeks,
+/// splitting a run whenever the set of local weekdays changes.
+///
+/// Only meaningful for weekly schedules: if any RRULE is not `FREQ=WEEKLY`,
+/// no spans are computed and a note explains why.
+pub fn apply_week_spans(result: &mut ExpandResult, spec: &RecurrenceSpec) {
+    let all_weekly = spec
+        .rrules
+        .iter()
+        .all(|rule| parse_rule_fields(rule).get("FREQ").map(String::as_str) == Some("WEEKLY"));
+    if !all_weekly {
+        result
+            .meta
+            .notes
+            .push("week spans are only computed for FREQ=WEEKLY rules".to_string());
+        return;
+    }
+
+    let mut weeks: BTreeMap<NaiveDate, Vec<Weekday>> = BTreeMap::new();
+    for local in result.occurrences.iter().filter_map(occurrence_local) {
+        let week = local.iso_week();
+        let Some(monday) = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)
+        else {
+            continue;
+        };
+        let days = weeks.entry(monday).or_default();
+        if !days.contains(&local.weekday()) {
+            days.push(local.weekday());
+        }
+    }
+
+    let iso_week = |monday: NaiveDate| {
+        let week = monday.iso_week();
+        format!("{}-W{:02}", week.year(), week.week())
+    };
+    let mut spans: Vec<(NaiveDate, NaiveDate, Vec<Weekday>)> = Vec::new();
+    for (monday, mut days) in weeks {
+        days.sort_by_key(|day| day.num_days_from_monday());
+        match spans.last_mut() {
+            Some((_, last, last_days))
+                if *last + Duration::weeks(1) == monday && *last_days == days =>
+            {
+                *last = monday;
+            }
+            _ => spans.push((monday, monday, days)),
+        }
+    }
+
+    result.week_spans = Some(
+        spans
+            .into_iter()
+            .map(|(from, to, days)| WeekSpan {
+                from_week: iso_week(from),
+                to_week: iso_week(to),
+                weekdays: days
+                    .iter()
+                    .map(|day| weekday_code(*day).to_string())
+                    .collect(),
+            })
+            .collect(),
+    );
 }
 
-fn parse_validated_rule(value: &str, dtstart: DateTime<Tz>) -> Result<RRule, CoreError> {
-    let unvalidated: RRule<Unvalidated> =
-        value
-            .parse::<RRule<Unvalidated>>()
-            .map_err(|err: rrule::RRuleError| CoreError::InvalidRrule {
-                rule: value.to_string(),
-                reason: err.to_string(),
-            })?;
+/// Counts the occurrences into a weekday × hour or month × day grid by their
+/// local start time, ready for charting.
+pub fn apply_heatmap(result: &mut ExpandResult, kind: HeatmapKind) {
+    let labels =
+        |range: std::ops::RangeInclusive<u32>| range.map(|n| format!("{n:02}")).collect::<Vec<_>>();
+    let (name, rows, columns) = match kind {
+        HeatmapKind::WeekdayHour => (
+            "weekday-hour",
+            [
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ]
+            .iter()
+            .map(|day| weekday_code(*day).to_string())
+            .collect(),
+            labels(0..=23),
+        ),
+        HeatmapKind::MonthDay => ("month-day", labels(1..=12), labels(1..=31)),
+    };
 
-    unvalidated
-        .validate(dtstart)
-        .map_err(|err: rrule::RRuleError| CoreError::InvalidRrule {
-            rule: value.to_string(),
-            reason: err.to_string(),
+    let mut counts = vec![vec![0; columns.len()]; rows.len()];
+    for local in result.occurrences.iter().filter_map(occurrence_local) {
+        let (row, column) = match kind {
+            HeatmapKind::WeekdayHour => (local.weekday().num_days_from_monday(), local.hour()),
+            HeatmapKind::MonthDay => (local.month0(), local.day0()),
+        };
+        counts[row as usize][column as usize] += 1;
+    }
+
+    result.heatmap = Some(Heatmap {
+        kind: name.to_string(),
+        rows,
+        columns,
+        counts,
+    });
+}
+
+/// An event in the shape FullCalendar's `events` option accepts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FullCalendarEvent {
+    pub title: String,
+    pub start: String,
+    /// Exclusive end; `None` when the spec has no duration.
+    pub end: Option<String>,
+    #[serde(rename = "allDay")]
+    pub all_day: bool,
+}
+
+/// Maps occurrences to FullCalendar events titled with the spec's SUMMARY
+/// (empty without one).
+///
+/// DATE specs become all-day events with `YYYY-MM-DD` bounds. Otherwise
+/// `start`/`end` are RFC 3339 with the occurrence's offset, or bare local
+/// times for floating specs, which FullCalendar reads as local time.
+pub fn fullcalendar_events(
+    spec: &RecurrenceSpec,
+    occurrences: &[Occurrence],
+) -> Vec<FullCalendarEvent> {
+    let all_day = spec.dtstart_type == DateValueType::Date;
+    let title = spec.summary.clone().unwrap_or_default();
+    occurrences
+        .iter()
+        .map(|occ| {
+            let (start, end) = if all_day {
+                let date = |local: &str| local.get(..10).unwrap_or(local).to_string();
+                let start = occurrence_local(occ)
+                    .map(|local| local.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| date(&occ.start_local));
+                (start, occ.end_local.as_deref().map(date))
+            } else {
+                let end = occ.end_local.as_deref().map(|local| match &occ.end_utc {
+                    Some(utc) => with_utc_offset(local, utc),
+                    None => local.to_string(),
+                });
+                (occurrence_rfc3339(occ), end)
+            };
+            FullCalendarEvent {
+                title: title.clone(),
+                start,
+                end,
+                all_day,
+            }
         })
+        .collect()
 }
 
-fn matches_rule_at(dtstart: DateTime<Tz>, rule: &RRule, at: DateTime<Tz>) -> bool {
-    let result = RRuleSet::new(dtstart)
-        .rrule(rule.clone())
-        .after(at)
-        .before(at)
-        .all(1);
-    !result.dates.is_empty()
+/// Flattens occurrences into one folded `RDATE` property line (CRLF line
+/// breaks), so systems without RRULE support can import explicit dates.
+///
+/// DATE specs emit `VALUE=DATE`; otherwise values carry the spec's `TZID`,
+/// or the `Z` suffix when the spec is in UTC. Returns an empty string when
+/// there are no occurrences.
+pub fn rdate_property(
+    spec: &RecurrenceSpec,
+    occurrences: &[Occurrence],
+) -> Result<String, CoreError> {
+    if occurrences.is_empty() {
+        return Ok(String::new());
+    }
+
+    let tz = parse_timezone(&spec.tz)?;
+    let values = occurrences
+        .iter()
+        .map(|occ| occurrence_start(&tz, occ).map(|start| ics_date_value(spec, &tz, start)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(fold_ics_line(&format!(
+        "{}:{}",
+        ics_date_property(spec, &tz, "RDATE"),
+        values.join(",")
+    )))
 }
 
-fn matches_exrule_at(dtstart: DateTime<Tz>, rule: &RRule, at: DateTime<Tz>) -> bool {
-    let result = RRuleSet::new(dtstart)
-        .exrule(rule.clone())
-        .after(at)
-        .before(at)
-        .all(1);
-    !result.dates.is_empty()
+/// Default `PRODID` for generated calendars.
+pub const DEFAULT_PRODID: &str = "-//rrulex//EN";
+
+/// Default per-event `UID` for [`ics_calendar`].
+pub const DEFAULT_UID_TEMPLATE: &str = "{hash}@rrulex";
+
+/// Wraps the occurrences in a `VCALENDAR` with one `VEVENT` each (folded,
+/// CRLF line breaks), for importing an expanded series elsewhere.
+///
+/// Each event's `UID` is rendered from `uid_template`: `{index}` (1-based),
+/// `{start_utc}` (`YYYYMMDDTHHMMSSZ`), `{uid}` (the spec's UID, synthesized
+/// when absent) and `{hash}` (spec fingerprint plus occurrence instant), so
+/// re-exports of the same series keep stable identifiers. The rendered UIDs
+/// must be unique. `prodid` must be non-empty and free of control characters.
+///
+/// `DTSTART` keeps the spec's value type, and a `DTEND` is added when the spec
+/// has a duration. Zoned DATE-TIME values carry a `TZID`, so a `VTIMEZONE`
+/// for it is included, listing the zone's offsets across the exported
+/// events. The required `DTSTAMP` is the series' DTSTART instant rather than
+/// the current time, so exporting the same input twice yields byte-identical
+/// output; floating specs have no such instant and are stamped with the
+/// Unix epoch.
+///
+/// This is the `expand --format ics` output. It takes the spec rather than
+/// an [`ExpandResult`], whose meta lacks the value type, duration and UID
+/// the events need.
+pub fn ics_calendar(
+    spec: &RecurrenceSpec,
+    occurrences: &[Occurrence],
+    prodid: &str,
+    uid_template: &str,
+) -> Result<String, CoreError> {
+    let mut out = ics_calendar_header(prodid)?;
+    let mut uids = UidRenderer::new(spec, uid_template)?;
+    let tz = parse_timezone(&spec.tz)?;
+    let starts = occurrences
+        .iter()
+        .map(|occ| occurrence_start(&tz, occ))
+        .collect::<Result<Vec<_>, _>>()?;
+    push_vtimezone(&mut out, spec, &tz, &starts)?;
+    for (index, start) in starts.into_iter().enumerate() {
+        let uid = uids.render(index, start)?;
+        push_ics_event(&mut out, spec, &tz, &uid, start, None)?;
+    }
+    out.push_str("END:VCALENDAR\r\n");
+    Ok(out)
 }
 
-fn parse_rule_fields(rule: &str) -> HashMap<String, String> {
-    rule.split(';')
-        .filter_map(|part| {
-            let (k, v) = part.split_once('=')?;
-            Some((k.trim().to_ascii_uppercase(), v.trim().to_string()))
+/// Like [`ics_calendar`], but folds the occurrences into a single `VEVENT`
+/// at the first one: with the `RRULE` from [`detect_rrule`] when there is
+/// one, otherwise with the remaining starts as an `RDATE` list. The `UID` is
+/// rendered for the first occurrence; no occurrences yield no `VEVENT`.
+pub fn ics_calendar_compressed(
+    spec: &RecurrenceSpec,
+    occurrences: &[Occurrence],
+    prodid: &str,
+    uid_template: &str,
+) -> Result<String, CoreError> {
+    let mut out = ics_calendar_header(prodid)?;
+    let mut uids = UidRenderer::new(spec, uid_template)?;
+    let tz = parse_timezone(&spec.tz)?;
+    let starts = occurrences
+        .iter()
+        .map(|occ| occurrence_start(&tz, occ))
+        .collect::<Result<Vec<_>, _>>()?;
+    push_vtimezone(&mut out, spec, &tz, &starts)?;
+    if let Some(first) = occurrences.first() {
+        let start = occurrence_start(&tz, first)?;
+        let uid = uids.render(0, start)?;
+        let recurrence = match detect_rrule(spec, occurrences)? {
+            Some(rule) => Some(fold_ics_line(&format!("RRULE:{rule}"))),
+            None => Some(rdate_property(spec, &occurrences[1..])?).filter(|line| !line.is_empty()),
+        };
+        push_ics_event(&mut out, spec, &tz, &uid, start, recurrence.as_deref())?;
+    }
+    out.push_str("END:VCALENDAR\r\n");
+    Ok(out)
+}
+
+/// A simple `FREQ[;INTERVAL];COUNT` rule that, anchored at the first
+/// occurrence, generates exactly `occurrences` and nothing else; `None` for
+/// fewer than two occurrences or when no such rule exists.
+///
+/// Candidates come from a constant step between starts: whole months on the
+/// same day and time (MONTHLY/YEARLY), whole local days at the same time of
+/// day (DAILY/WEEKLY), or an exact sub-daily span (HOURLY/MINUTELY/SECONDLY).
+/// A candidate is only returned if expanding it reproduces the input, so
+/// irregular gaps such as a removed EXDATE fall back to `None`.
+pub fn detect_rrule(
+    spec: &RecurrenceSpec,
+    occurrences: &[Occurrence],
+) -> Result<Option<String>, CoreError> {
+    if occurrences.len() < 2 {
+        return Ok(None);
+    }
+    let tz = parse_timezone(&spec.tz)?;
+    let starts = occurrences
+        .iter()
+        .map(|occ| occurrence_start(&tz, occ))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (freq, interval) in step_candidates(&starts) {
+        let rule = match interval {
+            1 => format!("FREQ={freq};COUNT={}", starts.len()),
+            n => format!("FREQ={freq};INTERVAL={n};COUNT={}", starts.len()),
+        };
+        let candidate = RecurrenceSpec {
+            dtstart: starts[0],
+            rrules: vec![rule.clone()],
+            rdates: Vec::new(),
+            exrules: Vec::new(),
+            exdates: Vec::new(),
+            overrides: Vec::new(),
+            ..spec.clone()
+        };
+        let Ok(expanded) = expand(&candidate, &ExpandQuery::Unbounded, starts.len() + 1) else {
+            continue;
+        };
+        let expanded = expanded
+            .iter()
+            .map(|occ| occurrence_start(&tz, occ))
+            .collect::<Result<Vec<_>, _>>()?;
+        if expanded == starts {
+            return Ok(Some(rule));
+        }
+    }
+    Ok(None)
+}
+
+/// FREQ/INTERVAL pairs matching a constant step between `starts`, coarsest
+/// first, so two starts a month apart read as MONTHLY rather than DAILY.
+fn step_candidates(starts: &[DateTime<Tz>]) -> Vec<(&'static str, i64)> {
+    let constant = |step: &dyn Fn(NaiveDateTime, NaiveDateTime) -> Option<i64>| {
+        let local = |dt: &DateTime<Tz>| dt.naive_local();
+        let first = step(local(&starts[0]), local(&starts[1])).filter(|n| *n > 0)?;
+        starts
+            .windows(2)
+            .all(|pair| step(local(&pair[0]), local(&pair[1])) == Some(first))
+            .then_some(first)
+    };
+    let mut candidates = Vec::new();
+
+    let months = constant(&|a, b| {
+        (a.day() == b.day() && a.time() == b.time()).then(|| {
+            i64::from(b.year() * 12 + b.month() as i32)
+                - i64::from(a.year() * 12 + a.month() as i32)
         })
-        .collect()
+    });
+    candidates.extend(months.map(|m| match m {
+        m if m % 12 == 0 => ("YEARLY", m / 12),
+        m => ("MONTHLY", m),
+    }));
+
+    let days = constant(&|a, b| (a.time() == b.time()).then(|| (b.date() - a.date()).num_days()));
+    candidates.extend(days.map(|d| match d {
+        d if d % 7 == 0 => ("WEEKLY", d / 7),
+        d => ("DAILY", d),
+    }));
+
+    let exact = starts
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_seconds())
+        .collect::<Vec<_>>();
+    if exact.iter().all(|s| *s == exact[0]) && (1..86_400).contains(&exact[0]) {
+        candidates.push(match exact[0] {
+            s if s % 3600 == 0 => ("HOURLY", s / 3600),
+            s if s % 60 == 0 => ("MINUTELY", s / 60),
+            s => ("SECONDLY", s),
+        });
+    }
+    candidates
 }
 
-fn rule_has_count_or_until(rule: &str) -> bool {
-    let fields = parse_rule_fields(rule);
-    fields.contains_key("COUNT") || fields.contains_key("UNTIL")
+/// `BEGIN:VCALENDAR` through `PRODID`, after validating `prodid`.
+fn ics_calendar_header(prodid: &str) -> Result<String, CoreError> {
+    if prodid.trim().is_empty() {
+        return Err(CoreError::InvalidProdid("must not be empty".to_string()));
+    }
+    if prodid.chars().any(char::is_control) {
+        return Err(CoreError::InvalidProdid(
+            "must not contain control characters".to_string(),
+        ));
+    }
+    let mut out = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n");
+    out.push_str(&fold_ics_line(&format!("PRODID:{prodid}")));
+    Ok(out)
 }
 
-fn unfold_ics_lines(input: &str) -> Vec<String> {
-    let normalized = input.replace("\r\n", "\n").replace('\r', "\n");
-    let mut lines: Vec<String> = Vec::new();
+/// Appends one `VEVENT` at `start`, with `DTEND` when the spec has a
+/// duration and an optional pre-folded recurrence line (`RRULE`/`RDATE`).
+///
+/// The `DTSTAMP` is the series' DTSTART instant; see [`ics_calendar`].
+fn push_ics_event(
+    out: &mut String,
+    spec: &RecurrenceSpec,
+    tz: &Tz,
+    uid: &str,
+    start: DateTime<Tz>,
+    recurrence: Option<&str>,
+) -> Result<(), CoreError> {
+    let dtstamp = if spec.floating {
+        "19700101T000000Z".to_string()
+    } else {
+        spec.dtstart
+            .with_timezone(&Utc)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    out.push_str("BEGIN:VEVENT\r\n");
+    out.push_str(&fold_ics_line(&format!("UID:{uid}")));
+    out.push_str(&format!("DTSTAMP:{dtstamp}\r\n"));
+    out.push_str(&fold_ics_line(&format!(
+        "{}:{}",
+        ics_date_property(spec, tz, "DTSTART"),
+        ics_date_value(spec, tz, start)
+    )));
+    if let Some(duration) = spec.duration {
+        out.push_str(&fold_ics_line(&format!(
+            "{}:{}",
+            ics_date_property(spec, tz, "DTEND"),
+            ics_date_value(spec, tz, duration.end_of(start)?)
+        )));
+    }
+    if let Some(recurrence) = recurrence {
+        out.push_str(recurrence);
+    }
+    out.push_str("END:VEVENT\r\n");
+    Ok(())
+}
 
-    for raw in normalized.lines() {
-        if raw.starts_with(' ') || raw.starts_with('\t') {
-            if let Some(last) = lines.last_mut() {
-                last.push_str(raw.trim_start());
-            }
+/// Appends the `VTIMEZONE` that the events' `TZID` refers to (RFC 5545
+/// §3.2.19), unless they are DATE, UTC or floating values. It starts with
+/// the offset in effect at the first start and adds one `STANDARD` or
+/// `DAYLIGHT` observance per transition until the last start or end.
+fn push_vtimezone(
+    out: &mut String,
+    spec: &RecurrenceSpec,
+    tz: &Tz,
+    starts: &[DateTime<Tz>],
+) -> Result<(), CoreError> {
+    if ics_date_property(spec, tz, "DTSTART") != format!("DTSTART;TZID={}", spec.tz) {
+        return Ok(());
+    }
+    let (Some(first), Some(last)) = (starts.iter().min(), starts.iter().max()) else {
+        return Ok(());
+    };
+    let last = match spec.duration {
+        Some(duration) => duration.end_of(*last)?,
+        None => *last,
+    };
+    let zone = parse_chrono_tz(&spec.tz)?;
+    let observance = |out: &mut String, at: i64, from: i32, to: i32| {
+        let Some(utc) = DateTime::from_timestamp(at, 0) else {
+            return;
+        };
+        let offset = zone.offset_from_utc_datetime(&utc.naive_utc());
+        let kind = if offset.dst_offset().is_zero() {
+            "STANDARD"
         } else {
-            lines.push(raw.to_string());
-        }
+            "DAYLIGHT"
+        };
+        let local = utc.naive_utc() + Duration::seconds(i64::from(from));
+        out.push_str(&format!("BEGIN:{kind}\r\n"));
+        out.push_str(&format!("DTSTART:{}\r\n", local.format("%Y%m%dT%H%M%S")));
+        out.push_str(&format!("TZOFFSETFROM:{}\r\n", ics_utc_offset(from)));
+        out.push_str(&format!("TZOFFSETTO:{}\r\n", ics_utc_offset(to)));
+        out.push_str(&fold_ics_line(&format!("TZNAME:{}", offset.abbreviation())));
+        out.push_str(&format!("END:{kind}\r\n"));
+    };
+
+    let (from, to) = (first.timestamp(), last.timestamp());
+    let initial = first.offset().fix().local_minus_utc();
+    out.push_str("BEGIN:VTIMEZONE\r\n");
+    out.push_str(&fold_ics_line(&format!("TZID:{}", spec.tz)));
+    observance(out, from, initial, initial);
+    for (at, before, after) in offset_transitions(tz, from, to, 86_400) {
+        observance(out, at, before, after);
+    }
+    out.push_str("END:VTIMEZONE\r\n");
+    Ok(())
+}
+
+/// A UTC offset in the `TZOFFSETFROM`/`TZOFFSETTO` form, e.g. `+0100`.
+fn ics_utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    let (hours, minutes, rest) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match rest {
+        0 => format!("{sign}{hours:02}{minutes:02}"),
+        _ => format!("{sign}{hours:02}{minutes:02}{rest:02}"),
+    }
+}
+
+/// Renders per-event UIDs from a parsed `--uid-template`, rejecting repeats.
+struct UidRenderer<'a> {
+    raw: &'a str,
+    template: Vec<UidSegment>,
+    uid: String,
+    fingerprint: u64,
+    seen: HashSet<String>,
+}
+
+impl<'a> UidRenderer<'a> {
+    fn new(spec: &RecurrenceSpec, raw: &'a str) -> Result<Self, CoreError> {
+        Ok(Self {
+            raw,
+            template: parse_uid_template(raw)?,
+            uid: spec.uid.clone().unwrap_or_else(|| synthesize_uid(spec)),
+            fingerprint: spec_fingerprint(spec),
+            seen: HashSet::new(),
+        })
+    }
+
+    fn render(&mut self, index: usize, start: DateTime<Tz>) -> Result<String, CoreError> {
+        let start_utc = start
+            .with_timezone(&Utc)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string();
+        let uid: String = self
+            .template
+            .iter()
+            .map(|segment| match segment {
+                UidSegment::Literal(text) => text.clone(),
+                UidSegment::Index => (index + 1).to_string(),
+                UidSegment::StartUtc => start_utc.clone(),
+                UidSegment::Uid => self.uid.clone(),
+                UidSegment::Hash => {
+                    let key = format!("{:016x}\n{start_utc}", self.fingerprint);
+                    format!("{:016x}", fnv1a(key.as_bytes()))
+                }
+            })
+            .collect();
+        if !self.seen.insert(uid.clone()) {
+            return Err(CoreError::InvalidUidTemplate(format!(
+                "'{}' renders duplicate UID '{uid}'",
+                self.raw
+            )));
+        }
+        Ok(uid)
+    }
+}
+
+enum UidSegment {
+    Literal(String),
+    Index,
+    StartUtc,
+    Uid,
+    Hash,
+}
+
+fn parse_uid_template(template: &str) -> Result<Vec<UidSegment>, CoreError> {
+    let invalid = |reason: String| CoreError::InvalidUidTemplate(reason);
+    if template.trim().is_empty() {
+        return Err(invalid("must not be empty".to_string()));
+    }
+    if template.chars().any(char::is_control) {
+        return Err(invalid("must not contain control characters".to_string()));
+    }
+
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find(['{', '}']) {
+        if rest[open..].starts_with('}') {
+            return Err(invalid(format!("unmatched '}}' in '{template}'")));
+        }
+        if open > 0 {
+            segments.push(UidSegment::Literal(rest[..open].to_string()));
+        }
+        let Some(close) = rest[open..].find('}') else {
+            return Err(invalid(format!("unmatched '{{' in '{template}'")));
+        };
+        segments.push(match &rest[open + 1..open + close] {
+            "index" => UidSegment::Index,
+            "start_utc" => UidSegment::StartUtc,
+            "uid" => UidSegment::Uid,
+            "hash" => UidSegment::Hash,
+            other => {
+                return Err(invalid(format!(
+                    "unknown placeholder '{{{other}}}' (expected {{index}}, {{start_utc}}, {{uid}} or {{hash}})"
+                )));
+            }
+        });
+        rest = &rest[open + close + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(UidSegment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// Property name plus value-type parameters for a date property of `spec`:
+/// `VALUE=DATE` for DATE specs, `TZID` unless the spec is in UTC.
+fn ics_date_property(spec: &RecurrenceSpec, tz: &Tz, name: &str) -> String {
+    match spec.dtstart_type {
+        DateValueType::Date => format!("{name};VALUE=DATE"),
+        DateValueType::DateTime if tz.name() == "UTC" || spec.floating => name.to_string(),
+        DateValueType::DateTime => format!("{name};TZID={}", spec.tz),
+    }
+}
+
+fn ics_date_value(spec: &RecurrenceSpec, tz: &Tz, at: DateTime<Tz>) -> String {
+    let local = at.with_timezone(tz);
+    match spec.dtstart_type {
+        DateValueType::Date => local.format("%Y%m%d").to_string(),
+        DateValueType::DateTime if spec.floating => local.format("%Y%m%dT%H%M%S").to_string(),
+        DateValueType::DateTime if tz.name() == "UTC" => local.format("%Y%m%dT%H%M%SZ").to_string(),
+        DateValueType::DateTime => local.format("%Y%m%dT%H%M%S").to_string(),
+    }
+}
+
+fn occurrence_start(tz: &Tz, occ: &Occurrence) -> Result<DateTime<Tz>, CoreError> {
+    Ok(tz.from_utc_datetime(&occurrence_utc(occ)?))
+}
+
+/// Local calendar days with more than `max` occurrences, in date order.
+pub fn days_over_limit(occurrences: &[Occurrence], max: usize) -> Vec<DayCount> {
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for local in occurrences.iter().filter_map(occurrence_local) {
+        *per_day.entry(local.date()).or_default() += 1;
+    }
+    per_day
+        .into_iter()
+        .filter(|(_, count)| *count > max)
+        .map(|(date, count)| DayCount {
+            date: date.format("%Y-%m-%d").to_string(),
+            count,
+        })
+        .collect()
+}
+
+/// Drops occurrences outside the calendar's working hours/days, judged on
+/// each occurrence's local time, and records the calendar in the meta.
+pub fn apply_business_calendar(result: &mut ExpandResult, calendar: &BusinessCalendar) {
+    result
+        .occurrences
+        .retain(|occ| occurrence_local(occ).is_some_and(|local| calendar.contains(local)));
+    result.meta.business_calendar = Some(calendar.meta());
+}
+
+/// The inclusive end of one natural period of the spec's rules from `start`.
+///
+/// Each RRULE contributes `INTERVAL` units of its `FREQ` (a year for
+/// `YEARLY`, a month for `MONTHLY`, and so on down to `SECONDLY`); the window
+/// ends one second before the longest of those periods elapses. Calendar
+/// units are added in local time, so a monthly period across a DST change
+/// still ends at the same wall-clock time.
+pub fn one_period_end(
+    spec: &RecurrenceSpec,
+    start: DateTime<Tz>,
+) -> Result<DateTime<Tz>, CoreError> {
+    let tz = start.timezone();
+    let start_local = start.naive_local();
+    let mut end: Option<DateTime<Tz>> = None;
+
+    for rule in &spec.rrules {
+        let fields = parse_rule_fields(rule);
+        let invalid = |reason: &str| CoreError::InvalidRrule {
+            rule: rule.clone(),
+            reason: reason.to_string(),
+        };
+        let interval = match fields.get("INTERVAL") {
+            Some(value) => value
+                .parse::<u32>()
+                .ok()
+                .filter(|interval| *interval > 0)
+                .ok_or_else(|| invalid("INTERVAL must be a positive integer"))?,
+            None => 1,
+        };
+        let freq = fields.get("FREQ").ok_or_else(|| invalid("missing FREQ"))?;
+        let calendar = |local: Option<NaiveDateTime>| -> Result<DateTime<Tz>, CoreError> {
+            let local = local.ok_or_else(|| invalid("period is out of range"))?;
+            resolve_local(tz, local, rule).map(|(dt, _)| dt)
+        };
+        let period_end = match freq.as_str() {
+            "YEARLY" => calendar(start_local.checked_add_months(Months::new(interval * 12)))?,
+            "MONTHLY" => calendar(start_local.checked_add_months(Months::new(interval)))?,
+            "WEEKLY" => calendar(start_local.checked_add_days(Days::new(u64::from(interval) * 7)))?,
+            "DAILY" => calendar(start_local.checked_add_days(Days::new(u64::from(interval))))?,
+            "HOURLY" => start + Duration::hours(i64::from(interval)),
+            "MINUTELY" => start + Duration::minutes(i64::from(interval)),
+            "SECONDLY" => start + Duration::seconds(i64::from(interval)),
+            _ => return Err(invalid("unknown FREQ")),
+        };
+        end = end.max(Some(period_end));
+    }
+
+    let end =
+        end.ok_or_else(|| CoreError::MissingField("RRULE to derive a period from".to_string()))?;
+    Ok(end - Duration::seconds(1))
+}
+
+/// Expands the next `count` occurrences from `start` and annotates each with
+/// the gap since its predecessor, making irregular spacing (DST shifts,
+/// BYxxx clustering) easy to spot.
+pub fn simulate(
+    spec: &RecurrenceSpec,
+    start: DateTime<Tz>,
+    count: usize,
+    hard_limit: usize,
+) -> Result<SimulateResult, CoreError> {
+    let mut result = expand_result(spec, &ExpandQuery::After { start, count }, hard_limit)?;
+
+    let mut previous: Option<NaiveDateTime> = None;
+    let mut occurrences = Vec::with_capacity(result.occurrences.len());
+    for mut occurrence in std::mem::take(&mut result.occurrences) {
+        let utc = occurrence_utc(&occurrence)?;
+        let gap_from_previous = previous.map(|prev| format_iso_duration(utc - prev));
+        previous = Some(utc);
+        if spec.floating {
+            float_occurrence(&mut occurrence);
+        }
+        occurrences.push(SimulatedOccurrence {
+            occurrence,
+            gap_from_previous,
+        });
+    }
+
+    apply_floating(&mut result, spec);
+    Ok(SimulateResult {
+        meta: result.meta,
+        occurrences,
+    })
+}
+
+/// Formats an exact duration as `PT#H#M#S`, omitting zero components.
+fn format_iso_duration(duration: Duration) -> String {
+    let total = duration.num_seconds();
+    if total == 0 {
+        return "PT0S".to_string();
+    }
+    let (sign, total) = if total < 0 {
+        ("-", -total)
+    } else {
+        ("", total)
+    };
+    let mut out = format!("{sign}PT");
+    for (value, unit) in [
+        (total / 3600, 'H'),
+        (total / 60 % 60, 'M'),
+        (total % 60, 'S'),
+    ] {
+        if value > 0 {
+            out.push_str(&format!("{value}{unit}"));
+        }
+    }
+    out
+}
+
+/// Counts occurrences within the window without materializing them.
+///
+/// With `at_least`, iteration stops as soon as that many occurrences were
+/// seen, which keeps "fires at least N times" checks cheap for dense rules.
+/// Counting past `hard_limit` fails with [`CoreError::LimitExceeded`] as soon
+/// as the first surplus occurrence is seen.
+pub fn count_occurrences(
+    spec: &RecurrenceSpec,
+    window: &Window,
+    options: CountOptions,
+    hard_limit: usize,
+) -> Result<CountResult, CoreError> {
+    let CountOptions {
+        at_least,
+        by_source,
+    } = options;
+    if at_least == Some(0) {
+        return Err(CoreError::InvalidCount(0));
+    }
+    if hard_limit == 0 {
+        return Err(CoreError::InvalidLimit(hard_limit));
+    }
+
+    let (rrules, exrules) = parse_validated_rules(spec)?;
+    let rdate_index = build_rdate_index(spec);
+
+    let mut count = 0;
+    let mut sources = SourceCounts::default();
+    for dt in spec_window_dates(spec, &rrules, &exrules, window) {
+        if count == hard_limit {
+            return Err(CoreError::LimitExceeded { limit: hard_limit });
+        }
+        count += 1;
+        if rdate_index.contains_key(&generated_slot(spec, &dt).timestamp()) {
+            sources.rdate += 1;
+        } else {
+            sources.rrule += 1;
+        }
+        if at_least == Some(count) {
+            break;
+        }
+    }
+
+    Ok(CountResult {
+        count,
+        at_least: at_least.map(|threshold| count >= threshold),
+        by_source: by_source.then_some(sources),
+    })
+}
+
+/// Why nothing matches before the series starts. RDATEs are the one source
+/// that may still lie earlier.
+fn before_dtstart_note(spec: &RecurrenceSpec) -> String {
+    let dtstart = spec.dtstart.format("%Y-%m-%dT%H:%M:%S");
+    if spec.rdates.iter().any(|rdate| *rdate < spec.dtstart) {
+        format!(
+            "queried time is before DTSTART {dtstart}; only RDATEs can occur before the series start"
+        )
+    } else {
+        format!(
+            "queried time is before DTSTART {dtstart}; no occurrences exist before the series start"
+        )
+    }
+}
+
+/// Explains a whole local calendar day: included when any occurrence starts
+/// on that day, listing every matching instant.
+fn explain_day(
+    spec: &RecurrenceSpec,
+    tz: &Tz,
+    date: NaiveDate,
+    at: &str,
+) -> Result<ExplainResult, CoreError> {
+    let midnight = |day: NaiveDate| {
+        day.and_hms_opt(0, 0, 0)
+            .ok_or_else(|| CoreError::InvalidDateTime {
+                input: at.to_string(),
+                reason: "could not build midnight datetime".to_string(),
+            })
+    };
+    let next_day = date.succ_opt().ok_or_else(|| CoreError::InvalidDateTime {
+        input: at.to_string(),
+        reason: "date out of range".to_string(),
+    })?;
+    let (start, _) = resolve_local(*tz, midnight(date)?, at)?;
+    let (end, _) = resolve_local(*tz, midnight(next_day)?, at)?;
+
+    let (rrules, exrules) = parse_validated_rules(spec)?;
+    let rdate_index = build_rdate_index(spec);
+    let day = Window::from_between(start, end - Duration::seconds(1))?;
+    let matches: Vec<Occurrence> = spec_window_dates(spec, &rrules, &exrules, &day)
+        .map(|dt| build_occurrence(spec, tz, &rrules, &rdate_index, dt))
+        .collect::<Result<_, _>>()?;
+
+    let first = matches.first();
+    let mut notes = vec![match matches.len() {
+        0 => format!("No occurrence on {at}"),
+        1 => format!("1 occurrence on {at}"),
+        n => format!("{n} occurrences on {at}"),
+    }];
+    if matches.is_empty() && end <= spec.dtstart {
+        notes.push(before_dtstart_note(spec));
+    }
+
+    Ok(ExplainResult {
+        at: at.to_string(),
+        included: first.is_some(),
+        generated_by: first.map(|occ| occ.source.clone()),
+        generated_rule_index: first.map(|occ| occ.rule_index),
+        excluded_by: None,
+        day_matches: Some(matches.into_iter().map(|occ| occ.start_local).collect()),
+        exclusions: None,
+        why_not: None,
+        nearest_before: None,
+        nearest_after: None,
+        notes,
+    })
+}
+
+/// Explains a raw `--at` value, interpreting naive input in the spec timezone.
+///
+/// Unlike [`parse_iso_datetime`], wall-clock times inside a DST transition
+/// are resolved per RFC 5545 §3.3.5 instead of rejected: an overlap uses the
+/// earlier instant, a gap uses the UTC offset from before the gap. A note
+/// records which interpretation was used.
+pub fn explain_local(spec: &RecurrenceSpec, at: &str) -> Result<ExplainResult, CoreError> {
+    explain_local_with(spec, at, ExplainOptions::default())
+}
+
+/// [`explain_local`] with options; date-only input ignores
+/// `all_exclusions` and `why_not`, since whole-day results carry no
+/// exclusion reason and no single time to check.
+pub fn explain_local_with(
+    spec: &RecurrenceSpec,
+    at: &str,
+    options: ExplainOptions,
+) -> Result<ExplainResult, CoreError> {
+    let tz = parse_timezone(&spec.tz)?;
+    if let Ok(date) = NaiveDate::parse_from_str(at, "%Y-%m-%d") {
+        return explain_day(spec, &tz, date, at);
+    }
+
+    let (at_dt, resolution) = parse_iso_datetime_resolved(at, &tz)?;
+    let mut result = explain_with(spec, at_dt, options)?;
+
+    let utc = at_dt.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ");
+    let offset = at_dt.format("%:z");
+    match resolution {
+        LocalResolution::Exact => {}
+        LocalResolution::Overlap => result.notes.push(format!(
+            "{at} occurs twice in {} (DST fall-back); interpreted as the earlier instant {utc} ({offset})",
+            spec.tz
+        )),
+        LocalResolution::Gap => result.notes.push(format!(
+            "{at} does not exist in {} (DST spring-forward); interpreted with the pre-transition offset as {utc} ({offset})",
+            spec.tz
+        )),
+    }
+
+    Ok(result)
+}
+
+pub fn explain(spec: &RecurrenceSpec, at: DateTime<Tz>) -> Result<ExplainResult, CoreError> {
+    explain_with(spec, at, ExplainOptions::default())
+}
+
+pub fn explain_with(
+    spec: &RecurrenceSpec,
+    at: DateTime<Tz>,
+    options: ExplainOptions,
+) -> Result<ExplainResult, CoreError> {
+    let tz = parse_timezone(&spec.tz)?;
+    let at_local = at.with_timezone(&tz);
+    let at_ts = at_local.timestamp();
+
+    let (rrules, exrules) = parse_validated_rules(spec)?;
+
+    let rdate_index = build_rdate_index(spec);
+
+    let exdate_hit = spec.exdates.iter().any(|d| d.timestamp() == at_ts);
+
+    let mut generated_by = None;
+    let mut generated_rule_index = None;
+
+    if let Some(idx) = rdate_index.get(&at_ts) {
+        generated_by = Some(OccurrenceSource::Rdate);
+        generated_rule_index = Some(*idx);
+    } else {
+        for (idx, rule) in rrules.iter().enumerate() {
+            if matches_rule_at(spec.dtstart, rule, at_local) {
+                generated_by = Some(OccurrenceSource::Rrule);
+                generated_rule_index = Some(idx);
+                break;
+            }
+        }
+    }
+
+    let exrule_hit = exrules
+        .iter()
+        .any(|rule| matches_exrule_at(spec.dtstart, rule, at_local));
+
+    let exclusions = options.all_exclusions.then(|| {
+        let exdates = spec
+            .exdates
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.timestamp() == at_ts)
+            .map(|(index, _)| Exclusion {
+                kind: "EXDATE".to_string(),
+                index,
+            });
+        let exrules = exrules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| matches_exrule_at(spec.dtstart, rule, at_local))
+            .map(|(index, _)| Exclusion {
+                kind: "EXRULE".to_string(),
+                index,
+            });
+        exdates.chain(exrules).collect::<Vec<_>>()
+    });
+
+    let excluded_by = if exdate_hit {
+        Some("EXDATE".to_string())
+    } else if exrule_hit {
+        Some("EXRULE".to_string())
+    } else {
+        None
+    };
+
+    let moved_here = spec.overrides.iter().find(|o| o.start.timestamp() == at_ts);
+    let moved_away = spec
+        .overrides
+        .iter()
+        .find(|o| o.recurrence_id.timestamp() == at_ts && o.start.timestamp() != at_ts);
+    let included = (generated_by.is_some() && excluded_by.is_none() && moved_away.is_none())
+        || moved_here.is_some();
+
+    let mut notes = Vec::new();
+    if let Some(source) = &generated_by {
+        match source {
+            OccurrenceSource::Rrule => notes.push("Generated by RRULE".to_string()),
+            OccurrenceSource::Rdate => notes.push("Generated by RDATE".to_string()),
+        }
+    } else {
+        notes.push("Not generated by RRULE/RDATE".to_string());
+        if at < spec.dtstart {
+            notes.push(before_dtstart_note(spec));
+        }
+    }
+
+    if let Some(excluded) = &excluded_by {
+        notes.push(format!("Excluded by {excluded}"));
+    }
+    let format = |dt: DateTime<Tz>| dt.format("%Y-%m-%dT%H:%M:%S").to_string();
+    if let Some(o) = moved_away {
+        notes.push(format!(
+            "Moved to {} by RECURRENCE-ID (SEQUENCE {})",
+            format(o.start.with_timezone(&tz)),
+            o.sequence
+        ));
+    }
+    if let Some(o) = moved_here {
+        notes.push(format!(
+            "Moved here from {} by RECURRENCE-ID (SEQUENCE {})",
+            format(o.recurrence_id.with_timezone(&tz)),
+            o.sequence
+        ));
+    }
+
+    let why_not = if options.why_not && generated_by.is_none() {
+        closest_rule_checks(spec.dtstart, &rrules, at_local)
+    } else {
+        None
+    };
+    if let Some(why_not) = &why_not {
+        notes.push(if why_not.failed.is_empty() {
+            format!(
+                "RRULE {} passes every checked condition; COUNT, BYSETPOS, BYYEARDAY or BYWEEKNO must exclude it",
+                why_not.rule_index
+            )
+        } else {
+            format!(
+                "RRULE {} fails: {}",
+                why_not.rule_index,
+                why_not.failed.join(", ")
+            )
+        });
+    }
+
+    let (nearest_before, nearest_after) = if included {
+        (None, None)
+    } else {
+        // The same searches as `previous_occurrence`/`next_occurrence`, which
+        // walk from DTSTART; `budget` keeps a far `at` from running long.
+        let deadline = Deadline::new(options.budget);
+        let nearest = (
+            previous_date(spec, &rrules, &exrules, at_local, deadline.clone()),
+            next_date(spec, &rrules, &exrules, at_local, deadline.clone()),
+        );
+        deadline.check()?;
+        nearest
+    };
+    if generated_by.is_none()
+        && moved_here.is_none()
+        && let Some(within) = options.suggest_within
+    {
+        let closest = [nearest_before, nearest_after]
+            .into_iter()
+            .flatten()
+            .min_by_key(|dt| (*dt - at_local).abs());
+        if let Some(closest) = closest.filter(|dt| (*dt - at_local).abs() <= within) {
+            notes.push(format!("Did you mean {}?", format(closest)));
+        }
+    }
+
+    Ok(ExplainResult {
+        at: format(at_local),
+        included,
+        generated_by,
+        generated_rule_index,
+        excluded_by,
+        day_matches: None,
+        exclusions,
+        why_not,
+        nearest_before: nearest_before.map(format),
+        nearest_after: nearest_after.map(format),
+        notes,
+    })
+}
+
+/// Runs [`rule_checks`] for every RRULE and keeps the one failing the fewest
+/// checks (the first on ties).
+fn closest_rule_checks(
+    dtstart: DateTime<Tz>,
+    rrules: &[RRule],
+    at: DateTime<Tz>,
+) -> Option<WhyNot> {
+    rrules
+        .iter()
+        .enumerate()
+        .map(|(rule_index, rule)| {
+            let checks = rule_checks(dtstart, rule, at);
+            let failed = checks
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.check.clone())
+                .collect();
+            WhyNot {
+                rule_index,
+                failed,
+                checks,
+            }
+        })
+        .min_by_key(|why_not| why_not.failed.len())
+}
+
+/// Evaluates a validated rule's conditions one by one against `at`.
+///
+/// The validated rule already carries the values RFC 5545 implies from
+/// DTSTART (e.g. a weekly rule without BYDAY runs on DTSTART's weekday), so
+/// those are checked like explicit BYxxx parts.
+fn rule_checks(dtstart: DateTime<Tz>, rule: &RRule, at: DateTime<Tz>) -> Vec<RuleCheck> {
+    let local = at.naive_local();
+    let mut checks = Vec::new();
+    let mut check = |name: &str, passed: bool, detail: String| {
+        checks.push(RuleCheck {
+            check: name.to_string(),
+            passed,
+            detail,
+        });
+    };
+
+    check(
+        "after_dtstart",
+        at >= dtstart,
+        format!("DTSTART is {}", dtstart.format("%Y-%m-%dT%H:%M:%S")),
+    );
+
+    if let Some(until) = rule.get_until() {
+        check(
+            "within_until",
+            at <= *until,
+            format!(
+                "UNTIL is {}",
+                until.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ")
+            ),
+        );
+    }
+
+    let freq = rule.get_freq();
+    let interval = i64::from(rule.get_interval());
+    let elapsed = elapsed_freq_units(freq, rule.get_week_start(), dtstart.naive_local(), local);
+    check(
+        "freq_cadence",
+        elapsed.rem_euclid(interval) == 0,
+        format!("{elapsed} {freq} periods after DTSTART, INTERVAL={interval}"),
+    );
+
+    let months = rule.get_by_month();
+    if !months.is_empty() {
+        check(
+            "bymonth",
+            months
+                .iter()
+                .any(|month| u32::from(*month) == local.month()),
+            format!("month {}, BYMONTH={}", local.month(), join_values(months)),
+        );
+    }
+
+    let month_days = rule.get_by_month_day();
+    if !month_days.is_empty() {
+        let last_day = last_day_of_month(local.date());
+        let matches = month_days.iter().any(|day| {
+            let day = i32::from(*day);
+            let resolved = if day < 0 {
+                last_day as i32 + 1 + day
+            } else {
+                day
+            };
+            resolved == local.day() as i32
+        });
+        check(
+            "bymonthday",
+            matches,
+            format!(
+                "day {}, BYMONTHDAY={}",
+                local.day(),
+                join_values(month_days)
+            ),
+        );
+    }
+
+    let weekdays = rule.get_by_weekday();
+    if !weekdays.is_empty() {
+        let matches = weekdays.iter().any(|weekday| match weekday {
+            NWeekday::Every(day) => *day == local.weekday(),
+            NWeekday::Nth(n, day) => {
+                *day == local.weekday()
+                    && nth_weekday_matches(
+                        local.date(),
+                        i32::from(*n),
+                        freq == Frequency::Yearly && months.is_empty(),
+                    )
+            }
+        });
+        let listed: Vec<_> = weekdays.iter().map(ToString::to_string).collect();
+        check(
+            "byday",
+            matches,
+            format!(
+                "{}, BYDAY={}",
+                weekday_code(local.weekday()),
+                listed.join(",")
+            ),
+        );
+    }
+
+    for (name, unit, values, actual) in [
+        ("byhour", "hour", rule.get_by_hour(), local.hour()),
+        ("byminute", "minute", rule.get_by_minute(), local.minute()),
+        ("bysecond", "second", rule.get_by_second(), local.second()),
+    ] {
+        if !values.is_empty() {
+            check(
+                name,
+                values.iter().any(|value| u32::from(*value) == actual),
+                format!(
+                    "{unit} {actual}, {}={}",
+                    name.to_ascii_uppercase(),
+                    join_values(values)
+                ),
+            );
+        }
+    }
+
+    checks
+}
+
+/// Whole FREQ units between the unit containing `anchor` and the one
+/// containing `local`, on the local calendar grid.
+fn elapsed_freq_units(
+    freq: Frequency,
+    wkst: Weekday,
+    anchor: NaiveDateTime,
+    local: NaiveDateTime,
+) -> i64 {
+    let days = (local.date() - anchor.date()).num_days();
+    let hours = days * 24 + i64::from(local.hour()) - i64::from(anchor.hour());
+    let minutes = hours * 60 + i64::from(local.minute()) - i64::from(anchor.minute());
+    match freq {
+        Frequency::Yearly => i64::from(local.year() - anchor.year()),
+        Frequency::Monthly => {
+            i64::from(local.year() - anchor.year()) * 12 + i64::from(local.month())
+                - i64::from(anchor.month())
+        }
+        Frequency::Weekly => {
+            let week_start = |date: NaiveDate| {
+                let offset =
+                    (date.weekday().num_days_from_monday() + 7 - wkst.num_days_from_monday()) % 7;
+                date - Duration::days(i64::from(offset))
+            };
+            (week_start(local.date()) - week_start(anchor.date())).num_days() / 7
+        }
+        Frequency::Daily => days,
+        Frequency::Hourly => hours,
+        Frequency::Minutely => minutes,
+        Frequency::Secondly => (local - anchor).num_seconds(),
+    }
+}
+
+/// Whether `date` is the `n`th (negative: from the end) of its weekday in its
+/// month, or in its year when `in_year` is set.
+fn nth_weekday_matches(date: NaiveDate, n: i32, in_year: bool) -> bool {
+    let (position, remaining) = if in_year {
+        let days_in_year = if date.leap_year() { 366 } else { 365 };
+        (date.ordinal0(), days_in_year - date.ordinal())
+    } else {
+        (date.day0(), last_day_of_month(date) - date.day())
+    };
+    if n > 0 {
+        (position / 7 + 1) as i32 == n
+    } else {
+        (remaining / 7 + 1) as i32 == -n
+    }
+}
+
+fn join_values<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    date.with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// Field order of [`canonicalize_rule`]: FREQ and INTERVAL, the BYxxx parts
+/// in RFC 5545 evaluation order, then WKST and the COUNT/UNTIL bound.
+const CANONICAL_RULE_ORDER: [&str; 14] = [
+    "FREQ",
+    "INTERVAL",
+    "BYMONTH",
+    "BYWEEKNO",
+    "BYYEARDAY",
+    "BYMONTHDAY",
+    "BYDAY",
+    "BYHOUR",
+    "BYMINUTE",
+    "BYSECOND",
+    "BYSETPOS",
+    "WKST",
+    "COUNT",
+    "UNTIL",
+];
+
+/// Rewrites `rule` with uppercased keys (and values of known parts) in
+/// [`CANONICAL_RULE_ORDER`], so equivalent rules from different sources
+/// compare equal as strings. Unknown parts keep their order after the known
+/// ones. A repeated key or a part without `=` is an error.
+pub fn canonicalize_rule(rule: &str) -> Result<String, CoreError> {
+    let invalid = |reason: String| CoreError::InvalidRrule {
+        rule: rule.to_string(),
+        reason,
+    };
+    if let Some(segment) = rule
+        .split(';')
+        .map(str::trim)
+        .find(|segment| !segment.is_empty() && !segment.contains('='))
+    {
+        return Err(invalid(format!("part '{segment}' has no '='")));
+    }
+
+    let parts = parse_rule_parts(rule);
+    let mut seen = HashSet::new();
+    for part in &parts {
+        if !seen.insert(part.key.as_str()) {
+            return Err(invalid(format!("{} appears more than once", part.key)));
+        }
+    }
+
+    let rank = |key: &str| {
+        CANONICAL_RULE_ORDER
+            .iter()
+            .position(|known| *known == key)
+            .unwrap_or(CANONICAL_RULE_ORDER.len())
+    };
+    let mut ordered: Vec<&RulePart> = parts.iter().collect();
+    ordered.sort_by_key(|part| rank(&part.key));
+    Ok(ordered
+        .into_iter()
+        .map(|part| {
+            if rank(&part.key) < CANONICAL_RULE_ORDER.len() {
+                format!("{}={}", part.key, part.value.to_ascii_uppercase())
+            } else {
+                format!("{}={}", part.key, part.value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";"))
+}
+
+/// Summarizes the series within `window`: its first and last occurrence and
+/// their count, which are only complete for a finite series or a window
+/// with an end. Otherwise just the first occurrence is looked up. Fails
+/// with [`CoreError::LimitExceeded`] past `hard_limit` occurrences, or past
+/// `hard_limit` instances generated before exclusions when counting those.
+pub fn summarize_series(
+    spec: &RecurrenceSpec,
+    window: &Window,
+    hard_limit: usize,
+) -> Result<SeriesSummary, CoreError> {
+    let (rrules, exrules) = parse_validated_rules(spec)?;
+    let mut dates = spec_window_dates(spec, &rrules, &exrules, window);
+    let format = |dt: DateTime<Tz>| dt.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+    let first = dates.next();
+    let (last, count) = if is_finite(spec) || window.end.is_some() {
+        let mut last = first;
+        let mut count = usize::from(first.is_some());
+        for dt in dates {
+            if count == hard_limit {
+                return Err(CoreError::LimitExceeded { limit: hard_limit });
+            }
+            count += 1;
+            last = Some(dt);
+        }
+        (last, Some(count))
+    } else {
+        (None, None)
+    };
+    let (exdates, exrules) = match count {
+        Some(_) => {
+            let (exdates, exrules) =
+                applied_exclusions(spec, &rrules, &exrules, window, hard_limit)?;
+            (Some(exdates), Some(exrules))
+        }
+        None => (None, None),
+    };
+
+    Ok(SeriesSummary {
+        description: describe(spec),
+        first: first.map(format),
+        last: last.map(format),
+        count,
+        is_finite: is_finite(spec),
+        exdates,
+        exrules,
+    })
+}
+
+/// How many of the series' instances within `window` an EXDATE and an
+/// EXRULE remove, found by generating the series without exclusions.
+fn applied_exclusions(
+    spec: &RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    window: &Window,
+    hard_limit: usize,
+) -> Result<(usize, usize), CoreError> {
+    if spec.exdates.is_empty() && exrules.is_empty() {
+        return Ok((0, 0));
+    }
+    let unexcluded = RecurrenceSpec {
+        exdates: Vec::new(),
+        exrules: Vec::new(),
+        ..spec.clone()
+    };
+    let exdates: HashSet<i64> = spec.exdates.iter().map(DateTime::timestamp).collect();
+    let (mut by_exdate, mut by_exrule) = (0, 0);
+    for (walked, dt) in
+        series_window_dates(&unexcluded, rrules, &[], window, Deadline::default()).enumerate()
+    {
+        if walked == hard_limit {
+            return Err(CoreError::LimitExceeded { limit: hard_limit });
+        }
+        by_exdate += usize::from(exdates.contains(&dt.timestamp()));
+        by_exrule += usize::from(
+            exrules
+                .iter()
+                .any(|rule| matches_exrule_at(spec.dtstart, rule, dt)),
+        );
+    }
+    Ok((by_exdate, by_exrule))
+}
+
+/// One English sentence per RRULE, e.g. "Every 2 weeks on Monday and
+/// Wednesday until 10 Jan 2026". Parts without a phrasing yet are echoed
+/// literally in parentheses; a rule with an unknown FREQ is echoed as-is.
+pub fn describe(spec: &RecurrenceSpec) -> Vec<String> {
+    spec.rrules
+        .iter()
+        .map(|rule| describe_rule(rule, spec.dtstart.timezone()))
+        .collect()
+}
+
+fn describe_rule(rule: &str, tz: Tz) -> String {
+    let parts = parse_rule_parts(rule);
+    let field = |key: &str| {
+        parts
+            .iter()
+            .rev()
+            .find(|part| part.key == key)
+            .map(|part| part.value.as_str())
+    };
+    let list = |key: &str| -> Vec<&str> {
+        field(key)
+            .map(|value| value.split(',').map(str::trim).collect())
+            .unwrap_or_default()
+    };
+
+    let Some(unit) = field("FREQ").and_then(freq_unit) else {
+        return rule.to_string();
+    };
+    let mut literal = Vec::new();
+    let interval = match field("INTERVAL").map(str::parse::<u32>) {
+        None => 1,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            literal.push(format!(
+                "INTERVAL={}",
+                field("INTERVAL").unwrap_or_default()
+            ));
+            1
+        }
+    };
+    let mut text = if interval == 1 {
+        format!("Every {unit}")
+    } else {
+        format!("Every {interval} {unit}s")
+    };
+
+    let months: Option<Vec<&str>> = list("BYMONTH")
+        .into_iter()
+        .map(|month| {
+            month
+                .parse::<u8>()
+                .ok()
+                .and_then(|n| chrono::Month::try_from(n).ok())
+                .map(|month| month.name())
+        })
+        .collect();
+    match months {
+        Some(months) if !months.is_empty() => {
+            text.push_str(&format!(" in {}", join_english(&months)));
+        }
+        Some(_) => {}
+        None => literal.push(format!("BYMONTH={}", field("BYMONTH").unwrap_or_default())),
+    }
+
+    let positions: Option<Vec<i32>> = list("BYSETPOS").iter().map(|p| p.parse().ok()).collect();
+    let positions = positions.unwrap_or_else(|| {
+        literal.push(format!(
+            "BYSETPOS={}",
+            field("BYSETPOS").unwrap_or_default()
+        ));
+        Vec::new()
+    });
+    let mut positions_used = positions.is_empty();
+
+    let days: Option<Vec<(Option<i32>, Weekday)>> = list("BYDAY")
+        .iter()
+        .map(|token| parse_byday_token(token))
+        .collect();
+    match days {
+        Some(days) if !days.is_empty() => {
+            let names: Vec<String> = days
+                .iter()
+                .map(|(nth, day)| match nth {
+                    Some(n) => format!("the {} {}", ordinal_word(*n), weekday_name(*day)),
+                    None => weekday_name(*day).to_string(),
+                })
+                .collect();
+            let plain = days.iter().all(|(nth, _)| nth.is_none());
+            if plain && !positions.is_empty() {
+                let which: Vec<String> = positions.iter().map(|n| ordinal_word(*n)).collect();
+                let which = join_english(&which);
+                if names.len() == 1 {
+                    text.push_str(&format!(" on the {which} {}", names[0]));
+                } else {
+                    text.push_str(&format!(" on the {which} of {}", join_english(&names)));
+                }
+                positions_used = true;
+            } else {
+                text.push_str(&format!(" on {}", join_english(&names)));
+            }
+        }
+        Some(_) => {}
+        None => literal.push(format!("BYDAY={}", field("BYDAY").unwrap_or_default())),
+    }
+
+    let monthdays: Option<Vec<String>> = list("BYMONTHDAY")
+        .iter()
+        .map(|day| day.parse::<i32>().ok().map(monthday_phrase))
+        .collect();
+    match monthdays {
+        Some(monthdays) if !monthdays.is_empty() => {
+            let noun = if monthdays.len() == 1 { "day" } else { "days" };
+            let connector = if field("BYDAY").is_some() {
+                " falling"
+            } else {
+                ""
+            };
+            text.push_str(&format!(
+                "{connector} on the {} {noun} of the month",
+                join_english(&monthdays)
+            ));
+        }
+        Some(_) => {}
+        None => literal.push(format!(
+            "BYMONTHDAY={}",
+            field("BYMONTHDAY").unwrap_or_default()
+        )),
+    }
+
+    if !positions_used {
+        let which: Vec<String> = positions.iter().map(|n| ordinal_word(*n)).collect();
+        let noun = if positions.len() == 1 {
+            "match"
+        } else {
+            "matches"
+        };
+        text.push_str(&format!(
+            ", keeping the {} {noun} per {unit}",
+            join_english(&which)
+        ));
+    }
+
+    const DESCRIBED: [&str; 8] = [
+        "FREQ",
+        "INTERVAL",
+        "BYMONTH",
+        "BYSETPOS",
+        "BYDAY",
+        "BYMONTHDAY",
+        "COUNT",
+        "UNTIL",
+    ];
+    literal.extend(
+        parts
+            .iter()
+            .filter(|part| !DESCRIBED.contains(&part.key.as_str()))
+            .map(|part| format!("{}={}", part.key, part.value)),
+    );
+    if !literal.is_empty() {
+        text.push_str(&format!(" ({})", literal.join("; ")));
+    }
+
+    if let Some(count) = field("COUNT") {
+        match count.parse::<u32>() {
+            Ok(1) => text.push_str(" for 1 occurrence"),
+            Ok(n) => text.push_str(&format!(" for {n} occurrences")),
+            Err(_) => text.push_str(&format!(" (COUNT={count})")),
+        }
+    }
+    if let Some(until) = field("UNTIL") {
+        match until_date(until, tz) {
+            Some(date) => text.push_str(&format!(" until {}", date.format("%-d %b %Y"))),
+            None => text.push_str(&format!(" (UNTIL={until})")),
+        }
+    }
+
+    text
+}
+
+fn freq_unit(freq: &str) -> Option<&'static str> {
+    match freq.to_ascii_uppercase().as_str() {
+        "YEARLY" => Some("year"),
+        "MONTHLY" => Some("month"),
+        "WEEKLY" => Some("week"),
+        "DAILY" => Some("day"),
+        "HOURLY" => Some("hour"),
+        "MINUTELY" => Some("minute"),
+        "SECONDLY" => Some("second"),
+        _ => None,
+    }
+}
+
+/// Which of the rule's BYDAY/BYMONTHDAY constraints DTSTART fails, if the
+/// rule indeed does not generate DTSTART; `None` otherwise.
+fn dtstart_pattern_mismatch(
+    spec: &RecurrenceSpec,
+    rule: &str,
+    fields: &HashMap<String, String>,
+) -> Option<String> {
+    let dtstart = spec.dtstart;
+    let mut failed = Vec::new();
+    if let Some(byday) = fields.get("BYDAY") {
+        let days: Vec<Weekday> = byday
+            .split(',')
+            .filter_map(|token| parse_byday_token(token.trim()))
+            .map(|(_, day)| day)
+            .collect();
+        if !days.is_empty() && !days.contains(&dtstart.weekday()) {
+            failed.push(format!(
+                "DTSTART is a {} ({}), not in BYDAY={byday}",
+                weekday_name(dtstart.weekday()),
+                weekday_code(dtstart.weekday())
+            ));
+        }
+    }
+    if let Some(bymonthday) = fields.get("BYMONTHDAY") {
+        let day = dtstart.day() as i32;
+        let month_len = last_day_of_month(dtstart.date_naive()) as i32;
+        let matches = bymonthday
+            .split(',')
+            .filter_map(|value| value.trim().parse::<i32>().ok())
+            .any(|n| n == day || n == day - month_len - 1);
+        if !matches {
+            failed.push(format!(
+                "DTSTART is day {day} of the month, not in BYMONTHDAY={bymonthday}"
+            ));
+        }
+    }
+    if failed.is_empty() {
+        return None;
+    }
+    let generated = parse_validated_rule(rule, dtstart, spec.dtstart_type)
+        .is_ok_and(|parsed| matches_rule_at(dtstart, &parsed, dtstart));
+    (!generated).then(|| failed.join("; "))
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// "first", "second", ..., "last", "second-to-last".
+fn ordinal_word(n: i32) -> String {
+    const WORDS: [&str; 5] = ["first", "second", "third", "fourth", "fifth"];
+    match n {
+        -1 => "last".to_string(),
+        n if n < 0 => format!("{}-to-last", ordinal_word(-n)),
+        1..=5 => WORDS[n as usize - 1].to_string(),
+        n => ordinal_suffix(n),
+    }
+}
+
+/// "1st", "2nd", "11th", "23rd".
+fn ordinal_suffix(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn monthday_phrase(day: i32) -> String {
+    if day < 0 {
+        ordinal_word(day)
+    } else {
+        ordinal_suffix(day)
+    }
+}
+
+fn join_english<S: AsRef<str>>(items: &[S]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.as_ref().to_string(),
+        [init @ .., last] => {
+            let init: Vec<&str> = init.iter().map(AsRef::as_ref).collect();
+            format!("{} and {}", init.join(", "), last.as_ref())
+        }
+    }
+}
+
+/// The UNTIL day as seen in the DTSTART zone.
+fn until_date(value: &str, tz: Tz) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some(date);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(
+            Utc.from_utc_datetime(&naive)
+                .with_timezone(&tz)
+                .date_naive(),
+        );
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .map(|naive| naive.date())
+}
+
+/// Occurrences as a JSON object keyed by instant, for membership lookups
+/// without scanning: the key is `start_utc`, or `start` after
+/// [`combine_datetimes`], or `start_local` for floating occurrences, and is
+/// removed from the value. Several occurrences at the same instant (e.g. from
+/// different rules) share one key whose value is an array.
+pub fn occurrence_map(occurrences: &[Occurrence]) -> Result<serde_json::Value, CoreError> {
+    let mut map = serde_json::Map::new();
+    for occ in occurrences {
+        let (field, key) = if !occ.start_utc.is_empty() {
+            ("start_utc", occ.start_utc.clone())
+        } else if let Some(start) = &occ.start {
+            ("start", start.clone())
+        } else {
+            ("start_local", occ.start_local.clone())
+        };
+        let mut value =
+            serde_json::to_value(occ).map_err(|err| CoreError::InvalidJson(err.to_string()))?;
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove(field);
+        }
+        match map.get_mut(&key) {
+            None => {
+                map.insert(key, value);
+            }
+            Some(serde_json::Value::Array(existing)) => existing.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = serde_json::Value::Array(vec![first, value]);
+            }
+        }
+    }
+    Ok(serde_json::Value::Object(map))
+}
+
+pub fn canonical_json(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut ordered: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+            for (k, v) in map {
+                ordered.insert(k.clone(), canonical_json(v));
+            }
+            let mut out = serde_json::Map::new();
+            for (k, v) in ordered {
+                out.insert(k, v);
+            }
+            serde_json::Value::Object(out)
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.iter().map(canonical_json).collect())
+        }
+        _ => value.clone(),
+    }
+}
+
+const ALL_WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+fn parse_weekday_code(value: &str) -> Option<Weekday> {
+    let day = match value.trim().to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+/// Splits a BYDAY token like `-1FR` or `MO` into its optional ordinal and
+/// weekday.
+fn parse_byday_token(token: &str) -> Option<(Option<i32>, Weekday)> {
+    let token = token.trim();
+    let split = token.len().checked_sub(2)?;
+    let (ordinal, day) = (token.get(..split)?, token.get(split..)?);
+    let day = parse_weekday_code(day)?;
+    if ordinal.is_empty() {
+        return Some((None, day));
+    }
+    let ordinal = ordinal.strip_prefix('+').unwrap_or(ordinal);
+    ordinal.parse::<i32>().ok().map(|n| (Some(n), day))
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// The occurrence's local wall-clock time, from `start_local` or `start`.
+fn occurrence_local(occ: &Occurrence) -> Option<NaiveDateTime> {
+    match &occ.start {
+        Some(start) => DateTime::parse_from_rfc3339(start)
+            .ok()
+            .map(|dt| dt.naive_local()),
+        None => NaiveDateTime::parse_from_str(&occ.start_local, "%Y-%m-%dT%H:%M:%S").ok(),
+    }
+}
+
+fn occurrence_utc(occ: &Occurrence) -> Result<NaiveDateTime, CoreError> {
+    NaiveDateTime::parse_from_str(&occ.start_utc, "%Y-%m-%dT%H:%M:%SZ").map_err(|err| {
+        CoreError::InvalidDateTime {
+            input: occ.start_utc.clone(),
+            reason: err.to_string(),
+        }
+    })
+}
+
+fn build_rdate_index(spec: &RecurrenceSpec) -> HashMap<i64, usize> {
+    let mut index = HashMap::new();
+    for (i, dt) in spec.rdates.iter().enumerate() {
+        index.entry(dt.timestamp()).or_insert(i);
+    }
+    index
+}
+
+/// The override that moved an instance to `dt`, if any.
+fn applied_override<'a>(
+    spec: &'a RecurrenceSpec,
+    dt: &DateTime<Tz>,
+) -> Option<&'a InstanceOverride> {
+    spec.overrides
+        .iter()
+        .find(|o| o.start.timestamp() == dt.timestamp())
+}
+
+/// The slot that generated `dt`: a moved instance is attributed to
+/// whatever generated its original slot, its `RECURRENCE-ID`.
+fn generated_slot(spec: &RecurrenceSpec, dt: &DateTime<Tz>) -> DateTime<Tz> {
+    applied_override(spec, dt).map_or(*dt, |o| o.recurrence_id.with_timezone(&dt.timezone()))
+}
+
+fn build_occurrence(
+    spec: &RecurrenceSpec,
+    tz: &Tz,
+    rrules: &[RRule],
+    rdate_index: &HashMap<i64, usize>,
+    dt: DateTime<Tz>,
+) -> Result<Occurrence, CoreError> {
+    let local = dt.with_timezone(tz);
+    let applied = applied_override(spec, &local);
+    let generated = generated_slot(spec, &local);
+    let ts = generated.timestamp();
+
+    let (source, rule_index) = if let Some(index) = rdate_index.get(&ts) {
+        (OccurrenceSource::Rdate, *index)
+    } else if rrules.len() <= 1 {
+        // A lone rule generated every non-RDATE date; skip the re-match,
+        // which walks the rule from DTSTART for each date.
+        (OccurrenceSource::Rrule, 0)
+    } else {
+        let found = rrules
+            .iter()
+            .position(|rule| matches_rule_at(spec.dtstart, rule, generated));
+        (OccurrenceSource::Rrule, found.unwrap_or(0))
+    };
+
+    let end = spec
+        .duration
+        .map(|duration| duration.end_of(local))
+        .transpose()?;
+    Ok(Occurrence {
+        start_local: local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        start_utc: local
+            .with_timezone(&Utc)
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string(),
+        start: None,
+        tz: Some(spec.tz.clone()),
+        source,
+        rule_index,
+        abbrev: None,
+        period: None,
+        end_local: end.map(|end| end.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        end_utc: end.map(|end| {
+            end.with_timezone(&Utc)
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string()
+        }),
+        recurrence_id: applied.map(|_| generated.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        sequence: applied.map(|o| o.sequence),
+        weekday: None,
+        day_of_year: None,
+        iso_week: None,
+        iso_year: None,
+    })
+}
+
+fn build_rrule_set(spec: &RecurrenceSpec, rrules: &[RRule], exrules: &[RRule]) -> RRuleSet {
+    let mut set = RRuleSet::new(spec.dtstart)
+        .set_rrules(rrules.to_vec())
+        .set_exrules(exrules.to_vec());
+    for dt in &spec.rdates {
+        set = set.rdate(*dt);
+    }
+    for dt in &spec.exdates {
+        set = set.exdate(*dt);
+    }
+    set
+}
+
+/// The spec's dates within `window`, in chronological order, with
+/// RECURRENCE-ID overrides applied.
+fn spec_window_dates<'a>(
+    spec: &'a RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    window: &Window,
+) -> Box<dyn Iterator<Item = DateTime<Tz>> + 'a> {
+    spec_window_dates_within(spec, rrules, exrules, window, Deadline::default())
+}
+
+/// Like [`spec_window_dates`], but the walk ends early once `deadline`
+/// passes; the caller reports that through [`Deadline::check`].
+fn spec_window_dates_within<'a>(
+    spec: &'a RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    window: &Window,
+    deadline: Deadline,
+) -> Box<dyn Iterator<Item = DateTime<Tz>> + 'a> {
+    let dates = series_window_dates(spec, rrules, exrules, window, deadline);
+    if spec.overrides.is_empty() {
+        return dates;
+    }
+
+    // Overridden instances leave their original slot and reappear at the
+    // override's start, which may lie inside or outside the window.
+    let moved: HashSet<i64> = spec
+        .overrides
+        .iter()
+        .map(|o| o.recurrence_id.timestamp())
+        .collect();
+    let Window { start, end } = *window;
+    let mut extra: Vec<_> = spec
+        .overrides
+        .iter()
+        .map(|o| o.start)
+        .filter(|dt| start.is_none_or(|start| *dt >= start) && end.is_none_or(|end| *dt <= end))
+        .collect();
+    extra.sort();
+    extra.dedup();
+    let mut extra = extra.into_iter().peekable();
+    let mut dates = dates
+        .filter(move |dt| !moved.contains(&dt.timestamp()))
+        .peekable();
+    Box::new(std::iter::from_fn(move || {
+        match (dates.peek(), extra.peek()) {
+            (Some(dt), Some(moved)) if moved < dt => extra.next(),
+            (Some(dt), Some(moved)) if moved == dt => {
+                extra.next();
+                dates.next()
+            }
+            (Some(_), _) => dates.next(),
+            (None, _) => extra.next(),
+        }
+    }))
+}
+
+/// The series' own dates within `window`, before RECURRENCE-ID overrides.
+///
+/// RDATE-only specs skip the `RRuleSet` walk: their dates are filtered
+/// against EXDATE/EXRULE directly.
+fn series_window_dates<'a>(
+    spec: &'a RecurrenceSpec,
+    rrules: &[RRule],
+    exrules: &[RRule],
+    window: &Window,
+    deadline: Deadline,
+) -> Box<dyn Iterator<Item = DateTime<Tz>> + 'a> {
+    if !rrules.is_empty() {
+        return Box::new(iter_window_dates(
+            build_rrule_set(spec, rrules, exrules),
+            window,
+            deadline,
+        ));
+    }
+
+    let Window { start, end } = *window;
+    let excluded: HashSet<i64> = spec.exdates.iter().map(DateTime::timestamp).collect();
+    let mut dates: Vec<_> = spec
+        .rdates
+        .iter()
+        .copied()
+        .filter(|dt| start.is_none_or(|start| *dt >= start) && end.is_none_or(|end| *dt <= end))
+        .filter(|dt| !excluded.contains(&dt.timestamp()))
+        .collect();
+    dates.sort();
+    dates.dedup_by_key(|dt| dt.timestamp());
+    let exrules = exrules.to_vec();
+    Box::new(dates.into_iter().filter(move |dt| {
+        !exrules
+            .iter()
+            .any(|rule| matches_exrule_at(spec.dtstart, rule, *dt))
+    }))
+}
+
+/// Lazily walks the set in chronological order, clipped to an inclusive
+/// window. Unlike `RRuleSet::all`, this is not capped at `u16::MAX` dates.
+///
+/// `deadline` is checked for every candidate the set yields, including the
+/// ones skipped before the window start.
+fn iter_window_dates(
+    set: RRuleSet,
+    window: &Window,
+    deadline: Deadline,
+) -> impl Iterator<Item = DateTime<Tz>> + use<> {
+    let Window { start, end } = *window;
+    set.limit()
+        .into_iter()
+        .take_while(move |_| deadline.admits())
+        .take_while(|dt| dt.with_timezone(&Utc).year() <= MAX_YEAR)
+        .skip_while(move |dt| start.is_some_and(|start| *dt < start))
+        .take_while(move |dt| end.is_none_or(|end| *dt <= end))
+}
+
+/// Optional wall-clock budget for a date walk. Clones share the expiry flag,
+/// so the iterator that stops early and the caller that reports it agree.
+#[derive(Clone, Default)]
+struct Deadline {
+    budget: Option<(Instant, StdDuration)>,
+    expired: Rc<Cell<bool>>,
+}
+
+impl Deadline {
+    fn new(budget: Option<StdDuration>) -> Self {
+        Self {
+            budget: budget.map(|budget| (Instant::now(), budget)),
+            expired: Rc::default(),
+        }
+    }
+
+    /// Whether the walk may take another candidate; records expiry.
+    fn admits(&self) -> bool {
+        let Some((started, budget)) = self.budget else {
+            return true;
+        };
+        if started.elapsed() >= budget {
+            self.expired.set(true);
+        }
+        !self.expired.get()
+    }
+
+    fn check(&self) -> Result<(), CoreError> {
+        match self.budget {
+            Some((_, budget)) if self.expired.get() => Err(CoreError::TimeLimitExceeded {
+                budget_ms: budget.as_millis(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Last year the `rrule` crate generates reliably; later UNTIL values and
+/// window bounds are rejected up front and iteration stops after it. The
+/// year is judged in UTC, so every reported `start_utc` has four digits.
+const MAX_YEAR: i32 = 9999;
+
+/// Feeds the query's dates to `f` in order, enforcing `hard_limit` the same
+/// way for every caller. Returns how many dates were passed on.
+///
+/// The wall-clock budget lives in the date iterator (see [`Deadline`]), which
+/// checks it per candidate; a rule that spins inside the `rrule` iterator
+/// without yielding is only stopped by its loop guard.
+fn for_each_date<T>(
+    dates: impl Iterator<Item = T>,
+    query: &ExpandQuery,
+    hard_limit: usize,
+    mut f: impl FnMut(T) -> ControlFlow<()>,
+) -> Result<usize, CoreError> {
+    // `Between` without a count fails once the window holds more than
+    // `hard_limit` dates; the other queries stop quietly at their cap.
+    let cap = match query {
+        ExpandQuery::Between { count: None, .. } => None,
+        ExpandQuery::Between {
+            count: Some(count), ..
+        }
+        | ExpandQuery::After { count, .. } => {
+            if *count == 0 {
+                return Err(CoreError::InvalidCount(*count));
+            }
+            if *count > hard_limit {
+                return Err(CoreError::LimitExceeded { limit: hard_limit });
+            }
+            Some(*count)
+        }
+        ExpandQuery::Before { .. } | ExpandQuery::Unbounded => Some(hard_limit),
+    };
+
+    let mut emitted = 0;
+    for dt in dates {
+        if cap == Some(emitted) {
+            break;
+        }
+        if emitted == hard_limit {
+            return Err(CoreError::LimitExceeded { limit: hard_limit });
+        }
+        emitted += 1;
+        if f(dt).is_break() {
+            break;
+        }
+    }
+    Ok(emitted)
+}
+
+fn parse_validated_rules(spec: &RecurrenceSpec) -> Result<(Vec<RRule>, Vec<RRule>), CoreError> {
+    let mut rrules = Vec::with_capacity(spec.rrules.len());
+    for raw in &spec.rrules {
+        rrules.push(parse_validated_rule(raw, spec.dtstart, spec.dtstart_type)?);
+    }
+
+    let mut exrules = Vec::with_capacity(spec.exrules.len());
+    for raw in &spec.exrules {
+        exrules.push(parse_validated_rule(raw, spec.dtstart, spec.dtstart_type)?);
+    }
+
+    Ok((rrules, exrules))
+}
+
+fn parse_validated_rule(
+    value: &str,
+    dtstart: DateTime<Tz>,
+    dtstart_type: DateValueType,
+) -> Result<RRule, CoreError> {
+    if let Some(part) = empty_rule_parts(&parse_rule_parts(value)).next() {
+        return Err(CoreError::InvalidRrule {
+            rule: value.to_string(),
+            reason: format!("{} has an empty value", part.key),
+        });
+    }
+    if let Some(year) = until_year_beyond_max(value, dtstart) {
+        return Err(CoreError::InvalidRrule {
+            rule: value.to_string(),
+            reason: format!("UNTIL year {year} is beyond {MAX_YEAR}"),
+        });
+    }
+
+    let stripped = strip_extension_parts(value);
+    let standard = stripped.as_deref().unwrap_or(value);
+    let normalized = match dtstart_type {
+        DateValueType::Date => normalize_date_until(standard, dtstart),
+        DateValueType::DateTime => normalize_local_until(standard, dtstart),
+    };
+    let unvalidated: RRule<Unvalidated> = normalized
+        .as_deref()
+        .unwrap_or(standard)
+        .parse::<RRule<Unvalidated>>()
+        .map_err(|err: rrule::RRuleError| CoreError::InvalidRrule {
+            rule: value.to_string(),
+            reason: err.to_string(),
+        })?;
+
+    unvalidated
+        .validate(dtstart)
+        .map_err(|err: rrule::RRuleError| CoreError::InvalidRrule {
+            rule: value.to_string(),
+            reason: err.to_string(),
+        })
+}
+
+/// The UTC year of the rule's `UNTIL` if it lies past [`MAX_YEAR`]. The
+/// `rrule` crate only reports such values as an unparseable format. A local
+/// `UNTIL` is read in DTSTART's zone, as [`normalize_local_until`] and
+/// [`normalize_date_until`] do, so late on 9999-12-31 it can land in 10000.
+fn until_year_beyond_max(rule: &str, dtstart: DateTime<Tz>) -> Option<i64> {
+    let part = parse_rule_parts(rule)
+        .into_iter()
+        .rev()
+        .find(|part| part.key == "UNTIL")?;
+    let date = part.value.split('T').next().unwrap_or_default();
+    if date.len() < 8 || !date.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year: i64 = date[..date.len() - 4].parse().ok()?;
+    if year > i64::from(MAX_YEAR) {
+        return Some(year);
+    }
+    if part.value.ends_with(['Z', 'z']) {
+        return None;
+    }
+    let local = match NaiveDate::parse_from_str(&part.value, "%Y%m%d") {
+        Ok(date) => date.and_hms_opt(23, 59, 59)?,
+        Err(_) => NaiveDateTime::parse_from_str(&part.value, "%Y%m%dT%H%M%S").ok()?,
+    };
+    let (until, _) = resolve_local(dtstart.timezone(), local, rule).ok()?;
+    let year = until.with_timezone(&Utc).year();
+    (year > MAX_YEAR).then_some(i64::from(year))
+}
+
+/// Rewrites a DATE `UNTIL=YYYYMMDD` to the last second of that local day in
+/// UTC, so all-day rules include the UNTIL day itself. The `rrule` crate
+/// would otherwise read the bare date as a floating midnight and reject it.
+fn normalize_date_until(rule: &str, dtstart: DateTime<Tz>) -> Option<String> {
+    let part = parse_rule_parts(rule)
+        .into_iter()
+        .rev()
+        .find(|part| part.key == "UNTIL")?;
+    let date = NaiveDate::parse_from_str(&part.value, "%Y%m%d").ok()?;
+    let (end_of_day, _) =
+        resolve_local(dtstart.timezone(), date.and_hms_opt(23, 59, 59)?, rule).ok()?;
+    let until = end_of_day.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ");
+    let (start, end) = part.span;
+    Some(format!("{}UNTIL={until}{}", &rule[..start], &rule[end..]))
+}
+
+/// Rewrites a local `UNTIL=YYYYMMDDTHHMMSS` (no `Z`) to UTC, reading it as
+/// wall-clock time in DTSTART's zone. The `rrule` crate only accepts UTC
+/// UNTIL values for a zoned DTSTART; a floating spec's stand-in UTC zone
+/// keeps its UNTIL unchanged.
+fn normalize_local_until(rule: &str, dtstart: DateTime<Tz>) -> Option<String> {
+    let part = parse_rule_parts(rule)
+        .into_iter()
+        .rev()
+        .find(|part| part.key == "UNTIL")?;
+    let local = NaiveDateTime::parse_from_str(&part.value, "%Y%m%dT%H%M%S").ok()?;
+    let (until, _) = resolve_local(dtstart.timezone(), local, rule).ok()?;
+    let until = until.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ");
+    let (start, end) = part.span;
+    Some(format!("{}UNTIL={until}{}", &rule[..start], &rule[end..]))
+}
+
+fn matches_rule_at(dtstart: DateTime<Tz>, rule: &RRule, at: DateTime<Tz>) -> bool {
+    let result = RRuleSet::new(dtstart)
+        .rrule(rule.clone())
+        .after(at)
+        .before(at)
+        .all(1);
+    !result.dates.is_empty()
+}
+
+/// An EXRULE excludes `at` exactly when it would generate `at`; a set holding
+/// only the exclusion yields nothing, so evaluate it as a generating rule.
+fn matches_exrule_at(dtstart: DateTime<Tz>, rule: &RRule, at: DateTime<Tz>) -> bool {
+    matches_rule_at(dtstart, rule, at)
+}
+
+#[derive(Debug, Clone)]
+struct RulePart {
+    key: String,
+    value: String,
+    span: (usize, usize),
+}
+
+impl RulePart {
+    /// An RFC 5545 `X-name` extension part.
+    fn is_extension(&self) -> bool {
+        self.key.starts_with("X-")
+    }
+}
+
+/// Byte span of the rule part that a [`CoreError::InvalidRrule`] `reason`
+/// blames, for pointing at it in diagnostics.
+///
+/// Parts are matched by their name or one of their values appearing in the
+/// reason (the `rrule` crate quotes them in backticks); a part whose name the
+/// reason also mentions wins. `None` when nothing in the rule is named.
+pub fn invalid_rule_span(rule: &str, reason: &str) -> Option<(usize, usize)> {
+    let quoted: Vec<&str> = reason.split('`').skip(1).step_by(2).collect();
+    let words: HashSet<&str> = reason
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .collect();
+    let parts = parse_rule_parts(rule);
+    let blamed: Vec<&RulePart> = parts
+        .iter()
+        .filter(|part| {
+            quoted.contains(&part.key.as_str())
+                || reason.starts_with(&format!("{} ", part.key))
+                || part.value.split(',').any(|value| {
+                    quoted
+                        .iter()
+                        .any(|quoted| quoted.eq_ignore_ascii_case(value.trim()))
+                })
+        })
+        .collect();
+    blamed
+        .iter()
+        .find(|part| words.contains(part.key.as_str()))
+        .or(blamed.first())
+        .map(|part| part.span)
+}
+
+/// The rule without its `X-` parts, or `None` if it has none. The `rrule`
+/// validator rejects every part it does not know.
+fn strip_extension_parts(rule: &str) -> Option<String> {
+    let is_extension = |segment: &&str| {
+        segment
+            .trim_start()
+            .get(..2)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("X-"))
+    };
+    if !rule.split(';').any(|segment| is_extension(&segment)) {
+        return None;
+    }
+    let kept: Vec<&str> = rule
+        .split(';')
+        .filter(|segment| !is_extension(segment))
+        .collect();
+    Some(kept.join(";"))
+}
+
+fn parse_rule_parts(rule: &str) -> Vec<RulePart> {
+    let mut parts = Vec::new();
+    let mut offset = 0;
+
+    for segment in rule.split(';') {
+        let start = offset + (segment.len() - segment.trim_start().len());
+        let end = offset + segment.trim_end().len();
+        offset += segment.len() + 1;
+
+        if let Some((k, v)) = segment.split_once('=') {
+            let key = k.trim().to_ascii_uppercase();
+            // FREQ values are compared throughout, and `rrule` accepts any case.
+            let value = match key.as_str() {
+                "FREQ" => v.trim().to_ascii_uppercase(),
+                _ => v.trim().to_string(),
+            };
+            parts.push(RulePart {
+                key,
+                value,
+                span: (start, end),
+            });
+        }
+    }
+
+    parts
+}
+
+const RULE_KEYS: [&str; 14] = [
+    "FREQ",
+    "UNTIL",
+    "COUNT",
+    "INTERVAL",
+    "BYSECOND",
+    "BYMINUTE",
+    "BYHOUR",
+    "BYDAY",
+    "BYMONTHDAY",
+    "BYYEARDAY",
+    "BYWEEKNO",
+    "BYMONTH",
+    "BYSETPOS",
+    "WKST",
+];
+
+const FREQ_VALUES: [&str; 7] = [
+    "YEARLY", "MONTHLY", "WEEKLY", "DAILY", "HOURLY", "MINUTELY", "SECONDLY",
+];
+
+/// RFC 7529 parts the validator rejects as unknown.
+const UNSUPPORTED_RULE_KEYS: [&str; 2] = ["RSCALE", "SKIP"];
+
+/// ICS properties read by `parse_ics_spec`; everything else is ignored.
+const ICS_PROPERTIES: [&str; 14] = [
+    "DTSTART",
+    "DTEND",
+    "DURATION",
+    "RRULE",
+    "EXRULE",
+    "RDATE",
+    "EXDATE",
+    "LOCATION",
+    "SUMMARY",
+    "GEO",
+    "UID",
+    "METHOD",
+    "RECURRENCE-ID",
+    "SEQUENCE",
+];
+
+/// Static description of the RRULE/ICS features this crate understands, for
+/// integrators that want to feature-detect before sending a spec.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        freq: FREQ_VALUES.to_vec(),
+        rule_parts: RULE_KEYS.to_vec(),
+        unsupported_rule_parts: UNSUPPORTED_RULE_KEYS.to_vec(),
+        ics_properties: ICS_PROPERTIES.to_vec(),
+        deprecated_properties: vec!["EXRULE"],
+        value_types: vec!["DATE", "DATE-TIME"],
+        inputs: vec!["flags", "ics", "json"],
+    }
+}
+
+/// Built-in expectations for [`selftest`]: a JSON spec and the UTC starts
+/// of its first occurrences. Most cases cross a DST change, so a wrong or
+/// stale tzdata shows up as shifted instants.
+const SELFTEST_CASES: &[(&str, &str, &[&str])] = &[
+    (
+        "berlin-weekly-across-dst",
+        r#"{"dtstart": "2026-03-23T10:00:00", "tz": "Europe/Berlin", "rrule": ["FREQ=WEEKLY;COUNT=3"]}"#,
+        &[
+            "2026-03-23T09:00:00Z",
+            "2026-03-30T08:00:00Z",
+            "2026-04-06T08:00:00Z",
+        ],
+    ),
+    (
+        "new-york-last-weekday",
+        r#"{"dtstart": "2026-01-30T09:00:00", "tz": "America/New_York", "rrule": ["FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=3"]}"#,
+        &[
+            "2026-01-30T14:00:00Z",
+            "2026-02-27T14:00:00Z",
+            "2026-03-31T13:00:00Z",
+        ],
+    ),
+    (
+        "lord-howe-half-hour-dst",
+        r#"{"dtstart": "2026-04-04T12:00:00", "tz": "Australia/Lord_Howe", "rrule": ["FREQ=DAILY;COUNT=2"]}"#,
+        &["2026-04-04T01:00:00Z", "2026-04-05T01:30:00Z"],
+    ),
+    (
+        "leap-day-yearly",
+        r#"{"dtstart": "2024-02-29T12:00:00", "tz": "UTC", "rrule": ["FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29;COUNT=2"]}"#,
+        &["2024-02-29T12:00:00Z", "2028-02-29T12:00:00Z"],
+    ),
+    (
+        "until-is-inclusive",
+        r#"{"dtstart": "2026-03-01T10:00:00", "tz": "UTC", "rrule": ["FREQ=DAILY;INTERVAL=2;UNTIL=20260305T100000Z"]}"#,
+        &[
+            "2026-03-01T10:00:00Z",
+            "2026-03-03T10:00:00Z",
+            "2026-03-05T10:00:00Z",
+        ],
+    ),
+    (
+        "tokyo-rdate-and-exdate",
+        r#"{"dtstart": "2026-06-01T08:00:00", "tz": "Asia/Tokyo", "rrule": ["FREQ=DAILY;COUNT=3"], "rdate": ["2026-06-10T08:00:00"], "exdate": ["2026-06-02T08:00:00"]}"#,
+        &[
+            "2026-05-31T23:00:00Z",
+            "2026-06-02T23:00:00Z",
+            "2026-06-09T23:00:00Z",
+        ],
+    ),
+];
+
+/// Outcome of one [`selftest`] case.
+#[derive(Debug, Clone, Serialize)]
+pub struct SelftestResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub expected: Vec<&'static str>,
+    /// UTC starts actually generated; empty when the case failed to expand.
+    pub actual: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs the built-in recurrence expectations, independent of any fixture
+/// files, as a quick check that parsing, expansion and the compiled-in
+/// tzdata agree with known results.
+pub fn selftest() -> Vec<SelftestResult> {
+    SELFTEST_CASES
+        .iter()
+        .map(|&(name, raw, expected)| {
+            let expanded = parse_json_spec(raw, None)
+                .and_then(|spec| expand(&spec, &ExpandQuery::Unbounded, expected.len() + 1));
+            let (actual, error) = match expanded {
+                Ok(occurrences) => (
+                    occurrences.into_iter().map(|occ| occ.start_utc).collect(),
+                    None,
+                ),
+                Err(err) => (Vec::new(), Some(err.to_string())),
+            };
+            SelftestResult {
+                name,
+                passed: error.is_none() && actual == expected,
+                expected: expected.to_vec(),
+                actual,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// Expands CLI shorthand like `daily`, `weekly` or `weekly mon,wed` into a
+/// full RRULE (`FREQ=WEEKLY;BYDAY=MO,WE`). Weekdays may be written as any
+/// prefix of their English name of at least two letters. Returns `None` for
+/// anything else, including real RRULE strings (anything containing `=`).
+pub fn parse_rrule_shorthand(value: &str) -> Option<String> {
+    if value.contains('=') {
+        return None;
+    }
+    let mut words = value.split_whitespace();
+    let freq = words.next()?.to_ascii_uppercase();
+    if !FREQ_VALUES.contains(&freq.as_str()) {
+        return None;
+    }
+    let days: Vec<&str> = words
+        .flat_map(|word| word.split(','))
+        .filter(|token| !token.is_empty())
+        .map(shorthand_weekday)
+        .collect::<Option<_>>()?;
+
+    if days.is_empty() {
+        Some(format!("FREQ={freq}"))
+    } else {
+        Some(format!("FREQ={freq};BYDAY={}", days.join(",")))
+    }
+}
+
+fn shorthand_weekday(token: &str) -> Option<&'static str> {
+    let token = token.to_ascii_lowercase();
+    if token.len() < 2 {
+        return None;
+    }
+    [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+    .into_iter()
+    .find(|day| weekday_name(*day).to_ascii_lowercase().starts_with(&token))
+    .map(weekday_code)
+}
+
+/// Parts of a known RRULE key written without a value (`BYDAY=`), which the
+/// validator would otherwise drop silently or report confusingly.
+fn empty_rule_parts(parts: &[RulePart]) -> impl Iterator<Item = &RulePart> {
+    parts
+        .iter()
+        .filter(|part| part.value.is_empty() && RULE_KEYS.contains(&part.key.as_str()))
+}
+
+/// How often an unnarrowed sub-daily rule fires per day: the FREQ's base
+/// rate divided by INTERVAL, rounded up. `None` for DAILY and coarser.
+fn occurrences_per_day(freq: &str, interval: u32) -> Option<u32> {
+    let base: u32 = match freq {
+        "SECONDLY" => 86_400,
+        "MINUTELY" => 1_440,
+        "HOURLY" => 24,
+        _ => return None,
+    };
+    Some(base.div_ceil(interval.max(1)))
+}
+
+fn parse_rule_fields(rule: &str) -> HashMap<String, String> {
+    parse_rule_parts(rule)
+        .into_iter()
+        .map(|part| (part.key, part.value))
+        .collect()
+}
+
+fn rule_has_count_or_until(rule: &str) -> bool {
+    let fields = parse_rule_fields(rule);
+    fields.contains_key("COUNT") || fields.contains_key("UNTIL")
+}
+
+/// Folds a content line at 75 octets (RFC 5545 §3.1), never splitting a
+/// UTF-8 sequence.
+fn fold_ics_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 37);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn unfold_ics_lines(input: &str) -> Vec<String> {
+    let normalized = input.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<String> = Vec::new();
+
+    for raw in normalized.lines() {
+        if raw.starts_with(' ') || raw.starts_with('\t') {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(raw.trim_start());
+            }
+        } else {
+            lines.push(raw.to_string());
+        }
+    }
+
+    lines
+}
+
+fn parse_property_head(head: &str) -> (String, HashMap<String, String>) {
+    let mut parts = split_outside_quotes(head, ';').into_iter();
+    let name = parts
+        .next()
+        .map(|s| s.trim().to_ascii_uppercase())
+        .unwrap_or_default();
+
+    let mut params = HashMap::new();
+    for part in parts {
+        if let Some((k, v)) = part.split_once('=') {
+            let v = v.trim();
+            let v = v
+                .strip_prefix('"')
+                .and_then(|inner| inner.strip_suffix('"'))
+                .unwrap_or(v);
+            params.insert(k.trim().to_ascii_uppercase(), v.to_string());
+        }
+    }
+
+    (name, params)
+}
+
+/// Byte index of the first `delimiter` outside a double-quoted parameter
+/// value (RFC 5545 §3.2 allows `;`, `:` and `,` inside quotes).
+fn find_outside_quotes(input: &str, delimiter: char) -> Option<usize> {
+    let mut quoted = false;
+    for (index, c) in input.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits on `delimiter`, leaving double-quoted sections intact.
+fn split_outside_quotes(input: &str, delimiter: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = input;
+    while let Some(index) = find_outside_quotes(rest, delimiter) {
+        parts.push(&rest[..index]);
+        rest = &rest[index + delimiter.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+fn unescape_ics_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn parse_geo(value: &str) -> Result<Geo, CoreError> {
+    let invalid = || CoreError::InvalidIcs(format!("GEO must be 'lat;lon', got '{value}'"));
+    let (lat, lon) = value.split_once(';').ok_or_else(invalid)?;
+    let lat: f64 = lat.trim().parse().map_err(|_| invalid())?;
+    let lon: f64 = lon.trim().parse().map_err(|_| invalid())?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(invalid());
+    }
+    Ok(Geo { lat, lon })
+}
+
+fn resolve_property_tz(
+    params: &HashMap<String, String>,
+    fallback_tz: Option<&str>,
+) -> Result<Tz, CoreError> {
+    if let Some(tzid) = params.get("TZID") {
+        return parse_timezone(tzid);
+    }
+
+    if let Some(tz) = fallback_tz {
+        return parse_timezone(tz);
+    }
+
+    Ok(Tz::UTC)
+}
+
+/// Rejects values that contradict an explicit `VALUE=DATE` or
+/// `VALUE=DATE-TIME` parameter, which would otherwise surface as a raw
+/// datetime parse error.
+fn check_value_type(
+    name: &str,
+    params: &HashMap<String, String>,
+    raw: &str,
+) -> Result<(), CoreError> {
+    let Some(declared) = params.get("VALUE") else {
+        return Ok(());
+    };
+    for value in raw.split(',').map(str::trim) {
+        let is_date = value.len() == 8 && value.chars().all(|c| c.is_ascii_digit());
+        if declared.eq_ignore_ascii_case("DATE") && value.contains('T') {
+            return Err(CoreError::InvalidIcs(format!(
+                "{name} has VALUE=DATE but value contains a time component ('{value}')"
+            )));
+        }
+        if declared.eq_ignore_ascii_case("DATE-TIME") && is_date {
+            return Err(CoreError::InvalidIcs(format!(
+                "{name} has VALUE=DATE-TIME but value has no time component ('{value}')"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn parse_value_type_for_multi(params: &HashMap<String, String>, raw: &str) -> DateValueType {
+    if params
+        .get("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+    {
+        return DateValueType::Date;
+    }
+
+    let first = raw.split(',').next().unwrap_or(raw);
+    if first.len() == 8 && first.chars().all(|c| c.is_ascii_digit()) {
+        DateValueType::Date
+    } else {
+        DateValueType::DateTime
+    }
+}
+
+fn parse_ics_multi_datetime_values(
+    raw: &str,
+    tz: &Tz,
+    value_type: DateValueType,
+    policy: DstPolicy,
+) -> Result<Vec<DateTime<Tz>>, CoreError> {
+    raw.split(',')
+        .map(|part| parse_ics_datetime_value(part.trim(), tz, value_type, policy))
+        .collect()
+}
+
+/// `YYYYMMDDTHHMMSS`, or the non-conforming `YYYYMMDDTHHMM` with seconds
+/// taken as 0; see [`lacks_seconds`].
+fn parse_ics_naive_datetime(value: &str, input: &str) -> Result<NaiveDateTime, CoreError> {
+    let format = if lacks_seconds(input) {
+        "%Y%m%dT%H%M"
+    } else {
+        "%Y%m%dT%H%M%S"
+    };
+    NaiveDateTime::parse_from_str(value, format).map_err(|err| CoreError::InvalidDateTime {
+        input: input.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// An ICS DATE-TIME written as `YYYYMMDDTHHMM[Z]`, which some producers emit
+/// although RFC 5545 requires seconds.
+fn lacks_seconds(value: &str) -> bool {
+    let value = value.strip_suffix('Z').unwrap_or(value);
+    value.len() == 13
+        && value.as_bytes()[8] == b'T'
+        && value
+            .char_indices()
+            .all(|(i, c)| i == 8 || c.is_ascii_digit())
+}
+
+fn parse_ics_datetime_value(
+    value: &str,
+    tz: &Tz,
+    value_type: DateValueType,
+    policy: DstPolicy,
+) -> Result<DateTime<Tz>, CoreError> {
+    let dt = match value_type {
+        DateValueType::Date => {
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|err| {
+                CoreError::InvalidDateTime {
+                    input: value.to_string(),
+                    reason: err.to_string(),
+                }
+            })?;
+            let local = date
+                .and_hms_opt(0, 0, 0)
+                .ok_or_else(|| CoreError::InvalidDateTime {
+                    input: value.to_string(),
+                    reason: "could not build midnight datetime".to_string(),
+                })?;
+            localize(*tz, local, value, policy)
+        }
+        DateValueType::DateTime => {
+            if let Some(stripped) = value.strip_suffix('Z') {
+                let naive = parse_ics_naive_datetime(stripped, value)?;
+                let utc_dt = Utc.from_utc_datetime(&naive);
+                Ok(utc_dt.with_timezone(tz))
+            } else {
+                let local = parse_ics_naive_datetime(value, value)?;
+                localize(*tz, local, value, policy)
+            }
+        }
+    }?;
+    within_max_year(dt, value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalResolution {
+    Exact,
+    Overlap,
+    Gap,
+}
+
+fn parse_iso_datetime_resolved(
+    value: &str,
+    tz: &Tz,
+) -> Result<(DateTime<Tz>, LocalResolution), CoreError> {
+    if let Ok(fixed) = DateTime::parse_from_rfc3339(value) {
+        return Ok((fixed.with_timezone(tz), LocalResolution::Exact));
+    }
+
+    let local = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").ok());
+
+    match local {
+        Some(local) => resolve_local(*tz, local, value),
+        None => parse_iso_datetime(value, tz).map(|(dt, _)| (dt, LocalResolution::Exact)),
+    }
+}
+
+fn resolve_local(
+    tz: Tz,
+    local: NaiveDateTime,
+    input: &str,
+) -> Result<(DateTime<Tz>, LocalResolution), CoreError> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Ok((dt, LocalResolution::Exact)),
+        LocalResult::Ambiguous(earliest, _) => Ok((earliest, LocalResolution::Overlap)),
+        LocalResult::None => Ok((offset_before_gap(tz, local, input)?, LocalResolution::Gap)),
+    }
+}
+
+/// The offset changes of `tz` after `from` up to `to` (UTC seconds), as
+/// `(instant, offset before, offset after)`. The offset is probed every
+/// `step` seconds and each change is bisected to the second, so changes
+/// that undo each other within one step are missed.
+fn offset_transitions(tz: &Tz, from: i64, to: i64, step: i64) -> Vec<(i64, i32, i32)> {
+    let offset_at = |at: i64| {
+        DateTime::from_timestamp(at, 0).map(|dt| {
+            tz.offset_from_utc_datetime(&dt.naive_utc())
+                .fix()
+                .local_minus_utc()
+        })
+    };
+    let mut transitions = Vec::new();
+    let (mut lo, Some(mut before)) = (from, offset_at(from)) else {
+        return transitions;
+    };
+    while lo < to {
+        let mut hi = (lo + step).min(to);
+        let Some(after) = offset_at(hi) else {
+            break;
+        };
+        if after == before {
+            lo = hi;
+            continue;
+        }
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if offset_at(mid) == Some(before) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let Some(after) = offset_at(hi) else {
+            break;
+        };
+        transitions.push((hi, before, after));
+        (lo, before) = (hi, after);
+    }
+    transitions
+}
+
+/// The spring-forward transition whose gap swallows `local`, as
+/// `(instant, offset before, offset after)`. The gap's wall-clock times run
+/// from the transition read with the offset before it to the transition
+/// read with the offset after it; only transitions within a day of `local`
+/// are considered.
+fn spring_forward_gap(
+    tz: Tz,
+    local: NaiveDateTime,
+    input: &str,
+) -> Result<(i64, i32, i32), CoreError> {
+    let naive = local.and_utc().timestamp();
+    offset_transitions(&tz, naive - 86_400, naive + 86_400, 900)
+        .into_iter()
+        .find(|(at, before, after)| {
+            (at + i64::from(*before)..at + i64::from(*after)).contains(&naive)
+        })
+        .ok_or_else(|| CoreError::InvalidDateTime {
+            input: input.to_string(),
+            reason: "invalid local time in timezone".to_string(),
+        })
+}
+
+/// `local` read with the UTC offset in effect just before the spring-forward
+/// gap that swallows it, per RFC 5545 §3.3.5: 02:30 on a 02:00→03:00 night
+/// is 03:30. The `rrule` crate places generated instances the same way, so
+/// explain and local UNTIL values keep in step with the series.
+fn offset_before_gap(tz: Tz, local: NaiveDateTime, input: &str) -> Result<DateTime<Tz>, CoreError> {
+    let (_, before, _) = spring_forward_gap(tz, local, input)?;
+    let utc = local - Duration::seconds(i64::from(before));
+    Ok(tz.from_utc_datetime(&utc))
+}
+
+/// The instant just after the spring-forward gap that swallows `local`, so
+/// 02:30 on a 02:00→03:00 night is 03:00.
+fn gap_end(tz: Tz, local: NaiveDateTime, input: &str) -> Result<DateTime<Tz>, CoreError> {
+    let (at, _, _) = spring_forward_gap(tz, local, input)?;
+    DateTime::from_timestamp(at, 0)
+        .map(|utc| utc.with_timezone(&tz))
+        .ok_or_else(|| CoreError::InvalidDateTime {
+            input: input.to_string(),
+            reason: "invalid local time in timezone".to_string(),
+        })
+}
+
+fn localize(
+    tz: Tz,
+    local: NaiveDateTime,
+    input: &str,
+    policy: DstPolicy,
+) -> Result<DateTime<Tz>, CoreError> {
+    match (tz.from_local_datetime(&local), policy) {
+        (LocalResult::Single(dt), _) => Ok(dt),
+        (LocalResult::Ambiguous(earliest, _), DstPolicy::Earliest) => Ok(earliest),
+        (LocalResult::Ambiguous(_, latest), DstPolicy::Latest) => Ok(latest),
+        (LocalResult::None, DstPolicy::ShiftForward) => gap_end(tz, local, input),
+        _ => Err(CoreError::InvalidDateTime {
+            input: input.to_string(),
+            reason: "ambiguous or invalid local time in timezone".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> Tz {
+        parse_timezone("Europe/Berlin").expect("timezone")
+    }
+
+    /// A Berlin DATE-TIME spec starting 2026-03-01 10:00 with no rules or
+    /// dates, for `..spec_fixture()` in the literals below.
+    fn spec_fixture() -> RecurrenceSpec {
+        RecurrenceSpec {
+            dtstart: berlin().with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            dtstart_type: DateValueType::DateTime,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![],
+            rdates: vec![],
+            exrules: vec![],
+            exdates: vec![],
+            location: None,
+            summary: None,
+            geo: None,
+            duplicate_rdates: vec![],
+            duplicate_exdates: vec![],
+            diagnostics: SpecDiagnostics::default(),
+            overrides: vec![],
+            uid: None,
+            uid_synthesized: false,
+            duration: None,
+            method: None,
+            floating: false,
+        }
+    }
+
+    #[test]
+    fn parses_iso_datetime() {
+        let tz = berlin();
+        let (dt, kind) = parse_iso_datetime("2026-03-01T10:00:00", &tz).expect("datetime");
+        assert_eq!(kind, DateValueType::DateTime);
+        assert_eq!(
+            dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "2026-03-01T10:00:00"
+        );
+    }
+
+    #[test]
+    fn expands_weekly_rule() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".to_string()],
+            ..spec_fixture()
+        };
+
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        assert_eq!(occ.len(), 4);
+        assert_eq!(occ[0].start_local, "2026-03-02T10:00:00");
+        assert_eq!(occ[1].start_local, "2026-03-04T10:00:00");
+    }
+
+    #[test]
+    fn lint_until_type_mismatch() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;UNTIL=20260110".to_string()],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, false, false);
+        assert_eq!(findings.errors.len(), 1);
+        assert_eq!(findings.errors[0].code, "E001");
+        assert_eq!(findings.errors[0].rule_index, Some(0));
+        assert_eq!(findings.errors[0].span, Some((11, 25)));
+    }
+
+    #[test]
+    fn rule_part_spans_track_byte_offsets() {
+        let rule = "FREQ=WEEKLY; BYSETPOS=1 ;COUNT=3";
+        let parts = parse_rule_parts(rule);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1].key, "BYSETPOS");
+        let (start, end) = parts[1].span;
+        assert_eq!(&rule[start..end], "BYSETPOS=1");
+        let (start, end) = parts[2].span;
+        assert_eq!(&rule[start..end], "COUNT=3");
+    }
+
+    #[test]
+    fn count_at_least_stops_early() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 4, 1, 0, 0, 0).unwrap();
+        let end = tz.with_ymd_and_hms(2026, 4, 30, 23, 59, 59).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=HOURLY".to_string()],
+            ..spec_fixture()
+        };
+
+        let window = Window::from_between(dtstart, end).expect("window");
+        let full = count_occurrences(&spec, &window, CountOptions::default(), 1000).expect("count");
+        assert_eq!(full.count, 720);
+        assert_eq!(full.at_least, None);
+
+        let early = count_occurrences(
+            &spec,
+            &window,
+            CountOptions {
+                at_least: Some(10),
+                ..CountOptions::default()
+            },
+            1000,
+        )
+        .expect("count");
+        assert_eq!(early.count, 10);
+        assert_eq!(early.at_least, Some(true));
+
+        let short = count_occurrences(
+            &spec,
+            &window,
+            CountOptions {
+                at_least: Some(1000),
+                ..CountOptions::default()
+            },
+            1000,
+        )
+        .expect("count");
+        assert_eq!(short.count, full.count);
+        assert_eq!(short.at_least, Some(false));
+
+        let err = count_occurrences(&spec, &window, CountOptions::default(), 100)
+            .expect_err("over limit");
+        assert!(matches!(err, CoreError::LimitExceeded { limit: 100 }));
+
+        let unbounded = count_occurrences(
+            &spec,
+            &Window::unbounded(),
+            CountOptions {
+                at_least: Some(5),
+                ..CountOptions::default()
+            },
+            10,
+        )
+        .expect("count");
+        assert_eq!(unbounded.count, 5);
+    }
+
+    #[test]
+    fn explains_exdate_exclusion() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+        let blocked = tz.with_ymd_and_hms(2026, 3, 3, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=5".to_string()],
+            exdates: vec![blocked],
+            ..spec_fixture()
+        };
+
+        let result = explain(&spec, blocked).expect("explain");
+        assert!(!result.included);
+        assert_eq!(result.excluded_by.as_deref(), Some("EXDATE"));
+    }
+
+    #[test]
+    fn one_period_end_follows_freq_and_interval() {
+        let tz = berlin();
+        let start = tz.with_ymd_and_hms(2026, 3, 15, 10, 0, 0).unwrap();
+        let mut spec = RecurrenceSpec {
+            dtstart: start,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![],
+            ..spec_fixture()
+        };
+
+        for (rule, expected) in [
+            ("FREQ=YEARLY", "2027-03-15T09:59:59"),
+            ("FREQ=MONTHLY;INTERVAL=2", "2026-05-15T09:59:59"),
+            ("FREQ=WEEKLY;BYDAY=MO", "2026-03-22T09:59:59"),
+            ("FREQ=DAILY", "2026-03-16T09:59:59"),
+            ("FREQ=HOURLY;INTERVAL=3", "2026-03-15T12:59:59"),
+            ("FREQ=MINUTELY;INTERVAL=15", "2026-03-15T10:14:59"),
+            ("FREQ=SECONDLY;INTERVAL=30", "2026-03-15T10:00:29"),
+        ] {
+            spec.rrules = vec![rule.to_string()];
+            let end = one_period_end(&spec, start).expect("period");
+            assert_eq!(
+                end.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                expected,
+                "{rule}"
+            );
+        }
+
+        // The monthly period crosses the spring-forward change in local time.
+        spec.rrules = vec!["FREQ=MONTHLY".to_string(), "FREQ=WEEKLY".to_string()];
+        let end = one_period_end(&spec, start).expect("period");
+        assert_eq!(
+            end.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            "2026-04-15T09:59:59+02:00"
+        );
+
+        spec.rrules.clear();
+        assert!(matches!(
+            one_period_end(&spec, start),
+            Err(CoreError::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn expand_for_each_stops_when_callback_breaks() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut seen = Vec::new();
+        let emitted = expand_for_each(&spec, &ExpandQuery::Unbounded, 1000, |occurrence| {
+            seen.push(occurrence.start_local);
+            if seen.len() == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .expect("expand");
+
+        assert_eq!(emitted, 3);
+        assert_eq!(
+            seen,
+            vec![
+                "2026-03-01T10:00:00",
+                "2026-03-02T10:00:00",
+                "2026-03-03T10:00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn local_until_is_read_in_the_dtstart_zone() {
+        let until = |value: &str| {
+            let raw = format!(
+                r#"{{"dtstart":"2026-03-01T10:00:00","rrule":["FREQ=DAILY;UNTIL={value}"]}}"#
+            );
+            let spec = parse_json_spec(&raw, Some("Europe/Berlin")).expect("spec");
+            let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+            occurrences
+                .last()
+                .map(|occ| occ.start_local.clone())
+                .unwrap_or_default()
+        };
+        // 10:00 Berlin is 09:00Z: a local UNTIL at 10:00 keeps that day, one
+        // a second earlier drops it, and the UTC spelling agrees.
+        assert_eq!(until("20260303T100000"), "2026-03-03T10:00:00");
+        assert_eq!(until("20260303T095959"), "2026-03-02T10:00:00");
+        assert_eq!(until("20260303T090000Z"), "2026-03-03T10:00:00");
+    }
+
+    #[test]
+    fn date_until_includes_the_until_day() {
+        for tz_name in ["Europe/Berlin", "America/New_York", "Pacific/Auckland"] {
+            let tz = parse_timezone(tz_name).expect("timezone");
+            let spec = RecurrenceSpec {
+                dtstart: tz.with_ymd_and_hms(2026, 1, 7, 0, 0, 0).unwrap(),
+                dtstart_type: DateValueType::Date,
+                tz: tz_name.to_string(),
+                rrules: vec!["FREQ=DAILY;UNTIL=20260110".to_string()],
+                ..spec_fixture()
+            };
+
+            let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+            let days: Vec<_> = occ.iter().map(|o| &o.start_local[..10]).collect();
+            assert_eq!(
+                days,
+                vec!["2026-01-07", "2026-01-08", "2026-01-09", "2026-01-10"],
+                "{tz_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn combined_datetimes_follow_dst_offsets() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 28, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=2".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        combine_datetimes(&mut result).expect("combine");
+        let starts: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|o| o.start.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            starts,
+            vec!["2026-03-28T10:00:00+01:00", "2026-03-29T10:00:00+02:00"]
+        );
+        assert!(result.occurrences[0].start_local.is_empty());
+    }
+
+    #[test]
+    fn after_count_is_not_capped_at_u16() {
+        let tz = parse_timezone("UTC").expect("timezone");
+        let start = tz.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart: start,
+            tz: "UTC".to_string(),
+            rrules: vec!["FREQ=MINUTELY".to_string()],
+            ..spec_fixture()
+        };
+
+        let query = ExpandQuery::After {
+            start,
+            count: 70_000,
+        };
+        let occ = expand(&spec, &query, 70_000).expect("expand");
+        assert_eq!(occ.len(), 70_000);
+        assert_eq!(occ[69_999].start_local, "2026-02-18T14:39:00");
+
+        assert!(matches!(
+            expand(&spec, &query, 69_999),
+            Err(CoreError::LimitExceeded { limit: 69_999 })
+        ));
+    }
+
+    #[test]
+    fn week_spans_show_alternating_weeks() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=6".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_week_spans(&mut result, &spec);
+        let spans = result.week_spans.expect("spans");
+        let weeks: Vec<_> = spans
+            .iter()
+            .map(|span| (span.from_week.as_str(), span.to_week.as_str()))
+            .collect();
+        assert_eq!(
+            weeks,
+            vec![
+                ("2026-W10", "2026-W10"),
+                ("2026-W12", "2026-W12"),
+                ("2026-W14", "2026-W14")
+            ]
+        );
+        assert_eq!(spans[0].weekdays, vec!["MO", "WE"]);
+
+        spec.rrules = vec!["FREQ=WEEKLY;BYDAY=TU;COUNT=3".to_string()];
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_week_spans(&mut result, &spec);
+        assert_eq!(result.week_spans.expect("spans").len(), 1);
+
+        spec.rrules = vec!["FREQ=DAILY;COUNT=3".to_string()];
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_week_spans(&mut result, &spec);
+        assert!(result.week_spans.is_none());
+        assert_eq!(result.meta.notes.len(), 1);
+    }
+
+    #[test]
+    fn rdate_property_round_trips_through_ics() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10".to_string()],
+            ..spec_fixture()
+        };
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+
+        let line = rdate_property(&spec, &occ).expect("rdate");
+        assert!(line.starts_with("RDATE;TZID=Europe/Berlin:20260302T100000,"));
+        assert!(line.split("\r\n").all(|part| part.len() <= 75));
+
+        let raw = format!(
+            "BEGIN:VEVENT\r\nDTSTART;TZID=Europe/Berlin:20260302T100000\r\n{line}END:VEVENT\r\n"
+        );
+        let parsed = parse_ics_spec(&raw, None).expect("ics parse");
+        let expected: Vec<_> = occ.iter().map(|o| o.start_utc.clone()).collect();
+        let round_tripped: Vec<_> = parsed
+            .rdates
+            .iter()
+            .map(|dt| {
+                dt.with_timezone(&Utc)
+                    .format("%Y-%m-%dT%H:%M:%SZ")
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(round_tripped, expected);
+
+        let date_spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap(),
+            dtstart_type: DateValueType::Date,
+            rrules: vec!["FREQ=DAILY;COUNT=2".to_string()],
+            ..spec
+        };
+        let occ = expand(&date_spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        assert_eq!(
+            rdate_property(&date_spec, &occ).expect("rdate"),
+            "RDATE;VALUE=DATE:20260302,20260303\r\n"
+        );
+    }
+
+    #[test]
+    fn simulate_reports_dst_gaps() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 28, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY".to_string()],
+            ..spec_fixture()
+        };
+
+        let result = simulate(&spec, spec.dtstart, 3, 100).expect("simulate");
+        let gaps: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|o| o.gap_from_previous.as_deref())
+            .collect();
+        assert_eq!(gaps, vec![None, Some("PT23H"), Some("PT24H")]);
+
+        let autumn = tz.with_ymd_and_hms(2026, 10, 24, 10, 0, 0).unwrap();
+        let result = simulate(&spec, autumn, 2, 100).expect("simulate");
+        assert_eq!(
+            result.occurrences[1].gap_from_previous.as_deref(),
+            Some("PT25H")
+        );
+        assert_eq!(format_iso_duration(Duration::seconds(5430)), "PT1H30M30S");
+    }
+
+    #[test]
+    fn flags_unbounded_secondly_rule_as_high_risk() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=SECONDLY".to_string()],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, true, true);
+        let codes: Vec<_> = findings.warnings.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, vec!["W004"]);
+        assert_eq!(findings.warnings[0].span, Some((0, 13)));
+
+        spec.rrules = vec!["FREQ=SECONDLY;BYSECOND=0".to_string()];
+        assert!(lint(&spec, true, true).warnings.is_empty());
+
+        spec.rrules = vec!["FREQ=HOURLY;INTERVAL=2".to_string()];
+        let details = lint(&spec, true, true).warnings[0].details.clone();
+        assert!(details.unwrap().contains("about 12 times a day"));
+
+        // Once a day at most is no denser than FREQ=DAILY.
+        for sparse in ["FREQ=HOURLY;INTERVAL=24", "FREQ=MINUTELY;INTERVAL=2880"] {
+            spec.rrules = vec![sparse.to_string()];
+            assert!(lint(&spec, true, true).warnings.is_empty(), "{sparse}");
+        }
+
+        spec.rrules = vec!["freq=minutely".to_string()];
+        let codes: Vec<_> = lint(&spec, true, true)
+            .warnings
+            .iter()
+            .map(|f| f.code.clone())
+            .collect();
+        assert_eq!(codes, vec!["W004"]);
+    }
+
+    #[test]
+    fn overridden_dtstart_reanchors_the_rule() {
+        let tz = berlin();
+        let template = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 1, 5, 9, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;COUNT=2".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut starts = Vec::new();
+        for (day, hour) in [(2, 10), (20, 14)] {
+            let mut spec = template.clone();
+            let dtstart = tz.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap();
+            spec.override_dtstart(dtstart, DateValueType::DateTime)
+                .expect("override");
+            let occ = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+            starts.push(occ.into_iter().map(|o| o.start_local).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            starts,
+            vec![
+                vec!["2026-03-02T10:00:00", "2026-03-09T10:00:00"],
+                vec!["2026-03-20T14:00:00", "2026-03-27T14:00:00"]
+            ]
+        );
+
+        let mut spec = template.clone();
+        spec.rrules = vec!["FREQ=DAILY;UNTIL=20260110".to_string()];
+        let later = tz.with_ymd_and_hms(2026, 1, 8, 9, 0, 0).unwrap();
+        assert!(
+            spec.override_dtstart(later, DateValueType::DateTime)
+                .is_err()
+        );
+        assert_eq!(spec.dtstart, template.dtstart);
+    }
+
+    #[test]
+    fn expansion_stops_when_time_budget_is_spent() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=SECONDLY".to_string()],
+            ..spec_fixture()
+        };
+
+        let result = expand_result_within(
+            &spec,
+            &ExpandQuery::Unbounded,
+            100_000,
+            Some(StdDuration::ZERO),
+        );
+        assert!(matches!(
+            result,
+            Err(CoreError::TimeLimitExceeded { budget_ms: 0 })
+        ));
+
+        // Candidates skipped before a far window start count against it too.
+        let far = ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2036, 1, 1, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2036, 1, 2, 0, 0, 0).unwrap(),
+            inclusive_end: true,
+            count: None,
+        };
+        let started = Instant::now();
+        let result = expand_result_within(&spec, &far, 100_000, Some(StdDuration::ZERO));
+        assert!(matches!(
+            result,
+            Err(CoreError::TimeLimitExceeded { budget_ms: 0 })
+        ));
+        assert!(started.elapsed() < StdDuration::from_secs(5));
+
+        let generous = Some(StdDuration::from_secs(60));
+        let result = expand_result_within(&spec, &ExpandQuery::Unbounded, 10, generous);
+        assert_eq!(result.expect("expand").occurrences.len(), 10);
+    }
+
+    #[test]
+    fn reports_every_exclusion_when_requested() {
+        let tz = berlin();
+        let blocked = tz.with_ymd_and_hms(2026, 3, 3, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=5".to_string()],
+            exrules: vec![
+                "FREQ=WEEKLY;BYDAY=MO".to_string(),
+                "FREQ=WEEKLY;BYDAY=TU".to_string(),
+            ],
+            exdates: vec![blocked],
+            ..spec_fixture()
+        };
+
+        let default = explain(&spec, blocked).expect("explain");
+        assert_eq!(default.excluded_by.as_deref(), Some("EXDATE"));
+        assert!(default.exclusions.is_none());
+
+        let options = ExplainOptions {
+            all_exclusions: true,
+            ..ExplainOptions::default()
+        };
+        let result = explain_with(&spec, blocked, options).expect("explain");
+        assert_eq!(result.excluded_by.as_deref(), Some("EXDATE"));
+        assert_eq!(
+            result.exclusions,
+            Some(vec![
+                Exclusion {
+                    kind: "EXDATE".to_string(),
+                    index: 0
+                },
+                Exclusion {
+                    kind: "EXRULE".to_string(),
+                    index: 1
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn finds_days_over_the_per_day_cap() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 20, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=HOURLY;COUNT=10".to_string()],
+            ..spec_fixture()
+        };
+
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        assert_eq!(
+            days_over_limit(&occ, 4),
+            vec![DayCount {
+                date: "2026-03-03".to_string(),
+                count: 6
+            }]
+        );
+        assert!(days_over_limit(&occ, 6).is_empty());
+    }
+
+    #[test]
+    fn hints_at_ordinal_byday_with_bysetpos() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=MONTHLY;BYDAY=1MO,3MO;BYSETPOS=1;COUNT=3".to_string()],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, false, false);
+        let codes: Vec<_> = findings.hints.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, vec!["H004"]);
+        assert_eq!(findings.hints[0].span, Some((27, 37)));
+
+        spec.rrules = vec!["FREQ=MONTHLY;BYDAY=MO,TU;BYSETPOS=-1;COUNT=3".to_string()];
+        assert!(lint(&spec, false, false).hints.is_empty());
+
+        assert_eq!(parse_byday_token("-1FR"), Some((Some(-1), Weekday::Fri)));
+        assert_eq!(parse_byday_token("+2TU"), Some((Some(2), Weekday::Tue)));
+        assert_eq!(parse_byday_token("SU"), Some((None, Weekday::Sun)));
+        assert_eq!(parse_byday_token("XMO"), None);
+    }
+
+    #[test]
+    fn heatmap_counts_weekly_rule_by_weekday_and_hour() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=10,15;COUNT=8".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_heatmap(&mut result, HeatmapKind::WeekdayHour);
+        let heatmap = result.heatmap.clone().expect("heatmap");
+        assert_eq!((heatmap.rows.len(), heatmap.columns.len()), (7, 24));
+        let mut expected = vec![vec![0; 24]; 7];
+        for row in [0, 2] {
+            expected[row][10] = 2;
+            expected[row][15] = 2;
+        }
+        assert_eq!(heatmap.counts, expected);
+
+        apply_heatmap(&mut result, HeatmapKind::MonthDay);
+        let heatmap = result.heatmap.expect("heatmap");
+        assert_eq!((heatmap.rows.len(), heatmap.columns.len()), (12, 31));
+        assert_eq!(heatmap.counts[2][1], 2);
+        assert_eq!(heatmap.counts[2][10], 2);
+        assert_eq!(heatmap.counts.iter().flatten().sum::<usize>(), 8);
+    }
+
+    #[test]
+    fn rejects_empty_rule_values() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;BYDAY=;COUNT=3".to_string()],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, false, false);
+        assert_eq!(findings.errors.len(), 1);
+        assert_eq!(findings.errors[0].code, "E002");
+        assert_eq!(findings.errors[0].message, "BYDAY has an empty value");
+        assert_eq!(findings.errors[0].span, Some((11, 17)));
+        let err = expand(&spec, &ExpandQuery::Unbounded, 100).expect_err("empty BYDAY");
+        assert!(err.to_string().contains("BYDAY has an empty value"));
+
+        spec.rrules = vec!["FREQ=DAILY;COUNT=".to_string()];
+        let findings = lint(&spec, false, false);
+        assert_eq!(findings.errors[0].message, "COUNT has an empty value");
+        let err = expand(&spec, &ExpandQuery::Unbounded, 100).expect_err("empty COUNT");
+        assert!(err.to_string().contains("COUNT has an empty value"));
+    }
+
+    #[test]
+    fn ics_calendar_uses_custom_prodid() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=2".to_string()],
+            uid: Some("team@example.com".to_string()),
+            ..spec_fixture()
+        };
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+
+        let ics = ics_calendar(
+            &spec,
+            &occurrences,
+            "-//Example Corp//Planner//EN",
+            "{index}-{uid}",
+        )
+        .expect("ics");
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("PRODID:-//Example Corp//Planner//EN\r\n"));
+        assert!(ics.contains("UID:2-team@example.com\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Europe/Berlin:20260303T100000\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+
+        assert!(matches!(
+            ics_calendar(&spec, &occurrences, " ", DEFAULT_UID_TEMPLATE),
+            Err(CoreError::InvalidProdid(_))
+        ));
+        assert!(matches!(
+            ics_calendar(&spec, &occurrences, "-//a\nb//EN", DEFAULT_UID_TEMPLATE),
+            Err(CoreError::InvalidProdid(_))
+        ));
+    }
+
+    #[test]
+    fn ics_calendar_emits_dtstamp_and_dtend() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 28, 23, 30, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=1".to_string()],
+            duration: Some(EventDuration::parse("PT3H").expect("duration")),
+            ..spec_fixture()
+        };
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        let ics =
+            ics_calendar(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE).expect("ics");
+        assert!(ics.contains("DTSTAMP:20260328T223000Z\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Europe/Berlin:20260328T233000\r\n"));
+        // Three exact hours across the spring-forward gap end at 03:30 CEST.
+        assert!(ics.contains("DTEND;TZID=Europe/Berlin:20260329T033000\r\n"));
+        assert!(ics.contains(
+            "BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nBEGIN:STANDARD\r\nDTSTART:20260328T233000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0100\r\nTZNAME:CET\r\nEND:STANDARD\r\n\
+             BEGIN:DAYLIGHT\r\nDTSTART:20260329T020000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nTZNAME:CEST\r\nEND:DAYLIGHT\r\nEND:VTIMEZONE\r\n"
+        ));
+
+        let floating = RecurrenceSpec {
+            tz: "UTC".to_string(),
+            dtstart: parse_timezone("UTC")
+                .expect("timezone")
+                .with_ymd_and_hms(2026, 3, 1, 10, 0, 0)
+                .unwrap(),
+            duration: None,
+            floating: true,
+            ..spec.clone()
+        };
+        let occurrences = expand(&floating, &ExpandQuery::Unbounded, 10).expect("expand");
+        let ics = ics_calendar(
+            &floating,
+            &occurrences,
+            DEFAULT_PRODID,
+            DEFAULT_UID_TEMPLATE,
+        )
+        .expect("ics");
+        assert!(ics.contains("DTSTAMP:19700101T000000Z\r\nDTSTART:20260301T100000\r\n"));
+        assert!(!ics.contains("VTIMEZONE"));
+
+        spec.dtstart = tz.with_ymd_and_hms(2026, 3, 28, 0, 0, 0).unwrap();
+        spec.dtstart_type = DateValueType::Date;
+        spec.duration = Some(EventDuration::parse("P2D").expect("duration"));
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        let ics =
+            ics_calendar(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE).expect("ics");
+        assert!(ics.contains("DTSTART;VALUE=DATE:20260328\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20260330\r\n"));
+    }
+
+    #[test]
+    fn ics_calendar_uid_template_is_stable_and_unique() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=3".to_string()],
+            ..spec_fixture()
+        };
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        let uids = |ics: &str| -> Vec<String> {
+            ics.split("\r\n")
+                .filter_map(|line| line.strip_prefix("UID:"))
+                .map(ToOwned::to_owned)
+                .collect()
+        };
+
+        let first =
+            ics_calendar(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE).expect("ics");
+        let second =
+            ics_calendar(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE).expect("ics");
+        let first_uids = uids(&first);
+        assert_eq!(first_uids, uids(&second));
+        assert_eq!(first_uids.len(), 3);
+        assert!(
+            first_uids
+                .iter()
+                .all(|uid| uid.ends_with("@rrulex") && uid.len() == 23)
+        );
+        assert_eq!(first_uids.iter().collect::<HashSet<_>>().len(), 3);
+
+        // The hash depends on the instant, not the position in the output.
+        let tail = ics_calendar(
+            &spec,
+            &occurrences[1..],
+            DEFAULT_PRODID,
+            DEFAULT_UID_TEMPLATE,
+        )
+        .expect("ics");
+        assert_eq!(uids(&tail), first_uids[1..]);
+
+        let custom = ics_calendar(
+            &spec,
+            &occurrences,
+            DEFAULT_PRODID,
+            "ev-{index}-{start_utc}",
+        )
+        .expect("ics");
+        assert_eq!(uids(&custom)[0], "ev-1-20260302T090000Z");
+
+        for template in ["standup@example.com", "{nope}", "{index", "index}", ""] {
+            assert!(matches!(
+                ics_calendar(&spec, &occurrences, DEFAULT_PRODID, template),
+                Err(CoreError::InvalidUidTemplate(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn periods_follow_weekly_interval_from_dtstart_week() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=3".to_string()],
+            rdates: vec![tz.with_ymd_and_hms(2026, 3, 5, 9, 0, 0).unwrap()],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_periods(&mut result, &spec).expect("periods");
+        let periods: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|occ| {
+                let period = occ.period.as_ref().expect("period");
+                (
+                    occ.start_local.as_str(),
+                    period.period_start.as_deref(),
+                    period.period_end.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            periods,
+            vec![
+                (
+                    "2026-03-04T10:00:00",
+                    Some("2026-03-02T00:00:00"),
+                    Some("2026-03-15T23:59:59")
+                ),
+                ("2026-03-05T09:00:00", None, None),
+                (
+                    "2026-03-16T10:00:00",
+                    Some("2026-03-16T00:00:00"),
+                    Some("2026-03-29T23:59:59")
+                ),
+                (
+                    "2026-03-18T10:00:00",
+                    Some("2026-03-16T00:00:00"),
+                    Some("2026-03-29T23:59:59")
+                ),
+            ]
+        );
+
+        let anchor = NaiveDate::from_ymd_opt(2026, 3, 15)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let local = NaiveDate::from_ymd_opt(2026, 8, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let (start, end) = rule_period("FREQ=MONTHLY;INTERVAL=3", anchor, local).expect("period");
+        assert_eq!(start.to_string(), "2026-06-01 00:00:00");
+        assert_eq!(end.to_string(), "2026-08-31 23:59:59");
+    }
+
+    #[test]
+    fn rejects_value_type_contradicting_the_value() {
+        let ics = |line: &str| {
+            format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:v@example.com\n{line}\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\nEND:VCALENDAR\n"
+            )
+        };
+
+        let err = parse_ics_spec(&ics("DTSTART;VALUE=DATE:20260301T100000"), Some("UTC"))
+            .expect_err("date with time");
+        assert!(
+            err.to_string()
+                .contains("VALUE=DATE but value contains a time component")
+        );
+
+        let err = parse_ics_spec(
+            &ics("DTSTART;TZID=Europe/Berlin;VALUE=DATE-TIME:20260301"),
+            None,
+        )
+        .expect_err("datetime without time");
+        assert!(
+            err.to_string()
+                .contains("VALUE=DATE-TIME but value has no time component")
+        );
+
+        let err = parse_ics_spec(
+            &ics("DTSTART;VALUE=DATE:20260301\nEXDATE;VALUE=DATE:20260302,20260303T100000"),
+            Some("UTC"),
+        )
+        .expect_err("exdate with time");
+        assert!(err.to_string().contains("EXDATE has VALUE=DATE"));
+    }
+
+    #[test]
+    fn meta_reports_when_the_limit_is_reached() {
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-01T10:00:00","rrule":["FREQ=DAILY;COUNT=3"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+
+        let exact = expand_result(&spec, &ExpandQuery::Unbounded, 3).expect("expand");
+        assert_eq!(exact.meta.returned, 3);
+        assert!(exact.meta.limit_reached);
+
+        let roomy = expand_result(&spec, &ExpandQuery::Unbounded, 4).expect("expand");
+        assert_eq!(roomy.meta.returned, 3);
+        assert!(!roomy.meta.limit_reached);
+    }
+
+    #[test]
+    fn between_with_count_keeps_the_first_occurrences() {
+        let tz = berlin();
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-01T10:00:00","rrule":["FREQ=HOURLY"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let window = |count| ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 12, 31, 0, 0, 0).unwrap(),
+            inclusive_end: true,
+            count,
+        };
+
+        let first = expand(&spec, &window(Some(2)), 100).expect("capped by count");
+        let starts: Vec<&str> = first.iter().map(|occ| occ.start_local.as_str()).collect();
+        assert_eq!(starts, vec!["2026-03-02T00:00:00", "2026-03-02T01:00:00"]);
+
+        let err = expand(&spec, &window(None), 100).expect_err("window over the limit");
+        assert!(matches!(err, CoreError::LimitExceeded { limit: 100 }));
+    }
+
+    #[test]
+    fn excluded_dates_do_not_consume_the_limit() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=HOURLY".to_string()],
+            exrules: vec![
+                "FREQ=HOURLY;BYHOUR=0,1,2,3,4,5,6,7,8,9,11,12,13,14,15,16,17,18,19,20,21,22,23"
+                    .to_string(),
+            ],
+            ..spec_fixture()
+        };
+        let query = ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 12, 31, 23, 59, 59).unwrap(),
+            inclusive_end: true,
+            count: None,
+        };
+
+        // ~8760 hourly candidates, of which only the 365 at 10:00 survive.
+        let occurrences = expand(&spec, &query, 400).expect("within limit");
+        assert_eq!(occurrences.len(), 365);
+        assert!(matches!(
+            expand(&spec, &query, 364),
+            Err(CoreError::LimitExceeded { limit: 364 })
+        ));
+    }
+
+    #[test]
+    fn why_not_names_the_failed_condition() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO;BYMONTH=3,4;UNTIL=20260430T000000Z".to_string(),
+            ],
+            ..spec_fixture()
+        };
+        let options = ExplainOptions {
+            why_not: true,
+            ..ExplainOptions::default()
+        };
+        let failed = |spec: &RecurrenceSpec, at: DateTime<Tz>| {
+            explain_with(spec, at, options)
+                .expect("explain")
+                .why_not
+                .expect("why_not")
+                .failed
+        };
+
+        let at = |m, d, h, min| tz.with_ymd_and_hms(2026, m, d, h, min, 0).unwrap();
+        assert_eq!(failed(&spec, at(3, 3, 10, 0)), vec!["byday"]);
+        assert_eq!(failed(&spec, at(3, 9, 10, 0)), vec!["freq_cadence"]);
+        assert_eq!(failed(&spec, at(3, 2, 10, 30)), vec!["byminute"]);
+        assert_eq!(failed(&spec, at(3, 2, 11, 0)), vec!["byhour"]);
+        assert_eq!(
+            failed(&spec, at(5, 11, 10, 0)),
+            vec!["within_until", "bymonth"]
+        );
+        assert_eq!(
+            failed(&spec, at(2, 16, 10, 0)),
+            vec!["after_dtstart", "bymonth"]
+        );
+
+        spec.rrules = vec!["FREQ=MONTHLY;BYMONTHDAY=15".to_string()];
+        assert_eq!(failed(&spec, at(3, 16, 10, 0)), vec!["bymonthday"]);
+
+        // The closest of several rules is reported.
+        spec.rrules = vec![
+            "FREQ=MONTHLY;BYDAY=-1FR".to_string(),
+            "FREQ=DAILY;BYHOUR=9".to_string(),
+        ];
+        let why_not = explain_with(&spec, at(3, 20, 10, 0), options)
+            .expect("explain")
+            .why_not
+            .expect("why_not");
+        assert_eq!(
+            (why_not.rule_index, why_not.failed),
+            (0, vec!["byday".to_string()])
+        );
+        assert!(
+            explain_with(&spec, at(3, 27, 10, 0), options)
+                .expect("explain")
+                .why_not
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn spec_json_round_trips() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:rt@example.com\nDTSTART;TZID=Europe/Berlin:20261024T023000\nRRULE:FREQ=WEEKLY;COUNT=3\nRRULE:FREQ=DAILY;COUNT=2\nRDATE;TZID=America/New_York:20261101T090000\nRDATE;TZID=Europe/Berlin:20261030T090000\nEXDATE:20261025T003000Z\nEND:VEVENT\nEND:VCALENDAR\n";
+        let spec = parse_ics_spec(raw, None).expect("ics");
+
+        let json = spec_to_json(&spec);
+        assert_eq!(json["rrule"][0], "FREQ=DAILY;COUNT=2");
+        assert_eq!(json["rdate"][0], "2026-10-30T09:00:00+01:00");
+        assert_eq!(json["rdate"][1], "2026-11-01T15:00:00+01:00");
+
+        let reparsed = parse_json_spec(&json.to_string(), None).expect("json");
+        assert_eq!(spec_to_json(&reparsed), json);
+        assert_eq!(reparsed.dtstart, spec.dtstart);
+        assert_eq!(
+            expand(&reparsed, &ExpandQuery::Unbounded, 100)
+                .expect("expand")
+                .len(),
+            expand(&spec, &ExpandQuery::Unbounded, 100)
+                .expect("expand")
+                .len()
+        );
+    }
+
+    #[test]
+    fn explain_matches_rdate_from_another_zone_by_instant() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:zones@example.com\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=WEEKLY;COUNT=2\nRDATE;TZID=America/New_York:20260310T090000,20260311T200000\nEXDATE;TZID=America/New_York:20260308T050000\nEND:VEVENT\nEND:VCALENDAR\n";
+        let spec = parse_ics_spec(raw, None).expect("ics");
+
+        // 09:00 EDT is 14:00 in Berlin (the US switched to DST first).
+        let result = explain_local(&spec, "2026-03-10T14:00:00").expect("explain");
+        assert!(result.included);
+        assert_eq!(result.generated_by, Some(OccurrenceSource::Rdate));
+        assert_eq!(result.generated_rule_index, Some(0));
+
+        let new_york: Tz = "America/New_York".parse::<ChronoTz>().unwrap().into();
+        let at = new_york.with_ymd_and_hms(2026, 3, 11, 20, 0, 0).unwrap();
+        let result = explain(&spec, at).expect("explain");
+        assert_eq!(result.at, "2026-03-12T01:00:00");
+        assert_eq!(result.generated_rule_index, Some(1));
+
+        // The day view buckets by the spec's local date, not the RDATE's.
+        let day = explain_local(&spec, "2026-03-12").expect("explain");
+        assert_eq!(
+            day.day_matches,
+            Some(vec!["2026-03-12T01:00:00".to_string()])
+        );
+
+        let result = explain_local(&spec, "2026-03-08T10:00:00").expect("explain");
+        assert_eq!(result.excluded_by.as_deref(), Some("EXDATE"));
+    }
+
+    #[test]
+    fn expands_rdate_only_spec_in_window() {
+        let tz = berlin();
+        let at = |d, h| tz.with_ymd_and_hms(2026, 3, d, h, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart: at(5, 10),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![],
+            rdates: vec![at(9, 8), at(1, 10), at(7, 9), at(6, 8), at(8, 8)],
+            exrules: vec!["FREQ=DAILY;BYHOUR=8;BYDAY=SU".to_string()],
+            exdates: vec![at(6, 8)],
+            ..spec_fixture()
+        };
+        let query = ExpandQuery::Between {
+            start: at(2, 0),
+            end: at(9, 12),
+            inclusive_end: true,
+            count: None,
+        };
+
+        let occurrences = expand(&spec, &query, 100).expect("expand");
+        let got: Vec<_> = occurrences
+            .iter()
+            .map(|occ| (occ.start_local.as_str(), occ.rule_index))
+            .collect();
+        // 03-06 is an EXDATE and 03-08 (a Sunday) matches the EXRULE.
+        assert_eq!(
+            got,
+            vec![("2026-03-07T09:00:00", 2), ("2026-03-09T08:00:00", 0)]
+        );
+        assert!(
+            occurrences
+                .iter()
+                .all(|occ| occ.source == OccurrenceSource::Rdate)
+        );
+        assert_eq!(
+            count_occurrences(&spec, &query.window(), CountOptions::default(), 1000)
+                .expect("count")
+                .count,
+            2
+        );
+    }
+
+    #[test]
+    fn abbreviations_change_across_dst() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 28, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=2".to_string()],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_abbreviations(&mut result).expect("abbrev");
+        let abbrevs: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|occ| occ.abbrev.as_deref())
+            .collect();
+        assert_eq!(abbrevs, vec![Some("CET"), Some("CEST")]);
+    }
+
+    #[test]
+    fn meta_reports_whether_the_series_is_finite() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=3".to_string()],
+            rdates: vec![tz.with_ymd_and_hms(2026, 4, 1, 10, 0, 0).unwrap()],
+            ..spec_fixture()
+        };
+        let result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert!(result.meta.is_finite);
+
+        spec.rrules.push("FREQ=WEEKLY".to_string());
+        let result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert!(!result.meta.is_finite);
+
+        spec.rrules.clear();
+        assert!(is_finite(&spec));
+    }
+
+    #[test]
+    fn samples_even_and_seeded_random() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=10".to_string()],
+            ..spec_fixture()
+        };
+        let full = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        let days = |result: &ExpandResult| {
+            result
+                .occurrences
+                .iter()
+                .map(|occ| occ.start_local[8..10].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut even = full.clone();
+        apply_sample(&mut even, 4, SampleMode::Even);
+        assert_eq!(days(&even), vec!["01", "04", "07", "10"]);
+        let meta = even.meta.sample.expect("sample");
+        assert_eq!((meta.total, meta.size, meta.seed), (10, 4, None));
+
+        let mut tail = full.clone();
+        apply_sample(&mut tail, 2, SampleMode::Tail);
+        assert_eq!(days(&tail), vec!["09", "10"]);
+
+        let sample = |seed| {
+            let mut result = full.clone();
+            apply_sample(&mut result, 3, SampleMode::Random { seed });
+            days(&result)
+        };
+        let first = sample(42);
+        assert_eq!(first.len(), 3);
+        assert!(first.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sample(42), first);
+        assert_ne!(sample(7), first);
+
+        let mut all = full.clone();
+        apply_sample(&mut all, 50, SampleMode::Even);
+        assert_eq!(all.occurrences.len(), 10);
+    }
+
+    #[test]
+    fn capabilities_match_validation() {
+        let dtstart = berlin().with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        let caps = capabilities();
+        for freq in &caps.freq {
+            let rule = format!("FREQ={freq};COUNT=1");
+            assert!(parse_validated_rule(&rule, dtstart, DateValueType::DateTime).is_ok());
+        }
+        for key in &caps.unsupported_rule_parts {
+            let rule = format!("FREQ=DAILY;COUNT=1;{key}=GREGORIAN");
+            assert!(parse_validated_rule(&rule, dtstart, DateValueType::DateTime).is_err());
+        }
+        assert!(caps.rule_parts.contains(&"BYSETPOS"));
+    }
+
+    #[test]
+    fn describes_rules_in_english() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20260110T225959Z".to_string(),
+                "FREQ=WEEKLY;COUNT=1".to_string(),
+                "FREQ=MONTHLY;BYDAY=FR;BYSETPOS=-1;COUNT=3".to_string(),
+                "FREQ=YEARLY;BYMONTH=3,10;BYDAY=-1SU".to_string(),
+                "FREQ=MONTHLY;BYMONTHDAY=1,-1".to_string(),
+                "FREQ=DAILY;BYHOUR=9,17".to_string(),
+                "FREQ=FORTNIGHTLY".to_string(),
+            ],
+            ..spec_fixture()
+        };
+
+        assert_eq!(
+            describe(&spec),
+            vec![
+                "Every 2 weeks on Monday and Wednesday until 10 Jan 2026",
+                "Every week for 1 occurrence",
+                "Every month on the last Friday for 3 occurrences",
+                "Every year in March and October on the last Sunday",
+                "Every month on the 1st and last days of the month",
+                "Every day (BYHOUR=9,17)",
+                "FREQ=FORTNIGHTLY",
+            ]
+        );
+    }
+
+    #[test]
+    fn describes_setpos_over_several_days() {
+        assert_eq!(
+            describe_rule("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=1,-2", berlin()),
+            "Every month on the first and second-to-last of Monday, Tuesday, Wednesday, \
+             Thursday and Friday"
+        );
+        assert_eq!(
+            describe_rule("FREQ=MONTHLY;BYMONTHDAY=13;BYDAY=FR", berlin()),
+            "Every month on Friday falling on the 13th day of the month"
+        );
+        assert_eq!(
+            describe_rule("FREQ=YEARLY;BYMONTH=1;BYMONTHDAY=1,2;BYSETPOS=2", berlin()),
+            "Every year in January on the 1st and 2nd days of the month, keeping the second match per year"
+        );
+    }
+
+    #[test]
+    fn expands_rrule_shorthand() {
+        let cases = [
+            ("daily", Some("FREQ=DAILY")),
+            ("Weekly", Some("FREQ=WEEKLY")),
+            ("weekly mon,wed", Some("FREQ=WEEKLY;BYDAY=MO,WE")),
+            ("weekly Tuesday, thu", Some("FREQ=WEEKLY;BYDAY=TU,TH")),
+            ("monthly fr", Some("FREQ=MONTHLY;BYDAY=FR")),
+            ("hourly", Some("FREQ=HOURLY")),
+            ("weekly s", None),
+            ("weekly funday", None),
+            ("fortnightly", None),
+            ("FREQ=DAILY;COUNT=3", None),
+            ("", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_rrule_shorthand(input).as_deref(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn warns_about_exdate_matching_no_occurrence() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=5".to_string()],
+            exdates: vec![
+                tz.with_ymd_and_hms(2026, 3, 3, 10, 0, 30).unwrap(),
+                tz.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap(),
+                tz.with_ymd_and_hms(2026, 6, 1, 10, 0, 0).unwrap(),
+            ],
+            ..spec_fixture()
+        };
+
+        let result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        assert_eq!(result.occurrences.len(), 4);
+        assert_eq!(
+            result.meta.warnings,
+            vec![
+                "EXDATE 2026-03-03T10:00:30 matched no occurrence (nearest: 2026-03-03T10:00:00)",
+                "EXDATE 2026-06-01T10:00:00 matched no occurrence",
+            ]
+        );
+
+        let march = ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 3, 31, 23, 59, 59).unwrap(),
+            inclusive_end: true,
+            count: None,
+        };
+        let result = expand_result(&spec, &march, 100).expect("expand");
+        assert_eq!(result.meta.warnings.len(), 1);
+    }
+
+    #[test]
+    fn parses_event_durations() {
+        let parse = |value: &str| EventDuration::parse(value).map(|d| (d.days, d.seconds));
+        assert_eq!(parse("PT1H30M").unwrap(), (0, 5400));
+        assert_eq!(parse("P1D").unwrap(), (1, 0));
+        assert_eq!(parse("P2W").unwrap(), (14, 0));
+        assert_eq!(parse("+P1DT12H").unwrap(), (1, 43200));
+        assert_eq!(parse("pt45s").unwrap(), (0, 45));
+        for invalid in [
+            "",
+            "P",
+            "PT",
+            "1H",
+            "P1M",
+            "P1Y",
+            "-PT1H",
+            "PT1X",
+            "PT1H30",
+            "PT9999999999999999H",
+            "P9999999999999999999W",
+        ] {
+            assert!(
+                matches!(parse(invalid), Err(CoreError::InvalidDuration { .. })),
+                "{invalid}"
+            );
+        }
+        let shown = |value: &str| EventDuration::parse(value).unwrap().to_string();
+        assert_eq!(shown("PT1H30M"), "PT1H30M");
+        assert_eq!(shown("P1W"), "P7D");
+        assert_eq!(shown("P1DT0H"), "P1D");
+        assert_eq!(shown("PT0S"), "PT0S");
+
+        let exact = |value: &str| EventDuration::parse(value).unwrap().to_exact();
+        assert_eq!(exact("P1DT1H").unwrap(), Duration::hours(25));
+        assert!(matches!(
+            exact("P99999999999999D"),
+            Err(CoreError::InvalidDuration { .. })
+        ));
+    }
+
+    #[test]
+    fn nominal_and_exact_durations_differ_across_dst() {
+        let tz = berlin();
+        // 2026-03-29 has only 23 hours in Europe/Berlin.
+        let start = tz.with_ymd_and_hms(2026, 3, 28, 10, 0, 0).unwrap();
+        let nominal = EventDuration::parse("P1D").unwrap().end_of(start).unwrap();
+        let exact = EventDuration::parse("PT24H")
+            .unwrap()
+            .end_of(start)
+            .unwrap();
+        assert_eq!(
+            nominal.format("%Y-%m-%dT%H:%M").to_string(),
+            "2026-03-29T10:00"
+        );
+        assert_eq!(
+            exact.format("%Y-%m-%dT%H:%M").to_string(),
+            "2026-03-29T11:00"
+        );
+
+        // Landing in the spring-forward gap moves past it.
+        let before_gap = tz.with_ymd_and_hms(2026, 3, 28, 2, 30, 0).unwrap();
+        let end = EventDuration::parse("P1D")
+            .unwrap()
+            .end_of(before_gap)
+            .unwrap();
+        assert_eq!(
+            end.format("%Y-%m-%dT%H:%M%:z").to_string(),
+            "2026-03-29T03:30+02:00"
+        );
+
+        let far = EventDuration::parse("P99999999999999D").unwrap();
+        assert!(matches!(
+            far.end_of(start),
+            Err(CoreError::InvalidDuration { .. })
+        ));
+    }
+
+    #[test]
+    fn ics_dtend_and_duration_set_occurrence_ends() {
+        let with = |extra: &str| {
+            format!(
+                "BEGIN:VEVENT\nUID:a@example.com\nDTSTART;TZID=Europe/Berlin:20260328T100000\n{extra}RRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\n"
+            )
+        };
+
+        let spec = parse_ics_spec(&with(""), None).expect("ics");
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(occ[0].end_local, None);
+        assert_eq!(occ[0].end_utc, None);
+
+        let spec =
+            parse_ics_spec(&with("DTEND;TZID=Europe/Berlin:20260328T113000\n"), None).expect("ics");
+        assert_eq!(
+            spec.duration,
+            Some(EventDuration {
+                days: 0,
+                seconds: 5400
+            })
+        );
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(occ[1].end_local.as_deref(), Some("2026-03-29T11:30:00"));
+        assert_eq!(occ[1].end_utc.as_deref(), Some("2026-03-29T09:30:00Z"));
+
+        let spec = parse_ics_spec(&with("DURATION:P1D\n"), None).expect("ics");
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(occ[0].end_local.as_deref(), Some("2026-03-29T10:00:00"));
+        assert_eq!(occ[0].end_utc.as_deref(), Some("2026-03-29T08:00:00Z"));
+
+        assert!(matches!(
+            parse_ics_spec(&with("DTEND;TZID=Europe/Berlin:20260328T090000\n"), None),
+            Err(CoreError::InvalidIcs(_))
+        ));
+        assert!(matches!(
+            parse_ics_spec(
+                &with("DURATION:PT1H\nDTEND;TZID=Europe/Berlin:20260328T110000\n"),
+                None
+            ),
+            Err(CoreError::InvalidIcs(_))
+        ));
+    }
+
+    #[test]
+    fn lint_warns_weekly_interval_byday_without_wkst() {
+        let tz = parse_timezone("America/New_York").expect("timezone");
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(1997, 8, 5, 9, 0, 0).unwrap(),
+            tz: "America/New_York".to_string(),
+            rrules: vec![
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU".to_string(),
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU".to_string(),
+                "FREQ=WEEKLY;COUNT=4;BYDAY=TU,SU".to_string(),
+            ],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, false, false);
+        let w005: Vec<_> = findings
+            .warnings
+            .iter()
+            .filter(|f| f.code == "W005")
+            .collect();
+        assert_eq!(w005.len(), 1);
+        assert_eq!(w005[0].rule_index, Some(0));
+        assert_eq!(w005[0].span, Some((12, 22)));
+        let details = w005[0].details.as_deref().unwrap_or_default();
+        assert!(
+            details.contains(
+                "WKST=MO: Tue 1997-08-05, Sun 1997-08-10, Tue 1997-08-19, Sun 1997-08-24"
+            )
+        );
+        assert!(
+            details.contains(
+                "WKST=SU: Tue 1997-08-05, Sun 1997-08-17, Tue 1997-08-19, Sun 1997-08-31"
+            )
+        );
+
+        let lowercase = RecurrenceSpec {
+            rrules: vec!["freq=weekly;interval=2;count=4;byday=tu,su".to_string()],
+            ..spec.clone()
+        };
+        let findings = lint(&lowercase, false, false);
+        assert!(findings.warnings.iter().any(|f| f.code == "W005"));
+    }
+
+    #[test]
+    fn finds_next_and_previous_occurrence() {
+        let tz = berlin();
+        let at = |d, h, m| tz.with_ymd_and_hms(2026, 3, d, h, m, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart: at(2, 10, 0),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=5".to_string()],
+            rdates: vec![at(20, 8, 0)],
+            exdates: vec![at(4, 10, 0)],
+            ..spec_fixture()
+        };
+        let next = |pivot| {
+            next_occurrence(&spec, pivot)
+                .unwrap()
+                .map(|occ| occ.start_local)
+        };
+        let prev = |pivot| {
+            previous_occurrence(&spec, pivot)
+                .unwrap()
+                .map(|occ| occ.start_local)
+        };
+
+        // Strictly after/before the pivot, skipping the EXDATE.
+        assert_eq!(next(at(3, 10, 0)).as_deref(), Some("2026-03-05T10:00:00"));
+        assert_eq!(prev(at(5, 10, 0)).as_deref(), Some("2026-03-03T10:00:00"));
+        assert_eq!(next(at(1, 0, 0)).as_deref(), Some("2026-03-02T10:00:00"));
+        // The RDATE outlives the RRULE's COUNT.
+        assert_eq!(next(at(6, 10, 0)).as_deref(), Some("2026-03-20T08:00:00"));
+        assert_eq!(prev(at(31, 0, 0)).as_deref(), Some("2026-03-20T08:00:00"));
+        // Past either end of the series.
+        assert_eq!(next(at(20, 8, 0)), None);
+        assert_eq!(prev(at(2, 10, 0)), None);
+
+        let mut infinite = spec.clone();
+        infinite.rrules = vec!["FREQ=WEEKLY".to_string()];
+        let far = tz.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap();
+        let occ = next_occurrence(&infinite, far)
+            .unwrap()
+            .expect("unbounded series");
+        assert_eq!(occ.start_local, "2030-01-07T10:00:00");
+
+        let spent = Some(StdDuration::ZERO);
+        assert!(matches!(
+            previous_occurrence_within(&infinite, far, spent),
+            Err(CoreError::TimeLimitExceeded { budget_ms: 0 })
+        ));
+        assert!(matches!(
+            next_occurrence_within(&infinite, far, spent),
+            Err(CoreError::TimeLimitExceeded { budget_ms: 0 })
+        ));
+    }
+
+    #[test]
+    fn expands_floating_dtstart_without_zone() {
+        let raw = "BEGIN:VEVENT\nDTSTART:20260327T090000\nRRULE:FREQ=DAILY;COUNT=3\nEXDATE:20260328T090000\nEND:VEVENT\n";
+        assert!(parse_ics_spec_with(raw, None, IcsOptions::default()).is_err());
+
+        let options = IcsOptions {
+            floating: true,
+            ..IcsOptions::default()
+        };
+        let spec = parse_ics_spec_with(raw, None, options).expect("floating spec");
+        assert!(spec.floating);
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        apply_floating(&mut result, &spec);
+        assert_eq!(result.meta.tz, None);
+        let got: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|occ| {
+                (
+                    occ.start_local.as_str(),
+                    occ.start_utc.as_str(),
+                    occ.tz.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("2026-03-27T09:00:00", "", None),
+                ("2026-03-29T09:00:00", "", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn offset_datetimes_keep_their_instant() {
+        let tz = berlin();
+        let (with_offset, kind) =
+            parse_iso_datetime("2026-03-10T10:00:00+02:00", &tz).expect("offset");
+        let (utc, _) = parse_iso_datetime("2026-03-10T08:00:00Z", &tz).expect("utc");
+        assert_eq!(kind, DateValueType::DateTime);
+        assert_eq!(with_offset, utc);
+        assert_eq!(with_offset.timezone(), tz);
+
+        let spec_with = |rdate: DateTime<Tz>| RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![],
+            rdates: vec![rdate],
+            ..spec_fixture()
+        };
+        let start_utc = |rdate| {
+            expand(&spec_with(rdate), &ExpandQuery::Unbounded, 10).expect("expand")[0]
+                .start_utc
+                .clone()
+        };
+        assert_eq!(start_utc(with_offset), "2026-03-10T08:00:00Z");
+        assert_eq!(start_utc(with_offset), start_utc(utc));
+    }
+
+    #[test]
+    fn counts_occurrences_by_source() {
+        let tz = berlin();
+        let at = |day, hour| tz.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart: at(2, 10),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=WEEKLY;BYDAY=MO".to_string()],
+            rdates: vec![at(4, 15), at(11, 15), at(30, 15)],
+            ..spec_fixture()
+        };
+        let window = Window::from_between(at(1, 0), at(20, 0)).expect("window");
+        let options = CountOptions {
+            by_source: true,
+            ..CountOptions::default()
+        };
+
+        let result = count_occurrences(&spec, &window, options, 1000).expect("count");
+        assert_eq!(result.count, 5);
+        assert_eq!(result.by_source, Some(SourceCounts { rrule: 3, rdate: 2 }));
+        assert_eq!(
+            count_occurrences(&spec, &window, CountOptions::default(), 1000)
+                .expect("count")
+                .by_source,
+            None
+        );
+
+        // A moved RDATE instance still counts as an RDATE, as expand says.
+        let moved = RecurrenceSpec {
+            overrides: vec![InstanceOverride {
+                recurrence_id: at(4, 15),
+                start: at(5, 9),
+                sequence: 1,
+            }],
+            ..spec
+        };
+        let result = count_occurrences(&moved, &window, options, 1000).expect("count");
+        assert_eq!(result.by_source, Some(SourceCounts { rrule: 3, rdate: 2 }));
+        let query = ExpandQuery::Between {
+            start: at(1, 0),
+            end: at(20, 0),
+            inclusive_end: true,
+            count: None,
+        };
+        let occurrences = expand(&moved, &query, 1000).expect("expand");
+        let rdates = occurrences
+            .iter()
+            .filter(|occ| occ.source == OccurrenceSource::Rdate)
+            .count();
+        assert_eq!(rdates, 2);
+    }
+
+    #[test]
+    fn rejects_dates_beyond_year_9999() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=YEARLY;UNTIL=100001231T000000Z".to_string()],
+            ..spec_fixture()
+        };
+        let err = expand(&spec, &ExpandQuery::Unbounded, 100).expect_err("UNTIL past 9999");
+        assert!(err.to_string().contains("UNTIL year 10000 is beyond 9999"));
+        let findings = lint(&spec, false, false);
+        assert_eq!(findings.errors[0].code, "E003");
+        assert_eq!(findings.errors[0].span, Some((12, 35)));
+
+        let err = parse_iso_datetime("10000-01-01T00:00:00", &tz).expect_err("window end");
+        assert!(err.to_string().contains("year is beyond 9999"));
+
+        spec.dtstart = tz.with_ymd_and_hms(9999, 12, 30, 10, 0, 0).unwrap();
+        spec.rrules = vec!["FREQ=DAILY".to_string()];
+        let query = ExpandQuery::After {
+            start: spec.dtstart,
+            count: 5,
+        };
+        let occurrences = expand(&spec, &query, 100).expect("expand");
+        assert_eq!(occurrences.len(), 2);
+        assert_eq!(occurrences[1].start_local, "9999-12-31T10:00:00");
+
+        let new_york = parse_timezone("America/New_York").expect("timezone");
+        let err = parse_iso_datetime("9999-12-31T20:00:00", &new_york).expect_err("UTC 10000");
+        assert!(err.to_string().contains("year is beyond 9999 in UTC"));
+
+        spec.dtstart = new_york.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+        spec.tz = "America/New_York".to_string();
+        spec.rrules = vec!["FREQ=YEARLY;UNTIL=99991231T235959".to_string()];
+        let err = expand(&spec, &ExpandQuery::Unbounded, 100).expect_err("local UNTIL");
+        assert!(err.to_string().contains("UNTIL year 10000 is beyond 9999"));
+        assert_eq!(lint(&spec, false, false).errors[0].code, "E003");
+    }
+
+    #[test]
+    fn canonicalizes_rule_part_order() {
+        assert_eq!(
+            canonicalize_rule(
+                "until=20261231T000000z;byday=mo,we;X-NAME=Team;freq=weekly;WKST=su;interval=2"
+            )
+            .expect("canonical"),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;WKST=SU;UNTIL=20261231T000000Z;X-NAME=Team"
+        );
+        assert_eq!(
+            canonicalize_rule("FREQ=DAILY;COUNT=3;").expect("canonical"),
+            "FREQ=DAILY;COUNT=3"
+        );
+
+        let err = canonicalize_rule("FREQ=DAILY;count=2;COUNT=3").expect_err("duplicate");
+        assert!(err.to_string().contains("COUNT appears more than once"));
+        let err = canonicalize_rule("FREQ=DAILY;BYDAY").expect_err("no value");
+        assert!(err.to_string().contains("part 'BYDAY' has no '='"));
+    }
+
+    #[test]
+    fn lint_checks_ordinal_byday_against_freq() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![
+                "FREQ=WEEKLY;BYDAY=MO,2TU,-1FR".to_string(),
+                "FREQ=YEARLY;BYDAY=1MO".to_string(),
+                "FREQ=YEARLY;BYMONTH=3;BYDAY=1MO".to_string(),
+                "FREQ=MONTHLY;BYDAY=2TU".to_string(),
+            ],
+            ..spec_fixture()
+        };
+
+        let findings = lint(&spec, true, true);
+        let codes = |findings: &[Finding], code: &str| -> Vec<(Option<usize>, Option<String>)> {
+            findings
+                .iter()
+                .filter(|f| f.code == code)
+                .map(|f| (f.rule_index, f.details.clone()))
+                .collect()
+        };
+        let e004 = codes(&findings.errors, "E004");
+        assert_eq!(e004.len(), 1);
+        assert_eq!(e004[0].0, Some(0));
+        assert!(
+            e004[0]
+                .1
+                .as_deref()
+                .unwrap()
+                .starts_with("2TU,-1FR has a numeric prefix")
+        );
+        assert_eq!(findings.errors[0].span, Some((12, 29)));
+
+        let w006 = codes(&findings.warnings, "W006");
+        assert_eq!(w006.len(), 1);
+        assert_eq!(w006[0].0, Some(1));
+        assert!(
+            w006[0]
+                .1
+                .as_deref()
+                .unwrap()
+                .starts_with("1MO counts weekdays")
+        );
+
+        let lowercase = RecurrenceSpec {
+            rrules: vec![
+                "FREQ=monthly;BYDAY=1MO;COUNT=2".to_string(),
+                "freq=yearly;byday=1mo".to_string(),
+                "freq=weekly;byday=2tu".to_string(),
+            ],
+            ..spec.clone()
+        };
+        let findings = lint(&lowercase, true, true);
+        let e004 = codes(&findings.errors, "E004");
+        assert_eq!(e004.len(), 1);
+        assert_eq!(e004[0].0, Some(2));
+        assert_eq!(
+            findings.errors[0].message,
+            "Ordinal BYDAY is not allowed with FREQ=WEEKLY"
+        );
+        assert_eq!(codes(&findings.warnings, "W006")[0].0, Some(1));
+    }
+
+    #[test]
+    fn maps_occurrences_by_instant() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;COUNT=2".to_string()],
+            ..spec_fixture()
+        };
+        let mut occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        let mut twin = occurrences[1].clone();
+        twin.rule_index = 1;
+        occurrences.push(twin);
+
+        let map = occurrence_map(&occurrences).expect("map");
+        let first = &map["2026-03-02T09:00:00Z"];
+        assert_eq!(first["start_local"], "2026-03-02T10:00:00");
+        assert!(first.get("start_utc").is_none());
+        let shared = map["2026-03-03T09:00:00Z"]
+            .as_array()
+            .expect("collision array");
+        assert_eq!(shared.len(), 2);
+        assert_eq!(shared[1]["rule_index"], 1);
+    }
+
+    #[test]
+    fn occurrence_iter_streams_without_a_limit() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY".to_string()],
+            rdates: vec![tz.with_ymd_and_hms(2026, 3, 2, 15, 0, 0).unwrap()],
+            exdates: vec![tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap()],
+            ..spec_fixture()
+        };
+
+        let window = Window {
+            start: None,
+            end: None,
+        };
+        let seen: Vec<_> = OccurrenceIter::new(&spec, &window)
+            .take(3)
+            .map(|occurrence| {
+                let occurrence = occurrence.expect("occurrence");
+                (occurrence.start_local, occurrence.source)
+            })
+            .collect();
+        assert_eq!(
+            seen,
+            vec![
+                ("2026-03-01T10:00:00".to_string(), OccurrenceSource::Rrule),
+                ("2026-03-02T15:00:00".to_string(), OccurrenceSource::Rdate),
+                ("2026-03-03T10:00:00".to_string(), OccurrenceSource::Rrule),
+            ]
+        );
+
+        let collected: Vec<_> = OccurrenceIter::new(&spec, &window)
+            .take(5)
+            .map(|occurrence| occurrence.expect("occurrence").start_local)
+            .collect();
+        let expanded: Vec<_> = expand(&spec, &ExpandQuery::Unbounded, 5)
+            .expect("expand")
+            .into_iter()
+            .map(|occurrence| occurrence.start_local)
+            .collect();
+        assert_eq!(collected, expanded);
+
+        let broken = RecurrenceSpec {
+            rrules: vec!["FREQ=SOMETIMES".to_string()],
+            ..spec
+        };
+        let mut iter = OccurrenceIter::new(&broken, &window);
+        assert!(matches!(
+            iter.next(),
+            Some(Err(CoreError::InvalidRrule { .. }))
+        ));
+        assert!(iter.next().is_none());
+    }
+
+    fn styled_occurrence() -> Occurrence {
+        Occurrence {
+            start_local: "2026-03-01T10:00:00".to_string(),
+            start_utc: "2026-03-01T09:00:00Z".to_string(),
+            start: None,
+            tz: Some("Europe/Berlin".to_string()),
+            source: OccurrenceSource::Rrule,
+            rule_index: 0,
+            abbrev: None,
+            period: None,
+            end_local: None,
+            end_utc: None,
+            recurrence_id: None,
+            sequence: None,
+            weekday: None,
+            day_of_year: None,
+            iso_week: None,
+            iso_year: None,
+        }
+    }
+
+    #[test]
+    fn formats_occurrence_default_style() {
+        let mut occ = styled_occurrence();
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Default),
+            "2026-03-01T10:00:00 (2026-03-01T09:00:00Z) RRULE idx=0"
+        );
+
+        occ.abbrev = Some("CET".to_string());
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Default),
+            "2026-03-01T10:00:00 CET (2026-03-01T09:00:00Z) RRULE idx=0"
+        );
+
+        float_occurrence(&mut occ);
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Default),
+            "2026-03-01T10:00:00 (floating) RRULE idx=0"
+        );
+    }
+
+    #[test]
+    fn formats_occurrence_table_style() {
+        let mut occ = styled_occurrence();
+        occ.source = OccurrenceSource::Rdate;
+        occ.rule_index = 2;
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Table),
+            "2026-03-01T10:00:00  2026-03-01T09:00:00Z  RDATE  2"
+        );
+
+        float_occurrence(&mut occ);
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Table),
+            "2026-03-01T10:00:00  -                     RDATE  2"
+        );
+    }
+
+    #[test]
+    fn formats_occurrence_human_style() {
+        let mut occ = styled_occurrence();
+        occ.abbrev = Some("CET".to_string());
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Human),
+            "Sun 1 Mar 2026, 10:00 CET (RRULE 0)"
+        );
+
+        occ.start = Some("2026-03-29T10:00:00+02:00".to_string());
+        occ.start_local.clear();
+        occ.start_utc.clear();
+        occ.abbrev = None;
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Human),
+            "Sun 29 Mar 2026, 10:00 (RRULE 0)"
+        );
+    }
+
+    #[test]
+    fn formats_occurrence_rfc3339_style() {
+        let mut occ = styled_occurrence();
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Rfc3339),
+            "2026-03-01T10:00:00+01:00"
+        );
+
+        occ.start = Some("2026-03-29T10:00:00+02:00".to_string());
+        assert_eq!(
+            format_occurrence(&occ, OccurrenceStyle::Rfc3339),
+            "2026-03-29T10:00:00+02:00"
+        );
+
+        let mut floating = styled_occurrence();
+        float_occurrence(&mut floating);
+        assert_eq!(
+            format_occurrence(&floating, OccurrenceStyle::Rfc3339),
+            "2026-03-01T10:00:00"
+        );
+    }
+
+    #[test]
+    fn resolves_fall_back_times_by_dst_policy() {
+        let tz = berlin();
+        let ambiguous = "2026-10-25T02:30:00";
+        assert!(matches!(
+            parse_iso_datetime(ambiguous, &tz),
+            Err(CoreError::InvalidDateTime { .. })
+        ));
+
+        let utc = |policy| {
+            let (dt, _) = parse_iso_datetime_with(ambiguous, &tz, policy).expect("resolved");
+            dt.with_timezone(&Utc).to_rfc3339()
+        };
+        assert_eq!(utc(DstPolicy::Earliest), "2026-10-25T00:30:00+00:00");
+        assert_eq!(utc(DstPolicy::Latest), "2026-10-25T01:30:00+00:00");
+
+        // Spring-forward gaps have no instant to pick.
+        assert!(parse_iso_datetime_with("2026-03-29T02:30:00", &tz, DstPolicy::Latest).is_err());
+
+        let raw = "BEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20261025T023000\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\n";
+        assert!(parse_ics_spec(raw, None).is_err());
+        let options = IcsOptions {
+            dst_policy: DstPolicy::Latest,
+            ..IcsOptions::default()
+        };
+        let spec = parse_ics_spec_with(raw, None, options).expect("spec");
+        assert_eq!(
+            spec.dtstart.with_timezone(&Utc).to_rfc3339(),
+            "2026-10-25T01:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn strips_extension_parts_for_validation() {
+        let tz = berlin();
+        let spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY;X-COMMENT=standup;COUNT=2".to_string()],
+            ..spec_fixture()
+        };
+
+        let result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(result.occurrences.len(), 2);
+        assert_eq!(result.meta.rules.rrule, spec.rrules);
+        assert_eq!(
+            result.meta.rule_extensions,
+            vec![RuleExtension {
+                property: "RRULE".to_string(),
+                rule_index: 0,
+                name: "X-COMMENT".to_string(),
+                value: "standup".to_string(),
+            }]
+        );
+
+        let findings = lint(&spec, false, false);
+        assert!(findings.errors.is_empty());
+        assert_eq!(findings.hints.len(), 1);
+        assert_eq!(findings.hints[0].code, "H006");
+        assert_eq!(
+            findings.hints[0].message,
+            "X-COMMENT stripped for validation"
+        );
+        assert_eq!(findings.hints[0].span, Some((11, 28)));
+
+        assert_eq!(
+            strip_extension_parts("FREQ=DAILY;x-team=core"),
+            Some("FREQ=DAILY".to_string())
+        );
+        assert_eq!(strip_extension_parts("FREQ=DAILY;COUNT=2"), None);
+    }
+
+    #[test]
+    fn shifts_spring_forward_times_past_the_gap() {
+        let tz = parse_timezone("America/New_York").expect("timezone");
+        let skipped = "2026-03-08T02:30:00";
+        assert!(matches!(
+            parse_iso_datetime(skipped, &tz),
+            Err(CoreError::InvalidDateTime { .. })
+        ));
+        assert!(parse_iso_datetime_with(skipped, &tz, DstPolicy::Latest).is_err());
+
+        let (dt, _) =
+            parse_iso_datetime_with(skipped, &tz, DstPolicy::ShiftForward).expect("shifted");
+        assert_eq!(dt.to_rfc3339(), "2026-03-08T03:00:00-04:00");
+        let (edge, _) =
+            parse_iso_datetime_with("2026-03-08T02:00:00", &tz, DstPolicy::ShiftForward)
+                .expect("shifted");
+        assert_eq!(edge, dt);
+        // explain and local UNTIL values keep the offset before the gap.
+        let local = NaiveDateTime::parse_from_str(skipped, "%Y-%m-%dT%H:%M:%S").unwrap();
+        assert_eq!(
+            resolve_local(tz, local, skipped)
+                .expect("gap")
+                .0
+                .to_rfc3339(),
+            "2026-03-08T03:30:00-04:00"
+        );
+
+        // Lord Howe Island skips only half an hour, 02:00 to 02:30.
+        let lord_howe = parse_timezone("Australia/Lord_Howe").expect("timezone");
+        let (half, _) =
+            parse_iso_datetime_with("2026-10-04T02:15:00", &lord_howe, DstPolicy::ShiftForward)
+                .expect("shifted");
+        assert_eq!(half.to_rfc3339(), "2026-10-04T02:30:00+11:00");
+
+        // Valid and ambiguous times are unaffected by the gap handling.
+        let (valid, _) =
+            parse_iso_datetime_with("2026-03-08T03:30:00", &tz, DstPolicy::ShiftForward)
+                .expect("valid");
+        assert_eq!(valid.to_rfc3339(), "2026-03-08T03:30:00-04:00");
+        assert!(
+            parse_iso_datetime_with("2026-11-01T01:30:00", &tz, DstPolicy::ShiftForward).is_err()
+        );
+    }
+
+    #[test]
+    fn blames_the_rule_part_an_error_names() {
+        let span = |rule: &str| match parse_validated_rule(
+            rule,
+            berlin().with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap(),
+            DateValueType::DateTime,
+        ) {
+            Err(CoreError::InvalidRrule { rule, reason }) => invalid_rule_span(&rule, &reason),
+            other => panic!("expected an invalid rule, got {other:?}"),
+        };
+
+        assert_eq!(span("FREQ=DAILY;COUNT=abc"), Some((11, 20)));
+        assert_eq!(span("FREQ=DAILY;BYDAY=MO,XX;COUNT=2"), Some((11, 22)));
+        assert_eq!(span("FREQ=SOMETIMES"), Some((0, 14)));
+        assert_eq!(span("FREQ=DAILY;COUNT=;BYHOUR=9"), Some((11, 17)));
+        // The BYHOUR value also appears as COUNT, but only BYHOUR is named.
+        assert_eq!(span("FREQ=DAILY;COUNT=25;BYHOUR=25"), Some((20, 29)));
+        assert_eq!(span("BYDAY=MO;COUNT=2"), None);
+    }
+
+    #[test]
+    fn maps_occurrences_to_fullcalendar_events() {
+        let timed = "BEGIN:VEVENT\nUID:standup\nSUMMARY:Daily standup\\, team A\nDTSTART;TZID=Europe/Berlin:20260328T090000\nDURATION:PT15M\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\n";
+        let spec = parse_ics_spec(timed, None).expect("spec");
+        assert_eq!(spec.summary.as_deref(), Some("Daily standup, team A"));
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        let events = fullcalendar_events(&spec, &occurrences);
+        assert_eq!(
+            events[1],
+            FullCalendarEvent {
+                title: "Daily standup, team A".to_string(),
+                start: "2026-03-29T09:00:00+02:00".to_string(),
+                end: Some("2026-03-29T09:15:00+02:00".to_string()),
+                all_day: false,
+            }
+        );
+
+        let all_day = "BEGIN:VEVENT\nUID:holiday\nDTSTART;VALUE=DATE:20260401\nDTEND;VALUE=DATE:20260402\nRRULE:FREQ=YEARLY;COUNT=1\nEND:VEVENT\n";
+        let spec = parse_ics_spec(all_day, Some("Europe/Berlin")).expect("spec");
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(
+            fullcalendar_events(&spec, &occurrences),
+            vec![FullCalendarEvent {
+                title: String::new(),
+                start: "2026-04-01".to_string(),
+                end: Some("2026-04-02".to_string()),
+                all_day: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn lint_flags_rdate_exdate_tzid_mismatch() {
+        let raw = "BEGIN:VEVENT\nUID:a\nDTSTART;TZID=Europe/Berlin:20260302T090000\nRRULE:FREQ=DAILY;COUNT=3\nRDATE;TZID=America/New_York:20260310T090000\nRDATE;TZID=America/New_York:20260311T090000\nEXDATE;TZID=Europe/Berlin:20260303T090000\nEND:VEVENT\n";
+        let spec = parse_ics_spec(raw, None).expect("spec");
+        assert_eq!(spec.diagnostics.property_tzids.len(), 3);
+
+        let findings = lint(&spec, false, false);
+        let w007: Vec<_> = findings
+            .warnings
+            .iter()
+            .filter(|f| f.code == "W007")
+            .collect();
+        assert_eq!(w007.len(), 1);
+        assert_eq!(w007[0].message, "RDATE TZID differs from DTSTART");
+        let details = w007[0].details.as_deref().unwrap_or_default();
+        assert!(details.contains("America/New_York"));
+        assert!(details.contains("Europe/Berlin"));
+
+        // The mismatching RDATE keeps its own zone.
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert!(
+            occurrences
+                .iter()
+                .any(|occ| occ.start_utc == "2026-03-10T13:00:00Z")
+        );
+    }
+
+    #[test]
+    fn explain_notes_times_before_dtstart() {
+        let tz = berlin();
+        let mut spec = RecurrenceSpec {
+            dtstart: tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap(),
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec!["FREQ=DAILY".to_string()],
+            ..spec_fixture()
+        };
+        let note = "queried time is before DTSTART 2026-03-01T10:00:00; no occurrences exist before the series start";
+
+        // 09:00 in London reads earlier than 10:00 but is DTSTART itself.
+        let london: Tz = parse_timezone("Europe/London").expect("timezone");
+        let after =
+            explain(&spec, london.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap()).expect("explain");
+        assert!(after.included);
+        assert!(!after.notes.iter().any(|n| n.contains("before DTSTART")));
+
+        let before = explain_local(&spec, "2026-03-01T09:59:59").expect("explain");
+        assert!(!before.included);
+        assert!(before.notes.iter().any(|n| n == note));
+
+        let day = explain_local(&spec, "2026-02-28").expect("explain");
+        assert!(day.notes.iter().any(|n| n == note));
+
+        spec.rdates = vec![tz.with_ymd_and_hms(2026, 2, 1, 10, 0, 0).unwrap()];
+        let before = explain_local(&spec, "2026-02-15T10:00:00").expect("explain");
+        assert!(
+            before
+                .notes
+                .iter()
+                .any(|n| n.ends_with("only RDATEs can occur before the series start"))
+        );
+    }
+
+    #[test]
+    fn lint_checks_byweekno_freq_and_wkst() {
+        let tz = berlin();
+        let findings_for = |rule: &str| {
+            let spec = RecurrenceSpec {
+                dtstart: tz.with_ymd_and_hms(2026, 5, 11, 9, 0, 0).unwrap(),
+                tz: "Europe/Berlin".to_string(),
+                rrules: vec![rule.to_string()],
+                ..spec_fixture()
+            };
+            lint(&spec, false, true)
+        };
+        let codes =
+            |findings: &[Finding]| findings.iter().map(|f| f.code.clone()).collect::<Vec<_>>();
+
+        let clean = findings_for("FREQ=YEARLY;BYWEEKNO=20;WKST=MO");
+        assert!(clean.errors.is_empty() && clean.warnings.is_empty());
+
+        let no_wkst = findings_for("FREQ=YEARLY;BYWEEKNO=20");
+        assert!(no_wkst.errors.is_empty());
+        assert_eq!(codes(&no_wkst.warnings), vec!["W008"]);
+        assert_eq!(no_wkst.warnings[0].span, Some((12, 23)));
+
+        let weekly = findings_for("FREQ=WEEKLY;BYWEEKNO=20;WKST=MO");
+        assert_eq!(codes(&weekly.errors), vec!["E005"]);
+        assert_eq!(
+            weekly.errors[0].message,
+            "BYWEEKNO is only allowed with FREQ=YEARLY, not FREQ=WEEKLY"
+        );
+        assert!(weekly.warnings.is_empty());
+
+        let lowercase = findings_for("freq=yearly;byweekno=20;wkst=MO;count=2");
+        assert!(lowercase.errors.is_empty() && lowercase.warnings.is_empty());
+        let lowercase = findings_for("freq=weekly;byweekno=20;wkst=MO");
+        assert_eq!(
+            lowercase.errors[0].message,
+            "BYWEEKNO is only allowed with FREQ=YEARLY, not FREQ=WEEKLY"
+        );
+    }
+
+    #[test]
+    fn accepts_ics_datetimes_without_seconds() {
+        let raw = "BEGIN:VEVENT\nUID:a\nDTSTART;TZID=Europe/Berlin:20260302T090000\nRRULE:FREQ=WEEKLY;COUNT=2\nRDATE;TZID=Europe/Berlin:20260304T1430,20260305T150000\nEXDATE:20260309T0800Z\nEND:VEVENT\n";
+        let spec = parse_ics_spec(raw, None).expect("spec");
+        let tz = berlin();
+        assert_eq!(
+            spec.rdates,
+            vec![
+                tz.with_ymd_and_hms(2026, 3, 4, 14, 30, 0).unwrap(),
+                tz.with_ymd_and_hms(2026, 3, 5, 15, 0, 0).unwrap(),
+            ]
+        );
+        assert_eq!(
+            spec.exdates,
+            vec![tz.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap()]
+        );
+        assert_eq!(
+            spec.diagnostics.short_datetimes,
+            vec!["RDATE:20260304T1430", "EXDATE:20260309T0800Z"]
+        );
+
+        let findings = lint(&spec, false, false);
+        let hint = findings
+            .hints
+            .iter()
+            .find(|f| f.code == "H007")
+            .expect("H007");
+        assert!(
+            hint.details
+                .as_deref()
+                .unwrap_or_default()
+                .contains("RDATE:20260304T1430, EXDATE:20260309T0800Z")
+        );
+
+        assert!(parse_ics_spec(&raw.replace("T1430", "T143"), None).is_err());
+        let strict = IcsOptions {
+            rfc_strict: true,
+            ..IcsOptions::default()
+        };
+        assert!(matches!(
+            parse_ics_spec_with(raw, None, strict),
+            Err(CoreError::InvalidIcs(message)) if message.starts_with("RDATE:20260304T1430")
+        ));
+    }
+
+    #[test]
+    fn selftest_cases_pass() {
+        for result in selftest() {
+            assert!(result.passed, "{result:?}");
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_stable_codes_and_fields() {
+        let report = CoreError::LimitExceeded { limit: 1000 }.report();
+        assert_eq!(report.code, "LIMIT_EXCEEDED");
+        assert_eq!(report.limit, Some(1000));
+        assert!(report.message.starts_with("hard limit exceeded (1000"));
+
+        let err = parse_validated_rule(
+            "FREQ=DAILY;BYHOUR=25",
+            berlin().with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap(),
+            DateValueType::DateTime,
+        )
+        .unwrap_err();
+        let json = serde_json::to_value(err.report()).expect("serialize");
+        assert_eq!(json["code"], "INVALID_RRULE");
+        assert_eq!(json["rule"], "FREQ=DAILY;BYHOUR=25");
+        assert!(json["reason"].is_string());
+        assert!(json.get("limit").is_none());
+    }
+
+    #[test]
+    fn explain_names_the_nearest_occurrences() {
+        let raw = r#"{"dtstart": "2026-03-02T10:00:00", "rrule": ["FREQ=DAILY;COUNT=5"]}"#;
+        let spec = parse_json_spec(raw, Some("Europe/Berlin")).expect("json parse");
+        let options = ExplainOptions {
+            suggest_within: Some(Duration::minutes(30)),
+            ..ExplainOptions::default()
+        };
+
+        let off = explain_local_with(&spec, "2026-03-03T10:30:00", options).expect("explain");
+        assert_eq!(off.nearest_before.as_deref(), Some("2026-03-03T10:00:00"));
+        assert_eq!(off.nearest_after.as_deref(), Some("2026-03-04T10:00:00"));
+        assert!(
+            off.notes
+                .contains(&"Did you mean 2026-03-03T10:00:00?".to_string())
+        );
+
+        let far = explain_local_with(&spec, "2026-03-03T16:00:00", options).expect("explain");
+        assert!(
+            !far.notes
+                .iter()
+                .any(|note| note.starts_with("Did you mean"))
+        );
+
+        let hit = explain_local_with(&spec, "2026-03-03T10:00:00", options).expect("explain");
+        assert_eq!((hit.nearest_before, hit.nearest_after), (None, None));
+    }
+
+    #[test]
+    fn compresses_regular_occurrences_into_an_rrule() {
+        let raw = r#"{"dtstart": "2026-03-26T10:00:00", "rrule": ["FREQ=DAILY;COUNT=5"]}"#;
+        let mut spec = parse_json_spec(raw, Some("Europe/Berlin")).expect("json parse");
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        // The run crosses the spring-forward change and still reads as DAILY.
+        assert_eq!(
+            detect_rrule(&spec, &occurrences)
+                .expect("detect")
+                .as_deref(),
+            Some("FREQ=DAILY;COUNT=5")
+        );
+        let ics =
+            ics_calendar_compressed(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE)
+                .expect("ics");
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains(
+            "DTSTART;TZID=Europe/Berlin:20260326T100000\r\nRRULE:FREQ=DAILY;COUNT=5\r\n"
+        ));
+
+        spec.rrules = vec!["FREQ=MONTHLY;INTERVAL=2;COUNT=3".to_string()];
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(
+            detect_rrule(&spec, &occurrences)
+                .expect("detect")
+                .as_deref(),
+            Some("FREQ=MONTHLY;INTERVAL=2;COUNT=3")
+        );
+
+        spec.rrules = vec!["FREQ=DAILY;COUNT=5".to_string()];
+        spec.exdates = vec![berlin().with_ymd_and_hms(2026, 3, 28, 10, 0, 0).unwrap()];
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(detect_rrule(&spec, &occurrences).expect("detect"), None);
+        let ics =
+            ics_calendar_compressed(&spec, &occurrences, DEFAULT_PRODID, DEFAULT_UID_TEMPLATE)
+                .expect("ics");
+        assert!(ics.contains(
+            "RDATE;TZID=Europe/Berlin:20260327T100000,20260329T100000,20260330T100000\r\n"
+        ));
+    }
+
+    #[test]
+    fn expansion_spans_accept_years_and_months() {
+        let parse = |value: &str| ExpansionSpan::parse(value).map(|span| span.to_string());
+        assert_eq!(parse("P1Y").unwrap(), "P1Y");
+        assert_eq!(parse("p1y2m10dt30m").unwrap(), "P1Y2M10DT30M");
+        assert_eq!(parse("P18M").unwrap(), "P1Y6M");
+        assert_eq!(parse("PT36H").unwrap(), "PT36H");
+        for invalid in ["", "P", "1Y", "P1X", "PT1Y", "-P1Y"] {
+            assert!(
+                matches!(parse(invalid), Err(CoreError::InvalidDuration { .. })),
+                "{invalid}"
+            );
+        }
+
+        let tz = berlin();
+        let start = tz.with_ymd_and_hms(2026, 1, 31, 10, 0, 0).unwrap();
+        let month = ExpansionSpan::parse("P1M")
+            .unwrap()
+            .end_from(start)
+            .unwrap();
+        assert_eq!(month, tz.with_ymd_and_hms(2026, 2, 28, 10, 0, 0).unwrap());
+        for far in ["P99999999999999D", "PT9999999999999S"] {
+            assert!(
+                matches!(
+                    ExpansionSpan::parse(far).unwrap().end_from(start),
+                    Err(CoreError::InvalidDuration { .. })
+                ),
+                "{far}"
+            );
+        }
+
+        let raw = r#"{"dtstart": "2026-03-02T10:00:00", "rrule": ["FREQ=WEEKLY"]}"#;
+        let spec = parse_json_spec(raw, Some("Europe/Berlin")).expect("json parse");
+        let end = ExpansionSpan::parse("P4W")
+            .unwrap()
+            .end_from(spec.dtstart)
+            .unwrap();
+        let query = ExpandQuery::Before { end };
+        assert_eq!(expand(&spec, &query, 100).expect("expand").len(), 5);
+        assert_eq!(expand(&spec, &query, 3).expect("expand").len(), 3);
+    }
+
+    #[test]
+    fn applies_override_with_highest_sequence() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:s@example.com\nDTSTART;TZID=Europe/Berlin:20260302T100000\nRRULE:FREQ=DAILY;COUNT=3\nEND:VEVENT\nBEGIN:VEVENT\nUID:s@example.com\nRECURRENCE-ID;TZID=Europe/Berlin:20260303T100000\nDTSTART;TZID=Europe/Berlin:20260303T150000\nSEQUENCE:2\nEND:VEVENT\nBEGIN:VEVENT\nUID:s@example.com\nRECURRENCE-ID;TZID=Europe/Berlin:20260303T100000\nDTSTART;TZID=Europe/Berlin:20260303T120000\nSEQUENCE:1\nEND:VEVENT\nEND:VCALENDAR\n";
+        let spec = parse_ics_spec(raw, None).expect("overrides are not separate events");
+        assert_eq!(spec.overrides.len(), 1);
+        assert_eq!(spec.overrides[0].sequence, 2);
+
+        let occurrences = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        let starts: Vec<&str> = occurrences
+            .iter()
+            .map(|occ| occ.start_local.as_str())
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                "2026-03-02T10:00:00",
+                "2026-03-03T15:00:00",
+                "2026-03-04T10:00:00"
+            ]
+        );
+        let moved = &occurrences[1];
+        assert_eq!(moved.recurrence_id.as_deref(), Some("2026-03-03T10:00:00"));
+        assert_eq!(moved.sequence, Some(2));
+        assert_eq!(occurrences[0].sequence, None);
+
+        let away = explain(
+            &spec,
+            berlin().with_ymd_and_hms(2026, 3, 3, 10, 0, 0).unwrap(),
+        )
+        .expect("explain");
+        assert!(!away.included);
+        assert!(
+            away.notes
+                .iter()
+                .any(|note| note.starts_with("Moved to 2026-03-03T15:00:00"))
+        );
+    }
+
+    #[test]
+    fn calendar_fields_use_the_local_date_and_iso_year() {
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-12-31T00:30:00","rrule":["FREQ=DAILY;COUNT=2"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        apply_calendar_fields(&mut result);
+        let fields: Vec<_> = result
+            .occurrences
+            .iter()
+            .map(|occ| {
+                (
+                    occ.weekday.as_deref(),
+                    occ.day_of_year,
+                    occ.iso_week,
+                    occ.iso_year,
+                )
+            })
+            .collect();
+        // The first instance is still Wednesday in UTC; 2027-01-01 belongs
+        // to week 53 of ISO year 2026.
+        assert_eq!(
+            fields,
+            vec![
+                (Some("TH"), Some(365), Some(53), Some(2026)),
+                (Some("FR"), Some(1), Some(53), Some(2026)),
+            ]
+        );
+
+        // The other direction: the last days of 2024 are in week 1 of 2025.
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2024-12-30T10:00:00","rrule":["FREQ=DAILY;COUNT=1"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        apply_calendar_fields(&mut result);
+        let occ = &result.occurrences[0];
+        assert_eq!(
+            (occ.day_of_year, occ.iso_week, occ.iso_year),
+            (Some(365), Some(1), Some(2025))
+        );
+    }
+
+    #[test]
+    fn validate_spec_reports_the_first_invalid_rule() {
+        let mut spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-01T10:00:00","rrule":["FREQ=DAILY;COUNT=2"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        validate_spec(&spec).expect("valid");
+
+        spec.exrules.push("FREQ=DAILY;BYHOUR=25".to_string());
+        let err = validate_spec(&spec).expect_err("invalid exrule");
+        assert_eq!(err.code(), "INVALID_RRULE");
+    }
+
+    #[test]
+    fn recent_zones_fall_back_to_their_older_name() {
+        let without_kyiv = |zone: &str| zone != "Europe/Kyiv";
+        assert_eq!(
+            fallback_for("Europe/Kyiv", without_kyiv),
+            Some("Europe/Kiev")
+        );
+        assert_eq!(fallback_for("Europe/Berlin", without_kyiv), None);
+        assert_eq!(fallback_for("Mars/Olympus", |_| false), None);
+        // The bundled tzdata knows every new name, so no alias is in use.
+        assert_eq!(timezone_fallback("Europe/Kyiv"), None);
+        for (_, old) in TIMEZONE_ALIASES {
+            parse_timezone(old).expect("alias target exists");
+        }
+    }
+
+    #[test]
+    fn lint_flags_dtstart_outside_byday() {
+        let w010_details = |dtstart: &str, rule: &str| {
+            let raw = format!(r#"{{"dtstart":"{dtstart}","rrule":["{rule}"]}}"#);
+            let spec = parse_json_spec(&raw, Some("Europe/Berlin")).expect("spec");
+            let findings = lint(&spec, false, false);
+            findings
+                .warnings
+                .into_iter()
+                .filter(|f| f.code == "W010")
+                .map(|f| f.details.unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+
+        let tuesday = w010_details("2026-03-03T10:00:00", "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=2");
+        assert_eq!(tuesday.len(), 1);
+        assert!(tuesday[0].starts_with("DTSTART is a Tuesday (TU), not in BYDAY=MO,WE"));
+
+        let last_day = w010_details("2026-02-28T10:00:00", "FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=2");
+        assert!(last_day.is_empty());
+        let mid_month = w010_details("2026-03-15T10:00:00", "FREQ=MONTHLY;BYMONTHDAY=1;COUNT=2");
+        assert!(mid_month[0].starts_with("DTSTART is day 15 of the month, not in BYMONTHDAY=1"));
+        assert!(w010_details("2026-03-02T10:00:00", "FREQ=WEEKLY;BYDAY=MO;COUNT=2").is_empty());
     }
 
-    lines
-}
+    #[test]
+    fn summarizes_a_count_bounded_weekly_series() {
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-02T10:00:00","rrule":["FREQ=WEEKLY;BYDAY=MO;COUNT=4"],"exdate":["2026-03-16T10:00:00"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let summary = summarize_series(&spec, &Window::unbounded(), 100).expect("summary");
+        assert_eq!(
+            summary.description,
+            vec!["Every week on Monday for 4 occurrences"]
+        );
+        assert_eq!(summary.first.as_deref(), Some("2026-03-02T10:00:00"));
+        assert_eq!(summary.last.as_deref(), Some("2026-03-23T10:00:00"));
+        assert_eq!(summary.count, Some(3));
+        assert!(summary.is_finite);
+        assert_eq!((summary.exdates, summary.exrules), (Some(1), Some(0)));
 
-fn parse_property_head(head: &str) -> (String, HashMap<String, String>) {
-    let mut parts = head.split(';');
-    let name = parts
-        .next()
-        .map(|s| s.trim().to_ascii_uppercase())
-        .unwrap_or_default();
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-02T10:00:00","rrule":["FREQ=DAILY"],"exrule":["FREQ=WEEKLY;BYDAY=SA,SU"],"exdate":["2026-03-03T10:00:00","2026-03-04T12:00:00","2026-04-01T10:00:00"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let window = Window::from_between(
+            berlin().with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap(),
+            berlin().with_ymd_and_hms(2026, 3, 8, 23, 59, 59).unwrap(),
+        )
+        .expect("window");
+        let summary = summarize_series(&spec, &window, 100).expect("summary");
+        assert_eq!(summary.count, Some(4));
+        assert_eq!((summary.exdates, summary.exrules), (Some(1), Some(2)));
 
-    let mut params = HashMap::new();
-    for part in parts {
-        if let Some((k, v)) = part.split_once('=') {
-            params.insert(k.trim().to_ascii_uppercase(), v.trim().to_string());
-        }
+        let spec = parse_json_spec(
+            r#"{"dtstart":"2026-03-02T10:00:00","rrule":["FREQ=WEEKLY"]}"#,
+            Some("Europe/Berlin"),
+        )
+        .expect("spec");
+        let summary = summarize_series(&spec, &Window::unbounded(), 100).expect("summary");
+        assert_eq!(summary.first.as_deref(), Some("2026-03-02T10:00:00"));
+        assert_eq!((summary.last, summary.count), (None, None));
+        assert_eq!((summary.exdates, summary.exrules), (None, None));
     }
 
-    (name, params)
-}
+    #[test]
+    fn parses_ndjson_lines_with_ids() {
+        let line = r#"{"id": "a", "dtstart": "2026-03-02T09:00:00", "rrule": ["FREQ=DAILY"]}"#;
+        let (id, spec) = parse_json_spec_line(line, Some("Europe/Berlin"));
+        assert_eq!(id, Some(serde_json::json!("a")));
+        assert_eq!(spec.expect("spec").rrules, vec!["FREQ=DAILY"]);
+        assert!(parse_json_spec(line, Some("Europe/Berlin")).is_err());
 
-fn resolve_property_tz(
-    params: &HashMap<String, String>,
-    fallback_tz: Option<&str>,
-) -> Result<Tz, CoreError> {
-    if let Some(tzid) = params.get("TZID") {
-        return parse_timezone(tzid);
+        // Errors point into the line as given, and the id survives them.
+        let typo = r#"{"id": 7, "dtstrt": "2026-03-02T09:00:00"}"#;
+        let (id, spec) = parse_json_spec_line(typo, Some("Europe/Berlin"));
+        assert_eq!(id, Some(serde_json::json!(7)));
+        let Err(CoreError::InvalidJson(reason)) = spec else {
+            panic!("expected InvalidJson");
+        };
+        assert!(reason.ends_with("line 1 column 18"), "{reason}");
     }
 
-    if let Some(tz) = fallback_tz {
-        return parse_timezone(tz);
+    #[test]
+    fn parses_minimal_ics() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=WEEKLY;COUNT=2\nRDATE;TZID=Europe/Berlin:20260310T100000\nEND:VEVENT\nEND:VCALENDAR\n";
+        let spec = parse_ics_spec(raw, None).expect("ics parse");
+        assert_eq!(spec.tz, "Europe/Berlin");
+        assert_eq!(spec.rrules.len(), 1);
+        assert_eq!(spec.rdates.len(), 1);
     }
 
-    Ok(Tz::UTC)
-}
+    #[test]
+    fn parses_each_vevent_separately() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VTIMEZONE\nTZID:Europe/Berlin\nBEGIN:STANDARD\nDTSTART:19701025T030000\nRRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\nEND:STANDARD\nEND:VTIMEZONE\nBEGIN:VEVENT\nUID:a@example.com\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=DAILY;COUNT=2\nBEGIN:VALARM\nTRIGGER:-PT5M\nRRULE:FREQ=HOURLY\nEND:VALARM\nEND:VEVENT\nBEGIN:VEVENT\nUID:b@example.com\nDTSTART;TZID=America/New_York:20260302T090000\nRRULE:FREQ=WEEKLY;COUNT=3\nEXDATE;TZID=America/New_York:20260309T090000\nEND:VEVENT\nEND:VCALENDAR\n";
 
-fn parse_value_type_for_multi(params: &HashMap<String, String>, raw: &str) -> DateValueType {
-    if params
-        .get("VALUE")
-        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
-    {
-        return DateValueType::Date;
+        let events = parse_ics_calendar(raw, None, IcsOptions::default()).expect("calendar");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].uid.as_deref(), Some("a@example.com"));
+        assert_eq!(events[0].tz, "Europe/Berlin");
+        assert_eq!(events[0].rrules, vec!["FREQ=DAILY;COUNT=2"]);
+        assert!(events[0].exdates.is_empty());
+        assert_eq!(events[1].tz, "America/New_York");
+        assert_eq!(events[1].rrules, vec!["FREQ=WEEKLY;COUNT=3"]);
+        assert_eq!(events[1].exdates.len(), 1);
+
+        let err = parse_ics_spec(raw, None).expect_err("ambiguous");
+        assert!(
+            err.to_string()
+                .contains("2 VEVENTs (UIDs: a@example.com, b@example.com)")
+        );
+
+        let selected = parse_ics_event(raw, None, IcsOptions::default(), Some("b@example.com"))
+            .expect("selected");
+        assert_eq!(selected.tz, "America/New_York");
+        assert!(matches!(
+            parse_ics_event(raw, None, IcsOptions::default(), Some("c@example.com")),
+            Err(CoreError::InvalidIcs(_))
+        ));
     }
 
-    let first = raw.split(',').next().unwrap_or(raw);
-    if first.len() == 8 && first.chars().all(|c| c.is_ascii_digit()) {
-        DateValueType::Date
-    } else {
-        DateValueType::DateTime
+    #[test]
+    fn keeps_concatenated_calendars_apart_with_their_method() {
+        let raw = "BEGIN:VCALENDAR\nMETHOD:PUBLISH\nBEGIN:VEVENT\nUID:a@example.com\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\nEND:VCALENDAR\nBEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:b@example.com\nDTSTART;TZID=America/New_York:20260302T090000\nRRULE:FREQ=WEEKLY;COUNT=3\nEND:VEVENT\nmethod:cancel\nEND:VCALENDAR\nBEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:c@example.com\nDTSTART:20260303T090000Z\nRDATE:20260304T090000Z\nEND:VEVENT\nEND:VCALENDAR\n";
+
+        let events = parse_ics_calendar(raw, None, IcsOptions::default()).expect("calendar");
+        let summary: Vec<_> = events
+            .iter()
+            .map(|spec| {
+                (
+                    spec.uid.as_deref(),
+                    spec.method.as_deref(),
+                    spec.rrules.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("a@example.com"), Some("PUBLISH"), 1),
+                (Some("b@example.com"), Some("CANCEL"), 1),
+                (Some("c@example.com"), None, 0),
+            ]
+        );
+        assert_eq!(events[1].tz, "America/New_York");
+
+        let cancel = lint(&events[1], false, false);
+        assert!(cancel.hints.iter().any(|f| f.code == "H005"));
+        let publish = lint(&events[0], false, false);
+        assert!(!publish.hints.iter().any(|f| f.code == "H005"));
     }
-}
 
-fn parse_ics_multi_datetime_values(
-    raw: &str,
-    tz: &Tz,
-    value_type: DateValueType,
-) -> Result<Vec<DateTime<Tz>>, CoreError> {
-    raw.split(',')
-        .map(|part| parse_ics_datetime_value(part.trim(), tz, value_type))
-        .collect()
-}
+    #[test]
+    fn selects_uid_less_event_by_synthesized_uid() {
+        let raw = "BEGIN:VEVENT\nDTSTART:20260301T100000Z\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\nBEGIN:VEVENT\nUID:single@example.com\nDTSTART:20260401T100000Z\nEND:VEVENT\n";
+        let err = parse_ics_calendar(raw, None, IcsOptions::default())
+            .map(|_| ())
+            .expect_err("the single event has no RRULE");
+        assert!(matches!(err, CoreError::MissingField(_)));
 
-fn parse_ics_datetime_value(
-    value: &str,
-    tz: &Tz,
-    value_type: DateValueType,
-) -> Result<DateTime<Tz>, CoreError> {
-    match value_type {
-        DateValueType::Date => {
-            let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|err| {
-                CoreError::InvalidDateTime {
-                    input: value.to_string(),
-                    reason: err.to_string(),
-                }
-            })?;
-            let local = date
-                .and_hms_opt(0, 0, 0)
-                .ok_or_else(|| CoreError::InvalidDateTime {
-                    input: value.to_string(),
-                    reason: "could not build midnight datetime".to_string(),
-                })?;
-            localize(*tz, local, value)
-        }
-        DateValueType::DateTime => {
-            if let Some(stripped) = value.strip_suffix('Z') {
-                let naive =
-                    NaiveDateTime::parse_from_str(stripped, "%Y%m%dT%H%M%S").map_err(|err| {
-                        CoreError::InvalidDateTime {
-                            input: value.to_string(),
-                            reason: err.to_string(),
-                        }
-                    })?;
-                let utc_dt = Utc.from_utc_datetime(&naive);
-                Ok(utc_dt.with_timezone(tz))
-            } else {
-                let local =
-                    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|err| {
-                        CoreError::InvalidDateTime {
-                            input: value.to_string(),
-                            reason: err.to_string(),
-                        }
-                    })?;
-                localize(*tz, local, value)
-            }
-        }
+        let first =
+            parse_ics_event_lines(split_vevents(raw).remove(0), None, IcsOptions::default())
+                .expect("first event");
+        let synthesized = first.uid.clone().expect("synthesized uid");
+        let selected = parse_ics_event(raw, None, IcsOptions::default(), Some(&synthesized))
+            .expect("selected by synthesized uid");
+        assert_eq!(selected.rrules, vec!["FREQ=DAILY;COUNT=2"]);
     }
-}
 
-fn localize(tz: Tz, local: NaiveDateTime, input: &str) -> Result<DateTime<Tz>, CoreError> {
-    tz.from_local_datetime(&local)
-        .single()
-        .ok_or_else(|| CoreError::InvalidDateTime {
-            input: input.to_string(),
-            reason: "ambiguous or invalid local time in timezone".to_string(),
-        })
-}
+    #[test]
+    fn missing_uid_is_synthesized_or_rejected_in_strict_mode() {
+        let raw = "BEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\n";
+        let spec = parse_ics_spec(raw, None).expect("ics parse");
+        assert!(spec.uid_synthesized);
+        let uid = spec.uid.clone().expect("synthesized uid");
+        assert!(uid.ends_with("@rrulex"));
+        assert_eq!(parse_ics_spec(raw, None).unwrap().uid, Some(uid));
+        let findings = lint(&spec, false, false);
+        assert!(findings.hints.iter().any(|f| f.code == "H003"));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let strict = IcsOptions {
+            rfc_strict: true,
+            ..IcsOptions::default()
+        };
+        assert!(matches!(
+            parse_ics_spec_with(raw, None, strict),
+            Err(CoreError::InvalidIcs(_))
+        ));
 
-    fn berlin() -> Tz {
-        parse_timezone("Europe/Berlin").expect("timezone")
+        let with_uid = raw.replace("END:VEVENT", "UID:abc@example.com\nEND:VEVENT");
+        let spec = parse_ics_spec_with(&with_uid, None, strict).expect("ics parse");
+        assert_eq!(spec.uid.as_deref(), Some("abc@example.com"));
+        assert!(!spec.uid_synthesized);
     }
 
     #[test]
-    fn parses_iso_datetime() {
-        let tz = berlin();
-        let (dt, kind) = parse_iso_datetime("2026-03-01T10:00:00", &tz).expect("datetime");
-        assert_eq!(kind, DateValueType::DateTime);
+    fn quoted_parameter_values_keep_delimiters() {
+        let (name, params) = parse_property_head("DTSTART;TZID=\"Weird;Zone\";VALUE=DATE-TIME");
+        assert_eq!(name, "DTSTART");
+        assert_eq!(params.get("TZID").map(String::as_str), Some("Weird;Zone"));
+        assert_eq!(params.get("VALUE").map(String::as_str), Some("DATE-TIME"));
+
+        let raw = "BEGIN:VEVENT\nDTSTART;X-NOTE=\"a;b:c\";TZID=\"Europe/Berlin\":20260301T100000\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\n";
+        let spec = parse_ics_spec(raw, None).expect("ics parse");
+        assert_eq!(spec.tz, "Europe/Berlin");
         assert_eq!(
-            dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            spec.dtstart.format("%Y-%m-%dT%H:%M:%S").to_string(),
             "2026-03-01T10:00:00"
         );
     }
 
     #[test]
-    fn expands_weekly_rule() {
+    fn strips_utf8_bom_before_parsing() {
+        let raw = "\u{feff}BEGIN:VCALENDAR\r\nBEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\r\nRRULE:FREQ=DAILY;COUNT=2\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let spec = parse_ics_spec(raw, None).expect("ics parse");
+        assert_eq!(spec.tz, "Europe/Berlin");
+        assert_eq!(spec.rrules, vec!["FREQ=DAILY;COUNT=2"]);
+    }
+
+    #[test]
+    fn parses_json_spec() {
+        let raw = r#"{"dtstart": "2026-03-01T10:00:00", "rrule": ["FREQ=DAILY;COUNT=3"], "exdate": ["2026-03-02T10:00:00"]}"#;
+        let spec = parse_json_spec(raw, Some("Europe/Berlin")).expect("json parse");
+        assert_eq!(spec.tz, "Europe/Berlin");
+        assert_eq!(spec.exdates.len(), 1);
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 10).expect("expand");
+        assert_eq!(occ.len(), 2);
+
+        assert!(matches!(
+            parse_json_spec(r#"{"dtstart": "2026-03-01", "rrules": []}"#, None),
+            Err(CoreError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn parses_location_and_geo_from_ics() {
+        let raw = "BEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=DAILY;COUNT=2\nLOCATION:Room 4\\, Building B\nGEO:52.52;13.405\nEND:VEVENT\n";
+        let spec = parse_ics_spec(raw, None).expect("ics parse");
+        assert_eq!(spec.location.as_deref(), Some("Room 4, Building B"));
+        assert_eq!(
+            spec.geo,
+            Some(Geo {
+                lat: 52.52,
+                lon: 13.405
+            })
+        );
+    }
+
+    #[test]
+    fn explain_local_notes_fall_back_overlap() {
         let tz = berlin();
-        let dtstart = tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        let dtstart = tz.with_ymd_and_hms(2026, 10, 20, 12, 0, 0).unwrap();
         let spec = RecurrenceSpec {
             dtstart,
-            dtstart_type: DateValueType::DateTime,
             tz: "Europe/Berlin".to_string(),
-            rrules: vec!["FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".to_string()],
-            rdates: vec![],
-            exrules: vec![],
-            exdates: vec![],
+            rrules: vec!["FREQ=DAILY;COUNT=10".to_string()],
+            ..spec_fixture()
         };
 
-        let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
-        assert_eq!(occ.len(), 4);
-        assert_eq!(occ[0].start_local, "2026-03-02T10:00:00");
-        assert_eq!(occ[1].start_local, "2026-03-04T10:00:00");
+        let result = explain_local(&spec, "2026-10-25T02:30:00").expect("explain");
+        assert!(!result.included);
+        assert_eq!(result.at, "2026-10-25T02:30:00");
+        assert!(
+            result
+                .notes
+                .iter()
+                .any(|note| note.contains("earlier instant 2026-10-25T00:30:00Z (+02:00)"))
+        );
+
+        let result = explain_local(&spec, "2026-03-29T02:30:00").expect("explain");
+        assert_eq!(result.at, "2026-03-29T03:30:00");
+        assert!(
+            result
+                .notes
+                .iter()
+                .any(|note| note.contains("does not exist"))
+        );
     }
 
     #[test]
-    fn lint_until_type_mismatch() {
+    fn lint_hints_rdate_interval_independence() {
         let tz = berlin();
-        let dtstart = tz.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+        let dtstart = tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        let rdate = tz.with_ymd_and_hms(2026, 3, 10, 10, 0, 0).unwrap();
         let spec = RecurrenceSpec {
             dtstart,
-            dtstart_type: DateValueType::DateTime,
             tz: "Europe/Berlin".to_string(),
-            rrules: vec!["FREQ=DAILY;UNTIL=20260110".to_string()],
-            rdates: vec![],
-            exrules: vec![],
-            exdates: vec![],
+            rrules: vec!["FREQ=WEEKLY;INTERVAL=2;COUNT=4".to_string()],
+            rdates: vec![rdate],
+            ..spec_fixture()
         };
 
         let findings = lint(&spec, false, false);
-        assert_eq!(findings.errors.len(), 1);
-        assert_eq!(findings.errors[0].code, "E001");
+        assert_eq!(findings.hints.len(), 1);
+        assert_eq!(findings.hints[0].code, "H001");
+        assert_eq!(findings.hints[0].span, Some((12, 22)));
+        let codes: Vec<_> = findings.warnings.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, vec!["W009"]);
+        assert_eq!(findings.warnings[0].span, Some((23, 30)));
+
+        let spec = RecurrenceSpec {
+            rdates: vec![],
+            ..spec
+        };
+        assert!(lint(&spec, false, false).hints.is_empty());
     }
 
     #[test]
-    fn explains_exdate_exclusion() {
+    fn business_calendar_filters_days_and_hours() {
+        let calendar = BusinessCalendar {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            days: BusinessCalendar::parse_days("MO-FR").expect("days"),
+        };
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert!(calendar.contains(at("2026-03-02T09:00:00")));
+        assert!(!calendar.contains(at("2026-03-02T17:00:00")));
+        assert!(!calendar.contains(at("2026-03-07T10:00:00")));
+
+        let overnight = BusinessCalendar {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            ..BusinessCalendar::default()
+        };
+        assert!(overnight.contains(at("2026-03-07T23:30:00")));
+        assert!(overnight.contains(at("2026-03-08T05:59:00")));
+        assert!(!overnight.contains(at("2026-03-08T12:00:00")));
+
+        assert_eq!(
+            BusinessCalendar::parse_days("SA-MO").expect("days"),
+            vec![Weekday::Sat, Weekday::Sun, Weekday::Mon]
+        );
+    }
+
+    #[test]
+    fn merges_rrules_differing_in_one_by_list() {
+        let merged = merge_rrules(&[
+            "FREQ=DAILY;BYHOUR=9;UNTIL=20260331T000000Z".to_string(),
+            "FREQ=DAILY;BYHOUR=17;UNTIL=20260331T000000Z".to_string(),
+        ]);
+        assert_eq!(
+            merged.rrules,
+            vec!["FREQ=DAILY;BYHOUR=9,17;UNTIL=20260331T000000Z".to_string()]
+        );
+        assert_eq!(merged.notes.len(), 1);
+
+        let counted = vec![
+            "FREQ=DAILY;BYHOUR=9;COUNT=5".to_string(),
+            "FREQ=DAILY;BYHOUR=17;COUNT=5".to_string(),
+        ];
+        let merged = merge_rrules(&counted);
+        assert_eq!(merged.rrules, counted);
+        assert!(merged.notes.is_empty());
+
+        let two_parts = vec![
+            "FREQ=WEEKLY;BYDAY=MO;BYHOUR=9".to_string(),
+            "FREQ=WEEKLY;BYDAY=TU;BYHOUR=17".to_string(),
+        ];
+        assert_eq!(merge_rrules(&two_parts).rrules, two_parts);
+    }
+
+    #[test]
+    fn explain_local_date_checks_whole_day() {
         let tz = berlin();
         let dtstart = tz.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
-        let blocked = tz.with_ymd_and_hms(2026, 3, 3, 10, 0, 0).unwrap();
         let spec = RecurrenceSpec {
             dtstart,
-            dtstart_type: DateValueType::DateTime,
             tz: "Europe/Berlin".to_string(),
-            rrules: vec!["FREQ=DAILY;COUNT=5".to_string()],
-            rdates: vec![],
-            exrules: vec![],
-            exdates: vec![blocked],
+            rrules: vec!["FREQ=DAILY;BYHOUR=10,18;COUNT=10".to_string()],
+            ..spec_fixture()
         };
 
-        let result = explain(&spec, blocked).expect("explain");
+        let result = explain_local(&spec, "2026-03-03").expect("explain");
+        assert!(result.included);
+        assert_eq!(result.generated_by, Some(OccurrenceSource::Rrule));
+        assert_eq!(
+            result.day_matches,
+            Some(vec![
+                "2026-03-03T10:00:00".to_string(),
+                "2026-03-03T18:00:00".to_string()
+            ])
+        );
+
+        let result = explain_local(&spec, "2026-04-01").expect("explain");
         assert!(!result.included);
-        assert_eq!(result.excluded_by.as_deref(), Some("EXDATE"));
+        assert_eq!(result.day_matches, Some(vec![]));
     }
 
     #[test]
-    fn parses_minimal_ics() {
-        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=WEEKLY;COUNT=2\nRDATE;TZID=Europe/Berlin:20260310T100000\nEND:VEVENT\nEND:VCALENDAR\n";
+    fn window_helpers() {
+        let tz = berlin();
+        let start = tz.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        let end = tz.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap();
+
+        let window = Window::from_between(start, end).expect("window");
+        assert!(window.contains(start));
+        assert!(window.contains(end));
+        assert!(!window.contains(end + Duration::seconds(1)));
+        assert_eq!(window.duration(), Some(Duration::days(1)));
+        assert!(matches!(
+            Window::from_between(end, start),
+            Err(CoreError::InvalidWindow)
+        ));
+
+        let open = ExpandQuery::After { start, count: 3 }.window();
+        assert!(open.contains(end + Duration::days(365)));
+        assert_eq!(open.duration(), None);
+        assert_eq!(open.meta().start.as_deref(), Some("2026-03-01T00:00:00"));
+    }
+
+    #[test]
+    fn dedupes_ics_rdates_and_hints() {
+        let raw = "BEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20260301T100000\nRRULE:FREQ=WEEKLY;COUNT=2\nRDATE;TZID=Europe/Berlin:20260310T100000,20260311T100000\nRDATE;TZID=Europe/Berlin:20260310T100000\nUID:dup@example.com\nEND:VEVENT\n";
         let spec = parse_ics_spec(raw, None).expect("ics parse");
-        assert_eq!(spec.tz, "Europe/Berlin");
-        assert_eq!(spec.rrules.len(), 1);
-        assert_eq!(spec.rdates.len(), 1);
+        assert_eq!(spec.rdates.len(), 2);
+        assert_eq!(spec.duplicate_rdates.len(), 1);
+
+        let occ = expand(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        let rdate_indices: Vec<usize> = occ
+            .iter()
+            .filter(|o| o.source == OccurrenceSource::Rdate)
+            .map(|o| o.rule_index)
+            .collect();
+        assert_eq!(rdate_indices, vec![0, 1]);
+
+        let findings = lint(&spec, false, false);
+        assert_eq!(findings.hints.len(), 1);
+        assert_eq!(findings.hints[0].code, "H002");
+    }
+
+    #[test]
+    fn renders_rules_in_their_own_timezones() {
+        let tz = berlin();
+        let dtstart = tz.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        let spec = RecurrenceSpec {
+            dtstart,
+            tz: "Europe/Berlin".to_string(),
+            rrules: vec![
+                "FREQ=DAILY;COUNT=1".to_string(),
+                "FREQ=DAILY;BYHOUR=16;COUNT=1".to_string(),
+            ],
+            ..spec_fixture()
+        };
+
+        let mut result = expand_result(&spec, &ExpandQuery::Unbounded, 100).expect("expand");
+        let zones = BTreeMap::from([(1, "America/New_York".to_string())]);
+        apply_rule_timezones(&mut result, &zones).expect("zones");
+
+        assert_eq!(result.occurrences[0].start_local, "2026-03-02T10:00:00");
+        assert_eq!(result.occurrences[0].tz.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(result.occurrences[1].start_local, "2026-03-02T10:00:00");
+        assert_eq!(result.occurrences[1].start_utc, "2026-03-02T15:00:00Z");
+        assert_eq!(
+            result.occurrences[1].tz.as_deref(),
+            Some("America/New_York")
+        );
+
+        let zones = BTreeMap::from([(2, "America/New_York".to_string())]);
+        let err = apply_rule_timezones(&mut result, &zones).expect_err("index past the rules");
+        assert!(matches!(
+            err,
+            CoreError::InvalidRuleIndex {
+                index: 2,
+                rule_count: 2
+            }
+        ));
+        assert!(err.to_string().contains("no RRULE with index 2"));
+        assert_eq!(err.code(), "INVALID_RULE_INDEX");
+    }
+
+    #[test]
+    fn window_links_step_by_month_or_duration() {
+        let tz = berlin();
+        let month = Window::from_between(
+            tz.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap(),
+            tz.with_ymd_and_hms(2026, 3, 31, 23, 59, 59).unwrap(),
+        )
+        .expect("window");
+        let next = month.next().expect("next").meta();
+        assert_eq!(next.start.as_deref(), Some("2026-04-01T00:00:00"));
+        assert_eq!(next.end.as_deref(), Some("2026-04-30T23:59:59"));
+        let prev = month.prev().expect("prev").meta();
+        assert_eq!(prev.start.as_deref(), Some("2026-02-01T00:00:00"));
+        assert_eq!(prev.end.as_deref(), Some("2026-02-28T23:59:59"));
+
+        let hours = Window::from_between(
+            tz.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap(),
+            tz.with_ymd_and_hms(2026, 3, 2, 10, 59, 59).unwrap(),
+        )
+        .expect("window");
+        let next = hours.next().expect("next").meta();
+        assert_eq!(next.start.as_deref(), Some("2026-03-02T11:00:00"));
+        assert_eq!(next.end.as_deref(), Some("2026-03-02T12:59:59"));
+
+        // Berlin springs forward on 2026-03-29; steps keep local lengths.
+        let day = Window::from_between(
+            tz.with_ymd_and_hms(2026, 3, 28, 0, 0, 0).unwrap(),
+            tz.with_ymd_and_hms(2026, 3, 28, 23, 59, 59).unwrap(),
+        )
+        .expect("window");
+        let next = day.next().expect("next").meta();
+        assert_eq!(next.start.as_deref(), Some("2026-03-29T00:00:00"));
+        assert_eq!(next.end.as_deref(), Some("2026-03-29T23:59:59"));
+        let week = ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 3, 23, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 3, 30, 0, 0, 0).unwrap(),
+            inclusive_end: false,
+            count: None,
+        };
+        let links = window_links(&week).expect("links");
+        assert_eq!(
+            links.prev.between,
+            ["2026-03-16T00:00:00", "2026-03-23T00:00:00"]
+        );
+        assert_eq!(
+            links.next.between,
+            ["2026-03-30T00:00:00", "2026-04-06T00:00:00"]
+        );
+
+        // A count can cut the window short, so there is no next window.
+        let counted = ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 3, 23, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 3, 30, 0, 0, 0).unwrap(),
+            inclusive_end: false,
+            count: Some(3),
+        };
+        assert!(window_links(&counted).is_none());
+    }
+
+    #[test]
+    fn exclusive_end_leaves_the_boundary_instant_out() {
+        let raw = r#"{"dtstart": "2026-03-01T00:00:00", "rrule": ["FREQ=MONTHLY;COUNT=3"]}"#;
+        let spec = parse_json_spec(raw, Some("Europe/Berlin")).expect("json parse");
+        let tz = berlin();
+        let march = |inclusive_end| ExpandQuery::Between {
+            start: tz.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap(),
+            end: tz.with_ymd_and_hms(2026, 4, 1, 0, 0, 0).unwrap(),
+            inclusive_end,
+            count: None,
+        };
+
+        let closed = expand_result(&spec, &march(true), 10).expect("expand");
+        assert_eq!(closed.occurrences.len(), 2);
+        assert!(!closed.meta.window.end_exclusive);
+
+        let half_open = expand_result(&spec, &march(false), 10).expect("expand");
+        assert_eq!(half_open.occurrences.len(), 1);
+        assert!(half_open.meta.window.end_exclusive);
+        let links = half_open.meta.links.expect("links");
+        assert_eq!(
+            links.next.between,
+            ["2026-04-01T00:00:00", "2026-05-01T00:00:00"]
+        );
+        assert_eq!(
+            links.prev.between,
+            ["2026-02-01T00:00:00", "2026-03-01T00:00:00"]
+        );
+
+        // Instants are whole seconds, so nothing can fall between the last
+        // second of a half-open window and its end.
+        let raw = r#"{"dtstart": "2026-03-01T00:00:00", "rrule": ["FREQ=MONTHLY;COUNT=1"], "rdate": ["2026-03-31T23:59:59.5+02:00"]}"#;
+        let err = parse_json_spec(raw, Some("Europe/Berlin")).expect_err("fractional second");
+        assert!(matches!(err, CoreError::InvalidDateTime { .. }));
+        assert!(err.to_string().contains("fractional seconds"));
     }
 }
@@ -0,0 +1,6 @@
+{
+  "dtstart": "2026-03-01T10:00:00",
+  "tz": "Europe/Berlin",
+  "rrule": ["FREQ=DAILY;COUNT=4"],
+  "exdate": ["2026-03-02T10:00:00"]
+}